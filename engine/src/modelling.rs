@@ -7,6 +7,8 @@ pub use cubic::{
     SHADOW_SHADER_MAX_LIGHTS,
     ShadowGroup,
     Skeleton,
+    WeightedBlended,
+    WeightedBlendedShaders,
 };
 
 mod quad;
@@ -17,5 +19,7 @@ mod bloom;
 pub use bloom::{Bloom, BloomGroup};
 mod simple_vertex;
 pub use simple_vertex::SimpleVertex;
+mod skinned_vertex;
+pub use skinned_vertex::{MAX_JOINTS, SkinnedVertex};
 
 pub mod test_models;
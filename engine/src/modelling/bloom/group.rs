@@ -29,12 +29,14 @@ impl<'a, X: FramebufferWithoutExtra<1, Tex = FlatTexture>> Draw for Group<'a, X>
     ) -> Result<()> {
         let Group { bloom, output_fb } = *self;
 
+        register.profile_section("blur x");
         let mut active_framebuffer_x = bloom.framebuffer_x.bind(register);
         let active_blur_x = opengl_shaders::bloom_x().use_program(marker);
         bloom
             .to_blur
             .draw(active_blur_x, &mut active_framebuffer_x)?;
 
+        register.profile_section("blur y");
         let mut active_output_fb = output_fb.bind(register);
         let active_blur_y = opengl_shaders::bloom_y().use_program(marker);
 
@@ -43,4 +45,8 @@ impl<'a, X: FramebufferWithoutExtra<1, Tex = FlatTexture>> Draw for Group<'a, X>
 
         Ok(())
     }
+
+    fn name(&self) -> String {
+        "bloom".to_string()
+    }
 }
@@ -3,11 +3,13 @@ pub use group::Group as CubicGroup;
 mod shadow;
 pub use shadow::{Group as ShadowGroup, SHADOW_SHADER_MAX_LIGHTS};
 mod model;
-pub use model::{Cubic, Mesh};
+pub use model::{Cubic, Mesh, Vertices};
 mod import;
 pub use import::Error as ImportError;
 mod builder;
 pub use builder::Builder;
+mod weighted_blended;
+pub use weighted_blended::{Shaders as WeightedBlendedShaders, WeightedBlended};
 
 mod skeleton;
 pub use skeleton::{Bone, Skeleton};
@@ -1,25 +1,43 @@
-use graphics::framebuffer::FramebufferContext;
+use graphics::framebuffer::attachments::WithDepth;
 use graphics::framebuffer::traits::FramebufferWithDepth;
+use graphics::framebuffer::{Framebuffer, FramebufferContext, Viewport};
 use graphics::linear_algebra::{Matrix, Vector};
-use graphics::shader_program::{ShaderProgram, ShaderProgramContext};
+use graphics::shader_program::{
+    ActiveShaderProgram,
+    RenderState,
+    ShaderProgram,
+    ShaderProgramContext,
+};
 use graphics::texture::FlatTexture;
 use graphics::{Draw, Result};
 
 use super::Camera;
 use super::geometry::YieldsPose;
 use super::lighting::simple::ListLights;
-use super::model::Cubic;
+use super::model::{Cubic, Layer, Pass, Translucent, back_to_front};
+use super::weighted_blended::{Shaders as WeightedBlendedShaders, WeightedBlended};
 
 #[derive(Debug)]
 pub struct Group<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT>> {
     shader: &'a ShaderProgram<(Cubic, ListLights<MAX>), OUT, D::Tex>,
+    skinned_shader: Option<&'a ShaderProgram<(Cubic, ListLights<MAX>), OUT, D::Tex>>,
     framebuffer: &'a D,
 
     camera_pos: Vector<3>,
     camera_look_at: Matrix<4, 4>,
 
     lights: &'a ListLights<MAX>,
+    /// Their translucent meshes are drawn with `transparent`
     opaque: Vec<(&'a Cubic, usize /* animation */, f32 /* time */)>,
+    /// Every mesh drawn as translucent, whatever its material
+    transparent: Vec<(&'a Cubic, usize /* animation */, f32 /* time */)>,
+    /// With `framebuffer` again, as the `Framebuffer` whose depth is copied
+    weighted_blended: Option<(
+        &'a WeightedBlended,
+        WeightedBlendedShaders<'a, MAX, OUT>,
+        &'a Framebuffer<OUT, WithDepth>,
+    )>,
+    viewport: Option<Viewport>,
 }
 
 impl<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT, Tex = FlatTexture>>
@@ -39,16 +57,105 @@ impl<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT, Tex =
     {
         Box::new(Self {
             shader,
+            skinned_shader: None,
             framebuffer,
             camera_pos: camera.position(hint.clone()),
             camera_look_at: camera.look_at(hint),
             lights,
             opaque,
+            transparent: Vec::new(),
+            weighted_blended: None,
+            viewport: None,
         })
     }
+
+    /// See `Viewport`
+    pub fn viewport(mut self: Box<Self>, viewport: Viewport) -> Box<Self> {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    /// The variant of the shader built with `SKINNED`, such as
+    /// `opengl_shaders::hdr_skinned`. Without it, skinned meshes are drawn
+    /// in their bind pose.
+    pub fn skinned_shader(
+        mut self: Box<Self>,
+        shader: &'a ShaderProgram<(Cubic, ListLights<MAX>), OUT, FlatTexture>,
+    ) -> Box<Self> {
+        self.skinned_shader = Some(shader);
+        self
+    }
+
+    /// Models drawn entirely as translucent, sorted with the translucent
+    /// meshes of the opaque ones
+    pub fn transparent(
+        mut self: Box<Self>,
+        transparent: Vec<(&'a Cubic, usize /* animation */, f32 /* time */)>,
+    ) -> Box<Self> {
+        self.transparent = transparent;
+        self
+    }
+
+    fn bind<const N: usize>(
+        &self,
+        active_shader: &ActiveShaderProgram<'_, '_, '_, (Cubic, ListLights<MAX>), FlatTexture, N>,
+    ) {
+        self.lights.bind(active_shader);
+
+        active_shader.set_uniform("projtimesview".to_string(), self.camera_look_at);
+        active_shader.set_uniform("camera_postion".to_string(), self.camera_pos.homogeneous());
+    }
+
+    /// Draw `translucent` in order into `framebuffer`, with a skinned shader
+    /// optional as for the group, in the state `render_state` makes from each
+    /// program's own
+    fn draw_translucent<'b, const N: usize, F: FramebufferWithDepth<N, Tex = FlatTexture>>(
+        &'b self,
+        fb_context: &mut FramebufferContext,
+        sp_context: &mut ShaderProgramContext,
+        translucent: &[Translucent<'b>],
+        (shader, skinned_shader): (
+            &ShaderProgram<(Cubic, ListLights<MAX>), N, FlatTexture>,
+            Option<&ShaderProgram<(Cubic, ListLights<MAX>), N, FlatTexture>>,
+        ),
+        framebuffer: &F,
+        render_state: fn(RenderState) -> RenderState,
+    ) -> Result<()> {
+        // Runs of meshes needing the same shader share one use of it
+        for run in translucent.chunk_by(|a, b| a.is_skinned() == b.is_skinned()) {
+            let (shader, pass) = match skinned_shader {
+                Some(skinned_shader) if run[0].is_skinned() => (skinned_shader, Pass::Skinned),
+                Some(_) => (shader, Pass::Rigid),
+                None => (shader, Pass::All),
+            };
+            let mut active_shader = shader.use_program(sp_context);
+            active_shader.render_state(render_state(shader.render_state()));
+            let mut active_framebuffer = framebuffer.bind_viewport(fb_context, self.viewport);
+            self.bind(&active_shader);
+
+            for mesh in run {
+                mesh.draw(&mut active_shader, &mut active_framebuffer, pass)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a, const MAX: usize, const OUT: usize> Group<'a, MAX, OUT, Framebuffer<OUT, WithDepth>> {
+    /// Blend translucent meshes in any order into the targets of
+    /// `weighted_blended` with `shaders` rather than sorting them
+    pub fn weighted_blended(
+        mut self: Box<Self>,
+        weighted_blended: &'a WeightedBlended,
+        shaders: WeightedBlendedShaders<'a, MAX, OUT>,
+    ) -> Box<Self> {
+        self.weighted_blended = Some((weighted_blended, shaders, self.framebuffer));
+        self
+    }
 }
 
-impl<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT>> Draw
+impl<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT, Tex = FlatTexture>> Draw
     for Group<'a, MAX, OUT, D>
 {
     fn draw(
@@ -56,19 +163,67 @@ impl<'a, const MAX: usize, const OUT: usize, D: FramebufferWithDepth<OUT>> Draw
         fb_context: &mut FramebufferContext,
         sp_context: &mut ShaderProgramContext,
     ) -> Result<()> {
-        let mut active_shader = self.shader.use_program(sp_context);
-        let mut active_framebuffer = self.framebuffer.bind(fb_context);
+        let skinned = self.opaque.iter().any(|(model, ..)| model.is_skinned());
+        let passes = match self.skinned_shader {
+            Some(skinned_shader) if skinned => {
+                vec![(self.shader, Pass::Rigid), (skinned_shader, Pass::Skinned)]
+            }
+            _ => vec![(self.shader, Pass::All)],
+        };
 
-        self.lights.bind(&active_shader);
-        let camera_pos = self.camera_pos;
+        for (shader, pass) in passes {
+            let mut active_shader = shader.use_program(sp_context);
+            let mut active_framebuffer = self.framebuffer.bind_viewport(fb_context, self.viewport);
+            self.bind(&active_shader);
 
-        active_shader.set_uniform("projtimesview".to_string(), self.camera_look_at);
-        active_shader.set_uniform("camera_postion".to_string(), camera_pos.homogeneous());
+            for &(model, animation, time) in &self.opaque {
+                model.draw(
+                    &mut active_shader,
+                    &mut active_framebuffer,
+                    pass,
+                    Layer::Opaque,
+                    animation,
+                    time,
+                )?;
+            }
+        }
 
-        for (model, animation, time) in self.opaque {
-            model.draw(&mut active_shader, &mut active_framebuffer, animation, time)?;
+        let mut translucent = back_to_front(&self.opaque, &self.transparent, self.camera_pos);
+        let Some((weighted_blended, shaders, scene)) = self.weighted_blended else {
+            return self.draw_translucent(
+                fb_context,
+                sp_context,
+                &translucent,
+                (self.shader, self.skinned_shader),
+                self.framebuffer,
+                |state| state.depth_write(false),
+            );
+        };
+        if translucent.is_empty() {
+            return Ok(());
         }
 
-        Ok(())
+        // Any order blends the same, so only the shaders matter
+        translucent.sort_by_key(Translucent::is_skinned);
+        weighted_blended.begin(fb_context, scene, self.viewport);
+        self.draw_translucent(
+            fb_context,
+            sp_context,
+            &translucent,
+            (shaders.shader, shaders.skinned_shader),
+            weighted_blended.framebuffer(),
+            WeightedBlended::render_state,
+        )?;
+        weighted_blended.composite(
+            fb_context,
+            sp_context,
+            shaders.composite,
+            self.framebuffer,
+            self.viewport,
+        )
+    }
+
+    fn name(&self) -> String {
+        "cubic".to_string()
     }
 }
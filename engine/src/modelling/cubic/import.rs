@@ -1,20 +1,26 @@
-use std::collections::HashMap;
+use std::array;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::identity;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use graphics::colour::{ColourRGB, ColourRGBA};
 use graphics::linear_algebra::{UnitVector, Vector};
 use graphics::texture::{FlatTexture, TextureHasBuilder};
 use graphics::types::{ElementArrayElem};
-use graphics::vertex::IncompleteVertex;
+use graphics::vertex::{IncompleteVertex, Vertex};
 use graphics::vertex_array::VertexArray;
+use russimp::Matrix4x4;
 use russimp::material::{PropertyTypeInfo, TextureType};
 use russimp::node::Node;
 use russimp::scene::{PostProcess, Scene};
 
-use super::material::Material;
+use super::geometry::Pose;
+use super::material::{Material, Shading};
 use super::model::{Cubic, Mesh};
+use super::{Bone, Skeleton};
 use crate::error::Result;
+use crate::modelling::{MAX_JOINTS, SimpleVertex, SkinnedVertex};
 
 mod error {
     use std::path::PathBuf;
@@ -43,6 +49,16 @@ mod error {
             dir: PathBuf,
         },
         ShouldNotOccur,
+        NoPositions {
+            mesh: String,
+        },
+        NotTriangles {
+            mesh: String,
+        },
+        UnsupportedImageFormat {
+            image: usize,
+            format: String,
+        },
         ElementArrayOverflow {
             which: &'static str,
             index_asked: usize,
@@ -72,6 +88,19 @@ pub use error::Error;
 
 use super::Builder;
 
+pub(super) mod gltf;
+mod keyframes;
+use keyframes::{
+    Clip,
+    NodeTracks,
+    Track,
+    bone_animations,
+    is_scaled,
+    matrix_scale,
+    pose_from_matrix,
+    trs_from_matrix,
+};
+
 pub(super) fn import<P: AsRef<Path>>(path: P, post_process: Vec<PostProcess>) -> Result<Builder> {
     let path = path.as_ref();
 
@@ -89,24 +118,191 @@ pub(super) fn import<P: AsRef<Path>>(path: P, post_process: Vec<PostProcess>) ->
             as_text: error.to_string(),
         })?;
 
-    let out = process_node(
-        &scene,
-        scene
-            .root
-            .as_ref()
-            .ok_or_else(|| Error::NoRootNode { path: path.into() })?,
-        dir,
-        &mut HashMap::new(),
-    )?;
-    Ok(Cubic::builder().meshes(out))
+    let root = scene
+        .root
+        .as_ref()
+        .ok_or_else(|| Error::NoRootNode { path: path.into() })?;
+
+    if has_scale(&scene, root) {
+        log::warn!(
+            "{}: scale is dropped, so the model may be the wrong size or skin wrongly",
+            path.display()
+        );
+    }
+
+    let inverse_bind_poses = inverse_bind_poses(&scene);
+    let clips = clips(&scene);
+
+    let mut skeleton = Skeleton::new();
+    let mut bones = HashMap::new();
+    build_skeleton(
+        root,
+        0,
+        &inverse_bind_poses,
+        &clips,
+        &mut skeleton,
+        &mut bones,
+    );
+
+    let out = process_node(&scene, root, dir, &bones, &mut 1, &mut HashMap::new())?;
+    Ok(Cubic::builder()
+        .meshes(out)
+        .skeleton(skeleton)
+        .relative(!inverse_bind_poses.is_empty()))
+}
+
+/// Assimp's row-major matrix as columns
+fn columns(matrix: &Matrix4x4) -> [[f32; 4]; 4] {
+    let m = matrix;
+    [
+        [m.a1, m.b1, m.c1, m.d1],
+        [m.a2, m.b2, m.c2, m.d2],
+        [m.a3, m.b3, m.c3, m.d3],
+        [m.a4, m.b4, m.c4, m.d4],
+    ]
+}
+
+/// Whether `node` or any below it, bone offset or animation channel scales
+fn has_scale(scene: &Scene, node: &Node) -> bool {
+    fn node_scaled(node: &Node) -> bool {
+        let children = node.children.borrow();
+        is_scaled(matrix_scale(columns(&node.transformation)))
+            || children.iter().any(|child| node_scaled(child))
+    }
+
+    let bones = (scene.meshes.iter().flat_map(|mesh| &mesh.bones))
+        .any(|bone| is_scaled(matrix_scale(columns(&bone.offset_matrix))));
+    let channels = (scene.animations.iter())
+        .flat_map(|animation| &animation.channels)
+        .flat_map(|channel| &channel.scaling_keys)
+        .any(|key| is_scaled([key.value.x, key.value.y, key.value.z]));
+
+    node_scaled(node) || bones || channels
+}
+
+/// By bone name, which is also the name of the node it follows. A bone in
+/// several meshes keeps its first offset.
+fn inverse_bind_poses(scene: &Scene) -> HashMap<String, Pose> {
+    let mut out = HashMap::new();
+
+    for bone in scene.meshes.iter().flat_map(|mesh| &mesh.bones) {
+        out.entry(bone.name.clone())
+            .or_insert_with(|| pose_from_matrix(columns(&bone.offset_matrix)));
+    }
+
+    out
 }
 
+fn clips(scene: &Scene) -> Vec<Clip<String>> {
+    scene
+        .animations
+        .iter()
+        .map(|animation| {
+            // Assimp leaves this 0 when the file does not say
+            let ticks_per_second = if animation.ticks_per_second > 0.0 {
+                animation.ticks_per_second
+            } else {
+                25.0
+            };
+            let seconds = |ticks: f64| (ticks / ticks_per_second) as f32;
+
+            let nodes = animation.channels.iter().map(|channel| {
+                let (times, values) = (channel.position_keys.iter())
+                    .map(|key| (seconds(key.time), [key.value.x, key.value.y, key.value.z]))
+                    .unzip();
+                let translation = Track::new(times, values, false);
+
+                let (times, values) = (channel.rotation_keys.iter())
+                    .map(|key| {
+                        let value = &key.value;
+                        (seconds(key.time), [value.x, value.y, value.z, value.w])
+                    })
+                    .unzip();
+                let rotation = Track::new(times, values, false);
+
+                let tracks = NodeTracks {
+                    translation,
+                    rotation,
+                };
+                (channel.name.clone(), tracks)
+            });
+
+            Clip {
+                duration: seconds(animation.duration),
+                nodes: nodes.collect(),
+            }
+        })
+        .collect()
+}
+
+/// Push a bone for every node, parents first, so that each keeps its
+/// transform as a pose and can be animated. Records the first bone of each
+/// name, as mesh bones name the node they follow.
+fn build_skeleton(
+    node: &Node,
+    parent: usize,
+    inverse_bind_poses: &HashMap<String, Pose>,
+    clips: &[Clip<String>],
+    skeleton: &mut Skeleton,
+    bones: &mut HashMap<String, usize>,
+) {
+    let rest = trs_from_matrix(columns(&node.transformation));
+    let mut builder = Bone::builder(parent);
+    if let Some(&inverse_bind_pose) = inverse_bind_poses.get(&node.name) {
+        builder = builder.inverse_bind_pose(inverse_bind_pose);
+    }
+    let animations = bone_animations(clips, &node.name, rest);
+
+    let Some(bone) = skeleton.push_bone(builder.all_animations(animations.into()).build()) else {
+        return;
+    };
+    bones.entry(node.name.clone()).or_insert(bone);
+
+    for child in node.children.borrow().iter() {
+        build_skeleton(child, bone, inverse_bind_poses, clips, skeleton, bones);
+    }
+}
+
+/// The joint with the most weight over the triangle's vertices
+fn dominant_joint(
+    influences: &[Vec<(usize, f32)>],
+    triangle: &[ElementArrayElem; 3],
+) -> Option<usize> {
+    let mut totals: Vec<(usize, f32)> = Vec::new();
+
+    for &(joint, weight) in triangle
+        .iter()
+        .filter_map(|index| influences.get(index.as_usize()))
+        .flatten()
+    {
+        let total = totals
+            .iter_mut()
+            .find(|(total_joint, _)| *total_joint == joint);
+        match total {
+            Some((_, total)) => *total += weight,
+            None => totals.push((joint, weight)),
+        }
+    }
+
+    totals
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(joint, _)| joint)
+}
+
+/// `next_bone` counts the nodes in the order `build_skeleton` pushed their
+/// bones, as node names need not be unique
 fn process_node(
     scene: &Scene,
     node: &Node,
     dir: &Path,
+    bones: &HashMap<String, usize>,
+    next_bone: &mut usize,
     current_mat: &mut HashMap<u32, Result<Rc<Material>>>,
 ) -> Result<Vec<Mesh>> {
+    let node_bone = *next_bone;
+    *next_bone += 1;
+
     // process current node and recursively process each in node.children.
     let mut curr_meshes = node
         .meshes
@@ -152,7 +348,25 @@ fn process_node(
                 .flat_map(|face| face.0.iter().copied().map(Into::into))
                 .collect();
 
-            let mut vertex_array_builder = VertexArray::builder();
+            // The skeleton bones the mesh's bones follow, and each vertex's
+            // weight on each of them by their index in `joints`
+            let mut joints = Vec::new();
+            let mut influences = vec![Vec::new(); positions.len()];
+            for bone in &mesh.bones {
+                let Some(&index) = bones.get(&bone.name) else {
+                    continue;
+                };
+                for weight in &bone.weights {
+                    if let Some(vertex) = influences.get_mut(weight.vertex_id as usize) {
+                        vertex.push((joints.len(), weight.weight));
+                    }
+                }
+                joints.push(index);
+            }
+            let skinned = !joints.is_empty() && joints.len() <= MAX_JOINTS;
+
+            let mut skinned_builder = VertexArray::<SkinnedVertex>::builder();
+            let mut vertex_array_builders = BTreeMap::new();
 
             fn is_no_vec_or_element_exists<'a, T>(
                 name: &'static str,
@@ -204,10 +418,24 @@ fn process_node(
 
                 let transpose = incomplete_triangle.try_map(identity)?;
 
-                vertex_array_builder.push_incomplete_triangle(&transpose);
-            }
+                if skinned {
+                    let vertices = SimpleVertex::from_incomplete_triangle(&transpose);
+                    skinned_builder.push_triangle(array::from_fn(|i| {
+                        let influences = influences.get(triangle[i].as_usize());
+                        SkinnedVertex::new(vertices[i], influences.map_or(&[][..], Vec::as_slice))
+                    }));
+                    continue;
+                }
 
-            let vertex_array = vertex_array_builder.build();
+                // Too many joints to skin, so each triangle follows whichever
+                // bone holds most of its weight
+                let bone =
+                    dominant_joint(&influences, triangle).map_or(node_bone, |joint| joints[joint]);
+                vertex_array_builders
+                    .entry(bone)
+                    .or_insert_with(VertexArray::<SimpleVertex>::builder)
+                    .push_incomplete_triangle(&transpose);
+            }
 
             // let mut vertex_array_builder = VertexArray::cubic_builder()
             // .position(vertices)
@@ -228,16 +456,34 @@ fn process_node(
                 .or_insert_with_key(|key| parse_material(&scene.materials[*key as usize], dir))
                 .clone()?;
 
-            Result::Ok(Mesh::new(Rc::new(vertex_array), mat, 0)) // TODO: bone
-            // set to 0 for
-            // debug
+            if skinned {
+                let vertex_array = skinned_builder.build();
+                vertex_array.set_label(&format!("{}: {}", dir.display(), mesh.name));
+                let mesh = Mesh::skinned(Rc::new(vertex_array), joints.into(), mat)
+                    .named(&node.name, &mesh.name)
+                    .centred(&positions);
+                return Result::Ok(vec![mesh]);
+            }
+
+            let meshes = vertex_array_builders.into_iter().map(|(bone, builder)| {
+                let vertex_array = builder.build();
+                vertex_array.set_label(&format!("{}: {} bone {bone}", dir.display(), mesh.name));
+                Mesh::new(Rc::new(vertex_array), mat.clone(), bone)
+                    .named(&node.name, &mesh.name)
+                    .centred(&positions)
+            });
+
+            Result::Ok(meshes.collect::<Vec<_>>())
         })
-        .collect::<Result<Vec<_>>>()?;
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
 
     let children = node.children.borrow();
     let child_meshes = children
         .iter()
-        .map(|node| process_node(scene, node, dir, current_mat))
+        .map(|node| process_node(scene, node, dir, bones, next_bone, current_mat))
         .collect::<Result<Vec<_>>>()?
         .into_iter()
         .flatten();
@@ -261,46 +507,144 @@ fn parse_material(material: &russimp::material::Material, dir: &Path) -> Result<
             )?;
     }
 
+    let texture_path = |semantic: TextureType| match material_properties
+        .get(&semantic)
+        .and_then(|properties| properties.get("$tex.file"))
+    {
+        Some(PropertyTypeInfo::String(filepath)) => {
+            Some([dir, filepath.as_ref()].into_iter().collect::<PathBuf>())
+        }
+        _ => None,
+    };
+    // A missing or unreadable texture leaves its own channel at the default
+    // instead of failing the whole model
+    let load = |semantic: TextureType, srgb: bool| {
+        let path = texture_path(semantic)?;
+        let builder = if srgb {
+            FlatTexture::builder().srgba_image(&path)
+        } else {
+            FlatTexture::builder().rgba_image(&path)
+        };
+        builder
+            .inspect_err(|error| log::warn!("{}: left out: {error:?}", path.display()))
+            .ok()
+            .map(|builder| builder.build())
+    };
+    let property = |key: &str| {
+        material_properties
+            .get(&TextureType::None)
+            .and_then(|properties| properties.get(key))
+    };
+    let floats = |key: &str| match property(key) {
+        Some(PropertyTypeInfo::FloatArray(values)) => Some(values.as_slice()),
+        _ => None,
+    };
+
     let mut builder = Material::builder();
 
-    if let Some(Some(PropertyTypeInfo::String(filepath))) = material_properties
-        .get(&TextureType::Diffuse)
-        .map(|x| x.get("$tex.file"))
-    {
-        builder = builder.diffuse(
-            FlatTexture::builder()
-                .srgba_image([dir, filepath.as_ref()].into_iter().collect::<PathBuf>())
-                .map_err(|error| Error::Graphics { error })?
-                .build(),
-        );
+    // Assimp reports the base colour of PBR formats apart from the diffuse
+    let diffuse = load(TextureType::Diffuse, true).or_else(|| load(TextureType::BaseColor, true));
+    // A diffuse colour alongside a diffuse texture is usually only an
+    // exporter's default, so it only colours untextured materials
+    let base_colour =
+        floats("$clr.base").or_else(|| floats("$clr.diffuse").filter(|_| diffuse.is_none()));
+    let opacity = floats("$mat.opacity").and_then(|values| values.first().copied());
+
+    if let Some(diffuse) = diffuse {
+        builder = builder.diffuse(diffuse);
+    } else if base_colour.is_some() {
+        builder = builder.diffuse(FlatTexture::white());
     }
-    if let Some(Some(PropertyTypeInfo::String(filepath))) = material_properties
-        .get(&TextureType::Specular)
-        .map(|x| x.get("$tex.file"))
-    {
-        builder = builder.specular(
-            FlatTexture::builder()
-                .srgba_image([dir, filepath.as_ref()].into_iter().collect::<PathBuf>())
-                .map_err(|error| Error::Graphics { error })?
-                .build(),
-        );
+    if base_colour.is_some() || opacity.is_some() {
+        let [r, g, b, a] = match base_colour.unwrap_or_default() {
+            &[r, g, b, a, ..] => [r, g, b, a],
+            &[r, g, b] => [r, g, b, 1.0],
+            _ => [1.0; 4],
+        };
+        builder = builder.diffuse_colour(ColourRGBA::new([r, g, b, a * opacity.unwrap_or(1.0)]));
     }
-    if let Some(Some(PropertyTypeInfo::String(filepath))) = material_properties
-        .get(&TextureType::Height)
-        .map(|x| x.get("$tex.file"))
-    {
-        builder = builder.normal_map(
-            FlatTexture::builder()
-                .rgba_image([dir, filepath.as_ref()].into_iter().collect::<PathBuf>())
-                .map_err(|error| Error::Graphics { error })?
-                .build(),
-        );
+
+    if let Some(specular) = load(TextureType::Specular, true) {
+        builder = builder.specular(specular);
+    }
+
+    let emission =
+        load(TextureType::Emissive, true).or_else(|| load(TextureType::EmissionColor, true));
+    let emission_colour = match floats("$clr.emissive") {
+        Some(&[r, g, b, ..]) => Some(ColourRGB::new([r, g, b])),
+        _ => None,
+    };
+    if let Some(emission) = emission {
+        builder = builder.emission(emission);
+    } else if emission_colour.is_some_and(|colour| colour.as_array() != [0.0; 3]) {
+        builder = builder.emission(FlatTexture::white());
+    }
+    if let Some(emission_colour) = emission_colour {
+        builder = builder.emission_colour(emission_colour);
     }
-    if let Some(Some(PropertyTypeInfo::FloatArray(arr))) = material_properties
-        .get(&TextureType::None)
-        .map(|x| x.get("$mat.shininess"))
-        && let Some(number) = arr.first() {
-            builder = builder.shininess(*number);
+
+    let ambient_occlusion =
+        load(TextureType::AmbientOcclusion, false).or_else(|| load(TextureType::LightMap, false));
+    if let Some(ambient_occlusion) = ambient_occlusion {
+        builder = builder.ambient_occlusion(ambient_occlusion);
+    }
+
+    let opacity_map = load(TextureType::Opacity, false);
+    builder =
+        builder.translucent(opacity_map.is_some() || opacity.is_some_and(|opacity| opacity < 1.0));
+    if let Some(opacity_map) = opacity_map {
+        builder = builder.opacity_map(opacity_map);
+    }
+
+    // Without a normal map, one is made from the height map. OBJ's `map_Bump`
+    // is read as a height map, but is often a normal map already.
+    let normal_map = load(TextureType::Normals, false).or_else(|| {
+        let path = texture_path(TextureType::Height)?;
+        FlatTexture::builder()
+            .normal_image_from_height(&path, 2.0)
+            .inspect_err(|error| log::warn!("{}: left out: {error:?}", path.display()))
+            .ok()
+            .map(|builder| builder.build())
+    });
+    if let Some(normal_map) = normal_map {
+        builder = builder.normal_map(normal_map);
+    }
+
+    let two_sided = match property("$mat.twosided") {
+        Some(PropertyTypeInfo::IntegerArray(values)) => {
+            values.first().is_some_and(|&value| value != 0)
+        }
+        Some(PropertyTypeInfo::Buffer(bytes)) => bytes.iter().any(|&byte| byte != 0),
+        _ => false,
+    };
+    builder = builder.two_sided(two_sided);
+
+    if let Some(&[shininess, ..]) = floats("$mat.shininess") {
+        builder = builder.shininess(shininess);
+    }
+
+    // Only PBR formats have either factor
+    let metallic = floats("$mat.metallicFactor").and_then(|values| values.first().copied());
+    let roughness = floats("$mat.roughnessFactor").and_then(|values| values.first().copied());
+    if metallic.is_some() || roughness.is_some() {
+        builder = builder
+            .shading(Shading::MetallicRoughness)
+            .metallic(metallic.unwrap_or(1.0))
+            .roughness(roughness.unwrap_or(1.0));
+
+        // glTF packs both into one image, which assimp reports under each
+        // semantic or as an unknown texture. Separate images are not packed
+        // the way the shaders read them, so those are left out.
+        let metalness_path = texture_path(TextureType::Metalness);
+        let packed =
+            if metalness_path.is_some() && metalness_path == texture_path(TextureType::Roughness) {
+                load(TextureType::Metalness, false)
+            } else {
+                load(TextureType::Unknown, false)
+            };
+        if let Some(metallic_roughness) = packed {
+            builder = builder.metallic_roughness(metallic_roughness);
+        }
     }
 
     Ok(Rc::new(builder.build()))
@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::{array, mem};
+
+use ::gltf::animation::Interpolation;
+use ::gltf::animation::util::ReadOutputs;
+use ::gltf::image::{self, Format};
+use ::gltf::material::AlphaMode;
+use ::gltf::mesh::{Mode, Primitive};
+use ::gltf::{Document, Node, Texture, buffer};
+use graphics::colour::{ColourRGB, ColourRGBA};
+use graphics::linear_algebra::{UnitVector, Vector};
+use graphics::texture::{FlatTexture, TextureHasBuilder};
+use graphics::vertex::{IncompleteVertex, Vertex};
+use graphics::vertex_array::VertexArray;
+
+use super::Error;
+use super::keyframes::{Clip, Track, bone_animations, is_scaled, matrix_scale, pose_from_matrix};
+use crate::error::Result;
+use crate::modelling::cubic::geometry::Pose;
+use crate::modelling::cubic::material::{Material, Shading};
+use crate::modelling::cubic::model::{Cubic, Mesh};
+use crate::modelling::cubic::{Bone, Builder, Skeleton};
+use crate::modelling::{MAX_JOINTS, SimpleVertex, SkinnedVertex};
+
+/// Load a glTF 2.0 or GLB file. Every node becomes a bone under the
+/// skeleton's root, so meshes follow their node and each glTF animation is
+/// the animation of the same index on every bone. Skin joints take their
+/// inverse bind poses from the skin, and skinned meshes blend up to four of
+/// them per vertex. A skin of more than `MAX_JOINTS` joints leaves its meshes
+/// in their bind pose on the root instead.
+///
+/// `Pose` has no scale, so scale is dropped from nodes, inverse bind matrices
+/// and animation channels alike, with a warning when there was any.
+pub(in crate::modelling::cubic) fn import<P: AsRef<Path>>(path: P) -> Result<Builder> {
+    let path = path.as_ref();
+
+    let (document, buffers, images) =
+        ::gltf::import(path).map_err(|error| Error::FileCannotBeParsed {
+            path: path.into(),
+            as_text: error.to_string(),
+        })?;
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| Error::NoRootNode { path: path.into() })?;
+
+    if has_scale(&document, &buffers) {
+        log::warn!(
+            "{}: scale is dropped, so the model may be the wrong size or skin wrongly",
+            path.display()
+        );
+    }
+
+    let mut importer = Importer {
+        path,
+        buffers: &buffers,
+        images: &images,
+        inverse_bind_poses: inverse_bind_poses(&document, &buffers),
+        clips: clips(&document, &buffers),
+        skeleton: Skeleton::new(),
+        bones: HashMap::new(),
+        mesh_nodes: Vec::new(),
+        meshes: Vec::new(),
+        materials: HashMap::new(),
+    };
+
+    for node in scene.nodes() {
+        importer.node(&node, 0)?;
+    }
+    // After the whole skeleton, as skins may name joints anywhere in it
+    for (node, bone) in mem::take(&mut importer.mesh_nodes) {
+        importer.mesh(&node, bone)?;
+    }
+
+    Ok(Cubic::builder()
+        .meshes(importer.meshes)
+        .skeleton(importer.skeleton))
+}
+
+struct Importer<'a> {
+    path: &'a Path,
+    buffers: &'a [buffer::Data],
+    images: &'a [image::Data],
+    /// By node index
+    inverse_bind_poses: HashMap<usize, Pose>,
+    clips: Vec<Clip<usize>>,
+    skeleton: Skeleton,
+    /// By node index
+    bones: HashMap<usize, usize>,
+    /// Nodes with a mesh and their bones
+    mesh_nodes: Vec<(Node<'a>, usize)>,
+    meshes: Vec<Mesh>,
+    /// By material index, `None` being glTF's default material
+    materials: HashMap<Option<usize>, Rc<Material>>,
+}
+
+impl<'a> Importer<'a> {
+    fn node(&mut self, node: &Node<'a>, parent: usize) -> Result<()> {
+        let (translation, rotation, _scale) = node.transform().decomposed();
+        let rest = (translation, rotation);
+
+        let mut builder = Bone::builder(parent);
+        if let Some(&inverse_bind_pose) = self.inverse_bind_poses.get(&node.index()) {
+            builder = builder.inverse_bind_pose(inverse_bind_pose);
+        }
+        let animations = bone_animations(&self.clips, &node.index(), rest);
+
+        let bone = self
+            .skeleton
+            .push_bone(builder.all_animations(animations.into()).build())
+            .ok_or(Error::ShouldNotOccur)?;
+        self.bones.insert(node.index(), bone);
+
+        if node.mesh().is_some() {
+            self.mesh_nodes.push((node.clone(), bone));
+        }
+
+        for child in node.children() {
+            self.node(&child, bone)?;
+        }
+
+        Ok(())
+    }
+
+    fn mesh(&mut self, node: &Node, bone: usize) -> Result<()> {
+        let Some(mesh) = node.mesh() else {
+            return Ok(());
+        };
+        let node_name = node.name().unwrap_or_default();
+        let name = mesh
+            .name()
+            .map_or_else(|| format!("mesh {}", mesh.index()), str::to_owned);
+
+        let joints = node
+            .skin()
+            .map(|skin| {
+                skin.joints()
+                    .map(|joint| self.bones.get(&joint.index()).copied())
+                    .collect::<Option<Rc<[usize]>>>()
+                    .ok_or(Error::ShouldNotOccur)
+            })
+            .transpose()?;
+
+        let skin = match joints {
+            Some(joints) if joints.len() <= MAX_JOINTS => Skin::Joints(joints),
+            // Skinned vertices are already placed by the skin's bind pose
+            Some(_) => Skin::BindPose,
+            None => Skin::None,
+        };
+
+        for primitive in mesh.primitives() {
+            let imported = self.primitive(node_name, &name, &primitive, bone, &skin)?;
+            self.meshes.push(imported);
+        }
+
+        Ok(())
+    }
+
+    fn primitive(
+        &mut self,
+        node: &str,
+        name: &str,
+        primitive: &Primitive,
+        bone: usize,
+        skin: &Skin,
+    ) -> Result<Mesh> {
+        if primitive.mode() != Mode::Triangles {
+            return Err(Error::NotTriangles { mesh: name.into() }.into());
+        }
+
+        let buffers = self.buffers;
+        let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+        let positions: Vec<Vector<3>> = reader
+            .read_positions()
+            .ok_or_else(|| Error::NoPositions { mesh: name.into() })?
+            .map(Vector::new)
+            .collect();
+
+        let textures: Vec<Vector<2>> = reader
+            .read_tex_coords(0)
+            .map(|coords| coords.into_f32().map(Vector::new).collect())
+            .unwrap_or_default();
+
+        let normals: Option<Vec<UnitVector<3>>> = reader.read_normals().map(|normals| {
+            normals
+                .map(|normal| Vector::new(normal).normalize())
+                .collect()
+        });
+
+        // The handedness in `w` has nowhere to go in a `SimpleVertex`
+        let tangents: Option<Vec<UnitVector<3>>> = reader.read_tangents().map(|tangents| {
+            tangents
+                .map(|[x, y, z, _]| Vector::new([x, y, z]).normalize())
+                .collect()
+        });
+
+        let indices: Vec<usize> = reader.read_indices().map_or_else(
+            || (0..positions.len()).collect(),
+            |indices| indices.into_u32().map(|index| index as usize).collect(),
+        );
+
+        // Each vertex's weight on each of the skin's joints
+        let influences: Vec<[(usize, f32); 4]> = match (skin, reader.read_joints(0)) {
+            (Skin::Joints(_), Some(joints)) => {
+                let weights: Vec<[f32; 4]> = reader
+                    .read_weights(0)
+                    .map(|weights| weights.into_f32().collect())
+                    .unwrap_or_default();
+                joints
+                    .into_u16()
+                    .zip(weights)
+                    .map(|(joints, weights)| array::from_fn(|i| (joints[i] as usize, weights[i])))
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+
+        let mut vertex_array_builder = VertexArray::<SimpleVertex>::builder();
+        let mut skinned_builder = VertexArray::<SkinnedVertex>::builder();
+
+        for triangle in indices.array_chunks::<3>() {
+            let incomplete_triangle = triangle.try_map(|index| {
+                let position = attribute("position", &positions, index)?;
+                // Untextured meshes still need coordinates to fill the vertex
+                let texture = if textures.is_empty() {
+                    Vector::default()
+                } else {
+                    attribute("texture", &textures, index)?
+                };
+                let normal = normals
+                    .as_deref()
+                    .map(|normals| attribute("normal", normals, index))
+                    .transpose()?;
+                let tangent = tangents
+                    .as_deref()
+                    .map(|tangents| attribute("tangent", tangents, index))
+                    .transpose()?;
+
+                Result::Ok(
+                    IncompleteVertex::new(position, texture)
+                        .opt_normal(normal)
+                        .opt_tangent(tangent),
+                )
+            })?;
+
+            if let Skin::Joints(_) = skin {
+                let weights = triangle.try_map(|index| attribute("joints", &influences, index))?;
+                let vertices = SimpleVertex::from_incomplete_triangle(&incomplete_triangle);
+                skinned_builder.push_triangle(array::from_fn(|i| {
+                    SkinnedVertex::new(vertices[i], &weights[i])
+                }));
+            } else {
+                vertex_array_builder.push_incomplete_triangle(&incomplete_triangle);
+            }
+        }
+
+        let key = primitive.material().index();
+        let material = match self.materials.get(&key) {
+            Some(material) => material.clone(),
+            None => {
+                let material = Rc::new(self.material(&primitive.material()));
+                self.materials.insert(key, material.clone());
+                material
+            }
+        };
+
+        let label = format!("{}: {name}", self.path.display());
+        let mesh = match skin {
+            Skin::Joints(joints) => {
+                let vertex_array = skinned_builder.build();
+                vertex_array.set_label(&label);
+                Mesh::skinned(Rc::new(vertex_array), joints.clone(), material)
+            }
+            Skin::BindPose | Skin::None => {
+                let vertex_array = vertex_array_builder.build();
+                vertex_array.set_label(&label);
+                let bone = match skin {
+                    Skin::BindPose => 0,
+                    _ => bone,
+                };
+                Mesh::new(Rc::new(vertex_array), material, bone)
+            }
+        };
+
+        Ok(mesh.named(node, name).centred(&positions))
+    }
+
+    fn material(&self, material: &::gltf::Material) -> Material {
+        let pbr = material.pbr_metallic_roughness();
+
+        let mut builder = Material::builder()
+            .shading(Shading::MetallicRoughness)
+            .translucent(material.alpha_mode() == AlphaMode::Blend)
+            .two_sided(material.double_sided())
+            .shininess(shininess(pbr.roughness_factor()))
+            .metallic(pbr.metallic_factor())
+            .roughness(pbr.roughness_factor())
+            .diffuse_colour(ColourRGBA::new(pbr.base_color_factor()))
+            .emission_colour(ColourRGB::new(material.emissive_factor()));
+
+        // The factors multiply white where there is no texture
+        let diffuse = pbr
+            .base_color_texture()
+            .and_then(|info| self.texture(&info.texture(), true, |_| {}));
+        builder = builder.diffuse(diffuse.unwrap_or_else(FlatTexture::white));
+
+        let emission = material
+            .emissive_texture()
+            .and_then(|info| self.texture(&info.texture(), true, |_| {}));
+        if let Some(emission) = emission {
+            builder = builder.emission(emission);
+        } else if material.emissive_factor() != [0.0; 3] {
+            builder = builder.emission(FlatTexture::white());
+        }
+
+        let metallic_roughness = pbr
+            .metallic_roughness_texture()
+            .and_then(|info| self.texture(&info.texture(), false, |_| {}));
+        if let Some(metallic_roughness) = metallic_roughness {
+            builder = builder.metallic_roughness(metallic_roughness);
+        }
+
+        if let Some(normal) = material.normal_texture() {
+            builder = builder.normal_scale(normal.scale());
+            if let Some(normal_map) = self.texture(&normal.texture(), false, |_| {}) {
+                builder = builder.normal_map(normal_map);
+            }
+        }
+
+        if let Some(occlusion) = material.occlusion_texture() {
+            builder = builder.occlusion_strength(occlusion.strength());
+        }
+        let ambient_occlusion = material.occlusion_texture().and_then(|occlusion| {
+            // Occlusion is only the red channel, often packed with roughness
+            // and metalness in the others
+            self.texture(&occlusion.texture(), false, |pixels| {
+                for [r, g, b, _] in pixels.array_chunks_mut() {
+                    (*g, *b) = (*r, *r);
+                }
+            })
+        });
+        if let Some(ambient_occlusion) = ambient_occlusion {
+            builder = builder.ambient_occlusion(ambient_occlusion);
+        }
+
+        builder.build()
+    }
+
+    /// `None` when the image cannot be used, leaving only that channel of
+    /// the material at its default. `edit` runs over the RGBA pixels first.
+    fn texture(
+        &self,
+        texture: &Texture,
+        colour: bool,
+        edit: impl FnOnce(&mut [u8]),
+    ) -> Option<FlatTexture> {
+        let (size, mut pixels) = self.pixels(texture).ok()?;
+        edit(&mut pixels);
+
+        let builder = if colour {
+            FlatTexture::builder().srgba_pixels(size, pixels)
+        } else {
+            FlatTexture::builder().rgba_pixels(size, pixels)
+        };
+        builder.ok().map(|builder| builder.build())
+    }
+
+    /// The texture's image as 8-bit RGBA
+    fn pixels(&self, texture: &Texture) -> Result<((u32, u32), Vec<u8>)> {
+        let index = texture.source().index();
+        let image = self.images.get(index).ok_or(Error::ShouldNotOccur)?;
+        let pixels = &image.pixels;
+
+        let rgba = match image.format {
+            Format::R8 => pixels.iter().flat_map(|&r| [r, r, r, u8::MAX]).collect(),
+            Format::R8G8 => pixels
+                .array_chunks()
+                .flat_map(|&[r, g]| [r, g, 0, u8::MAX])
+                .collect(),
+            Format::R8G8B8 => pixels
+                .array_chunks()
+                .flat_map(|&[r, g, b]| [r, g, b, u8::MAX])
+                .collect(),
+            Format::R8G8B8A8 => pixels.clone(),
+            format => {
+                return Err(Error::UnsupportedImageFormat {
+                    image: index,
+                    format: format!("{format:?}"),
+                }
+                .into());
+            }
+        };
+
+        Ok(((image.width, image.height), rgba))
+    }
+}
+
+/// What a node's mesh follows
+enum Skin {
+    None,
+    /// The bones of the skin's joints, in the skin's order
+    Joints(Rc<[usize]>),
+    /// Too many joints to skin, so held where the skin placed it
+    BindPose,
+}
+
+fn attribute<T: Copy>(which: &'static str, values: &[T], index: usize) -> Result<T> {
+    values.get(index).copied().ok_or_else(|| {
+        Error::ElementArrayOverflow {
+            which,
+            index_asked: index,
+            actual_len: values.len(),
+        }
+        .into()
+    })
+}
+
+/// The Blinn-Phong exponent closest to a glTF roughness
+fn shininess(roughness: f32) -> f32 {
+    (2.0 / roughness.powi(4).max(f32::EPSILON) - 2.0).clamp(1.0, 2048.0)
+}
+
+/// Whether any node, inverse bind matrix or animation channel scales
+fn has_scale(document: &Document, buffers: &[buffer::Data]) -> bool {
+    let nodes = document
+        .nodes()
+        .any(|node| is_scaled(node.transform().decomposed().2));
+
+    let skins = document.skins().any(|skin| {
+        let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+        reader
+            .read_inverse_bind_matrices()
+            .is_some_and(|matrices| matrices.map(matrix_scale).any(is_scaled))
+    });
+
+    let channels = document.animations().any(|animation| {
+        animation.channels().any(|channel| {
+            let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+            match reader.read_outputs() {
+                Some(ReadOutputs::Scales(values)) => values.into_iter().any(is_scaled),
+                _ => false,
+            }
+        })
+    });
+
+    nodes || skins || channels
+}
+
+/// By node index. A node in several skins keeps its first bind pose.
+fn inverse_bind_poses(document: &Document, buffers: &[buffer::Data]) -> HashMap<usize, Pose> {
+    let mut out = HashMap::new();
+
+    for skin in document.skins() {
+        let reader = skin.reader(|buffer| Some(&buffers[buffer.index()]));
+        // Without matrices every joint is bound at the identity
+        let Some(matrices) = reader.read_inverse_bind_matrices() else {
+            continue;
+        };
+
+        for (joint, matrix) in skin.joints().zip(matrices) {
+            out.entry(joint.index())
+                .or_insert_with(|| pose_from_matrix(matrix));
+        }
+    }
+
+    out
+}
+
+fn clips(document: &Document, buffers: &[buffer::Data]) -> Vec<Clip<usize>> {
+    document
+        .animations()
+        .map(|animation| {
+            let mut clip = Clip {
+                duration: 0.0,
+                nodes: HashMap::new(),
+            };
+
+            for channel in animation.channels() {
+                let reader = channel.reader(|buffer| Some(&buffers[buffer.index()]));
+                let Some(times) = reader.read_inputs() else {
+                    continue;
+                };
+                let times: Vec<f32> = times.collect();
+                let interpolation = channel.sampler().interpolation();
+                let step = interpolation == Interpolation::Step;
+                let tracks = clip
+                    .nodes
+                    .entry(channel.target().node().index())
+                    .or_default();
+
+                match reader.read_outputs() {
+                    Some(ReadOutputs::Translations(values)) => {
+                        let values = keyframe_values(values.collect(), interpolation);
+                        tracks.translation = Track::new(times.clone(), values, step);
+                    }
+                    Some(ReadOutputs::Rotations(values)) => {
+                        let values = keyframe_values(values.into_f32().collect(), interpolation);
+                        tracks.rotation = Track::new(times.clone(), values, step);
+                    }
+                    // Poses cannot scale, and `SimpleVertex` has no morph targets
+                    _ => continue,
+                }
+
+                if let Some(&end) = times.last() {
+                    clip.duration = clip.duration.max(end);
+                }
+            }
+
+            clip
+        })
+        .collect()
+}
+
+/// Cubic splines store an in-tangent, value and out-tangent per keyframe, of
+/// which only the value is kept
+fn keyframe_values<T: Copy>(values: Vec<T>, interpolation: Interpolation) -> Vec<T> {
+    if interpolation != Interpolation::CubicSpline {
+        return values;
+    }
+
+    values
+        .chunks(3)
+        .filter_map(|chunk| chunk.get(1).copied())
+        .collect()
+}
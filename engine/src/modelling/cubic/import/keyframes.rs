@@ -0,0 +1,205 @@
+use std::array;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use graphics::linear_algebra::{Matrix, Vector};
+use quaternion::{Quaternion, UnitQuaternion};
+
+use crate::modelling::cubic::geometry::{Animation, Orientation, Pose};
+
+/// A translation and `[x, y, z, w]` rotation
+pub(super) type Trs = ([f32; 3], [f32; 4]);
+
+pub(super) fn pose_from_trs((translation, [x, y, z, w]): Trs) -> Pose {
+    Pose::new_from_orientation_translation(
+        Orientation::from_quaternion(Quaternion::new(w, x, y, z).normalize()),
+        Vector::new(translation),
+    )
+}
+
+/// The rotation and translation of a column-major affine matrix, dropping
+/// any scale
+pub(super) fn trs_from_matrix([x, y, z, w]: [[f32; 4]; 4]) -> Trs {
+    let axis = |[a, b, c, _]: [f32; 4]| Vector::new([a, b, c]).normalize().v();
+    let rotation = UnitQuaternion::from_matrix(Matrix::from_col_major([axis(x), axis(y), axis(z)]));
+    let (real, vector) = (rotation.q().scalar(), rotation.q().vector());
+
+    ([w[0], w[1], w[2]], [vector[0], vector[1], vector[2], real])
+}
+
+/// The length of each axis of a column-major affine matrix
+pub(super) fn matrix_scale([x, y, z, _]: [[f32; 4]; 4]) -> [f32; 3] {
+    [x, y, z].map(|[a, b, c, _]| (a * a + b * b + c * c).sqrt())
+}
+
+/// Far enough from 1 on any axis that dropping it visibly changes the model
+pub(super) fn is_scaled(scale: [f32; 3]) -> bool {
+    scale.iter().any(|axis| (axis - 1.0).abs() > 1e-3)
+}
+
+pub(super) fn pose_from_matrix(matrix: [[f32; 4]; 4]) -> Pose {
+    pose_from_trs(trs_from_matrix(matrix))
+}
+
+/// One animation of a model file, its tracks keyed by node
+pub(super) struct Clip<K> {
+    /// Seconds
+    pub(super) duration: f32,
+    pub(super) nodes: HashMap<K, NodeTracks>,
+}
+
+/// `Pose` has no scale, so neither do these
+#[derive(Default)]
+pub(super) struct NodeTracks {
+    pub(super) translation: Option<Track<[f32; 3]>>,
+    pub(super) rotation: Option<Track<[f32; 4]>>,
+}
+
+pub(super) struct Track<T> {
+    times: Vec<f32>,
+    values: Vec<T>,
+    step: bool,
+}
+
+/// A bone's animations, one per clip, or its rest pose alone when there are
+/// no clips
+pub(super) fn bone_animations<K: Eq + Hash>(
+    clips: &[Clip<K>],
+    node: &K,
+    rest: Trs,
+) -> Vec<Animation> {
+    if clips.is_empty() {
+        let mut still = Animation::new();
+        still.set(pose_from_trs(rest));
+        return vec![still];
+    }
+
+    clips
+        .iter()
+        .map(|clip| clip.animation(node, rest))
+        .collect()
+}
+
+impl<K: Eq + Hash> Clip<K> {
+    /// Keyframes at every time either track has one, looping over the whole
+    /// clip so that all bones stay in step
+    fn animation(&self, node: &K, rest: Trs) -> Animation {
+        let mut animation = Animation::new();
+
+        let Some(tracks) = self.nodes.get(node).filter(|_| self.duration > 0.0) else {
+            animation.set(pose_from_trs(rest));
+            return animation;
+        };
+
+        let mut times: Vec<f32> = tracks
+            .translation
+            .iter()
+            .flat_map(|track| &track.times)
+            .chain(tracks.rotation.iter().flat_map(|track| &track.times))
+            .chain(&[0.0, self.duration])
+            .copied()
+            .filter(|time| (0.0..=self.duration).contains(time))
+            .collect();
+        times.sort_by(f32::total_cmp);
+        times.dedup();
+
+        for (index, &time) in times.iter().enumerate() {
+            let next = times.get(index + 1).copied().unwrap_or(time);
+            animation
+                .push(pose_from_trs(tracks.sample(time, rest)), next - time)
+                .expect("Fresh animation above");
+        }
+
+        animation
+    }
+}
+
+impl NodeTracks {
+    fn sample(&self, time: f32, (translation, rotation): Trs) -> Trs {
+        let translation = (self.translation.as_ref())
+            .and_then(|track| {
+                track.sample(time, |a, b, t| array::from_fn(|i| a[i] + (b[i] - a[i]) * t))
+            })
+            .unwrap_or(translation);
+
+        // Normalised into a pose afterwards, so lerping is enough between
+        // keyframes this close
+        let rotation = (self.rotation.as_ref())
+            .and_then(|track| {
+                track.sample(time, |a, b, t| {
+                    let dot: f32 = a.iter().zip(b).map(|(a, b)| a * b).sum();
+                    let sign = dot.signum();
+                    array::from_fn(|i| a[i] + (sign * b[i] - a[i]) * t)
+                })
+            })
+            .unwrap_or(rotation);
+
+        (translation, rotation)
+    }
+}
+
+impl<T: Copy> Track<T> {
+    /// `None` when there are no keyframes. `step` holds each value until the
+    /// next keyframe instead of blending towards it.
+    pub(super) fn new(mut times: Vec<f32>, mut values: Vec<T>, step: bool) -> Option<Self> {
+        let len = times.len().min(values.len());
+        times.truncate(len);
+        values.truncate(len);
+
+        (len > 0).then_some(Self {
+            times,
+            values,
+            step,
+        })
+    }
+
+    fn sample(&self, time: f32, lerp: fn(T, T, f32) -> T) -> Option<T> {
+        let next = self.times.partition_point(|&keyframe| keyframe <= time);
+
+        match (next.checked_sub(1), self.values.get(next)) {
+            (Some(previous), Some(&after)) if !self.step => {
+                let (start, end) = (self.times[previous], self.times[next]);
+                Some(lerp(
+                    self.values[previous],
+                    after,
+                    (time - start) / (end - start),
+                ))
+            }
+            (Some(previous), _) => Some(self.values[previous]),
+            (None, _) => self.values.first().copied(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_matrix_scale() {
+        let scaled = [
+            [0.0, 0.01, 0.0, 0.0],
+            [-0.01, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 0.01, 0.0],
+            [1.0, 2.0, 3.0, 1.0],
+        ];
+        let scale = matrix_scale(scaled);
+        assert!(scale.iter().all(|axis| (axis - 0.01).abs() < 1e-6));
+        assert!(is_scaled(scale));
+        assert!(!is_scaled([1.0, 1.0, 1.0 + 1e-4]));
+    }
+
+    #[test]
+    fn test_track_sample() {
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let linear = Track::new(vec![1.0, 2.0, 4.0], vec![0.0, 10.0, 30.0], false).unwrap();
+        let step = Track::new(vec![1.0, 2.0], vec![0.0, 10.0], true).unwrap();
+
+        assert_eq!(linear.sample(0.0, lerp), Some(0.0));
+        assert_eq!(linear.sample(1.5, lerp), Some(5.0));
+        assert_eq!(linear.sample(3.0, lerp), Some(20.0));
+        assert_eq!(linear.sample(5.0, lerp), Some(30.0));
+        assert_eq!(step.sample(1.5, lerp), Some(0.0));
+        assert!(Track::<f32>::new(vec![0.0], vec![], false).is_none());
+    }
+}
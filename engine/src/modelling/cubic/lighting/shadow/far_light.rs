@@ -1,3 +1,6 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use graphics::colour::ColourRGBA;
 use graphics::framebuffer::attachments::WithDepth;
 use graphics::framebuffer::{Builder, Framebuffer};
@@ -21,20 +24,25 @@ pub struct ShadowFarLight {
 
 impl ShadowFarLight {
     pub fn new(light: FarLight, size: (TexDim, TexDim)) -> Self {
-        Self {
-            light,
-            framebuffer: Builder::new_flat()
-                .depth()
-                .size(size)
-                .map_attachment(|tex_builder| {
-                    tex_builder
-                        .wrap_s_t(WrapType::ClampToBorder(ColourRGBA::new([
-                            0.0, 0.0, 0.0, 1.0,
-                        ])))
-                        .mag_filter(Magnification::Linear)
-                })
-                .build(),
-        }
+        let mut framebuffer = Builder::new_flat()
+            .depth()
+            .size(size)
+            .map_attachment(|tex_builder| {
+                tex_builder
+                    .wrap_s_t(WrapType::ClampToBorder(ColourRGBA::new([
+                        0.0, 0.0, 0.0, 1.0,
+                    ])))
+                    .mag_filter(Magnification::Linear)
+            })
+            .build();
+        framebuffer.set_label("far light shadow map");
+
+        Self { light, framebuffer }
+    }
+
+    /// The depth rendered from the light, for viewing while debugging
+    pub fn depth_map(&self) -> Rc<RefCell<FlatTexture>> {
+        self.framebuffer.get_attachment_texture()
     }
 
     pub(crate) fn camera(&self, target: Vector<3>) -> Camera<Pose> {
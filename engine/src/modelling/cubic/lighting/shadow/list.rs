@@ -9,6 +9,7 @@ use graphics::texture::Texture;
 use super::{ShadowFarLight, ShadowPointLight, ShadowSpotLight};
 use crate::modelling::Cubic;
 use crate::modelling::cubic::lighting::traits::ShadowLightCompatible;
+use crate::modelling::cubic::model::{Layer, Pass};
 use crate::opengl_shaders;
 
 #[derive(Debug, Default)]
@@ -23,82 +24,107 @@ impl<const MAX: usize> ShadowListLights<MAX> {
         &self,
         sp_context: &mut ShaderProgramContext,
         fb_context: &mut FramebufferContext,
-        complete_models: &[(&Cubic, usize /* animation */, f32 /* time */)],
+        // Each model with which of its meshes cast shadows, its animation and
+        // its time
+        complete_models: &[(&Cubic, Layer, usize, f32)],
         target_position: Vector<3>,
     ) -> Result<()> {
-        let mut active_depth_only_shader =
-            opengl_shaders::far_light_depth().use_program(sp_context);
-
-        // let cull_face_marker =
-        // active_depth_only_shader.cull_face(CullFace::FrontFace);
-
-        for light in &self.far {
-            // Draw the scene from the lights perspective, saving to the light's internal
-            // framebuffer
-            active_depth_only_shader.set_uniform(
-                "projtimesview".to_string(),
-                light.projtimesview(target_position),
-            );
-
-            let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
-
-            for (model, animation, time) in complete_models {
-                model.draw(
-                    &mut active_depth_only_shader,
-                    &mut active_light_framebuffer,
-                    *animation,
-                    *time,
-                )?;
+        // Skinned meshes need their own shaders, skipped when there are none
+        let passes = if complete_models.iter().any(|(model, ..)| model.is_skinned()) {
+            &[Pass::Rigid, Pass::Skinned][..]
+        } else {
+            &[Pass::Rigid]
+        };
+
+        for &pass in passes {
+            let (far_shader, point_shader) = match pass {
+                Pass::Skinned => (
+                    opengl_shaders::far_light_depth_skinned(),
+                    opengl_shaders::point_depth_skinned(),
+                ),
+                _ => (
+                    opengl_shaders::far_light_depth(),
+                    opengl_shaders::point_depth(),
+                ),
+            };
+
+            let mut active_depth_only_shader = far_shader.use_program(sp_context);
+
+            // let cull_face_marker =
+            // active_depth_only_shader.cull_face(CullFace::FrontFace);
+
+            for light in &self.far {
+                // Draw the scene from the lights perspective, saving to the light's internal
+                // framebuffer
+                active_depth_only_shader.set_uniform(
+                    "projtimesview".to_string(),
+                    light.projtimesview(target_position),
+                );
+
+                let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
+
+                for &(model, layer, animation, time) in complete_models {
+                    model.draw(
+                        &mut active_depth_only_shader,
+                        &mut active_light_framebuffer,
+                        pass,
+                        layer,
+                        animation,
+                        time,
+                    )?;
+                }
             }
-        }
-        drop(active_depth_only_shader);
-
-        let mut active_depth_only_shader =
-            opengl_shaders::far_light_depth().use_program(sp_context);
-
-        for light in &self.spot {
-            // Draw the scene from the lights perspective, saving to the light's internal
-            // framebuffer
-            active_depth_only_shader
-                .set_uniform("projtimesview".to_string(), light.projtimesview());
-
-            let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
-
-            for (model, animation, time) in complete_models {
-                model.draw(
-                    &mut active_depth_only_shader,
-                    &mut active_light_framebuffer,
-                    *animation,
-                    *time,
-                )?;
+            drop(active_depth_only_shader);
+
+            let mut active_depth_only_shader = far_shader.use_program(sp_context);
+
+            for light in &self.spot {
+                // Draw the scene from the lights perspective, saving to the light's internal
+                // framebuffer
+                active_depth_only_shader
+                    .set_uniform("projtimesview".to_string(), light.projtimesview());
+
+                let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
+
+                for &(model, layer, animation, time) in complete_models {
+                    model.draw(
+                        &mut active_depth_only_shader,
+                        &mut active_light_framebuffer,
+                        pass,
+                        layer,
+                        animation,
+                        time,
+                    )?;
+                }
             }
-        }
-        drop(active_depth_only_shader);
-
-        let mut active_depth_only_shader_point =
-            opengl_shaders::point_depth().use_program(sp_context);
-
-        for light in &self.point {
-            for (index, matrix) in light.get_look_at_matrices().into_iter().enumerate() {
-                active_depth_only_shader_point.set_uniform(format!("matrix[{index}]"), matrix);
+            drop(active_depth_only_shader);
+
+            let mut active_depth_only_shader_point = point_shader.use_program(sp_context);
+
+            for light in &self.point {
+                for (index, matrix) in light.get_look_at_matrices().into_iter().enumerate() {
+                    active_depth_only_shader_point.set_uniform(format!("matrix[{index}]"), matrix);
+                }
+                active_depth_only_shader_point
+                    .set_uniform("light_position".into(), light.light.position);
+                active_depth_only_shader_point.set_uniform("far_plane".into(), light.far_plane());
+
+                let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
+
+                for &(model, layer, animation, time) in complete_models {
+                    model.draw(
+                        &mut active_depth_only_shader_point,
+                        &mut active_light_framebuffer,
+                        pass,
+                        layer,
+                        animation,
+                        time,
+                    )?;
+                }
             }
-            active_depth_only_shader_point
-                .set_uniform("light_position".into(), light.light.position);
-            active_depth_only_shader_point.set_uniform("far_plane".into(), light.far_plane());
-
-            let mut active_light_framebuffer = light.framebuffer.bind(fb_context);
-
-            for (model, animation, time) in complete_models {
-                model.draw(
-                    &mut active_depth_only_shader_point,
-                    &mut active_light_framebuffer,
-                    *animation,
-                    *time,
-                )?;
-            }
-        }
 
-        drop(active_depth_only_shader_point);
+            drop(active_depth_only_shader_point);
+        }
         // from this point, the depth buffers should be filled with depth data.
 
         Ok(())
@@ -19,10 +19,11 @@ pub struct ShadowPointLight {
 
 impl ShadowPointLight {
     pub fn new(light: PointLight, size: TexDim) -> Self {
-        let framebuffer = Builder::new_cubic()
+        let mut framebuffer = Builder::new_cubic()
             .cubic_depth()
             .size((size, size))
             .build();
+        framebuffer.set_label("point light shadow map");
         Self { light, framebuffer }
     }
 
@@ -1,4 +1,4 @@
-use graphics::colour::ColourRGBA;
+use graphics::colour::{ColourRGB, ColourRGBA};
 // use crate::modelling::Cubic;
 use graphics::shader_program::ActiveShaderProgram;
 use graphics::texture::{FlatTexture, Texture};
@@ -6,15 +6,49 @@ use utils::{builder, new};
 
 use crate::modelling::Cubic;
 
+/// How a material turns the light reaching it into colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shading {
+    /// Lit by each light's ambient, diffuse and specular colours, with
+    /// `shininess` and the `specular_map`
+    #[default]
+    BlinnPhong,
+    /// Cook-Torrance, with `diffuse` as the base colour and `metallic`,
+    /// `roughness` and the `metallic_roughness` map in place of the
+    /// specular ones
+    MetallicRoughness,
+}
+
 #[derive(Debug)]
 pub struct Material {
-    pub _translucent: bool,
+    pub shading: Shading,
+    /// Drawn after every opaque mesh, back to front and without writing
+    /// depth, rather than with them
+    pub translucent: bool,
     pub shininess: f32,
     pub diffuse: FlatTexture,
     pub specular_map: FlatTexture,
     pub emission: FlatTexture,
     pub normal_map: FlatTexture,
     pub ambient_occlusion: FlatTexture,
+    /// Its red channel scales the diffuse alpha
+    pub opacity_map: FlatTexture,
+    /// Multiplies `diffuse`, its alpha being the opacity
+    pub diffuse_colour: ColourRGBA,
+    /// Multiplies `emission`
+    pub emission_colour: ColourRGB,
+    /// Drawn without culling either face, such as leaves and cloth
+    pub two_sided: bool,
+    /// Multiplies the blue channel of `metallic_roughness`
+    pub metallic: f32,
+    /// Multiplies the green channel of `metallic_roughness`
+    pub roughness: f32,
+    /// Packed as glTF does, roughness in green and metallic in blue
+    pub metallic_roughness: FlatTexture,
+    /// Scales the tilt of `normal_map` away from the surface normal
+    pub normal_scale: f32,
+    /// How much of `ambient_occlusion` applies, from none at 0 to all at 1
+    pub occlusion_strength: f32,
 }
 
 impl Material {
@@ -38,6 +72,19 @@ impl Material {
         name: &str,
     ) {
         shader.set_uniform(format!("{name}.shininess"), self.shininess);
+        shader.set_uniform(format!("{name}.diffuse_colour"), self.diffuse_colour);
+        shader.set_uniform(format!("{name}.emission_colour"), self.emission_colour);
+        shader.set_uniform(
+            format!("{name}.metallic_roughness_shading"),
+            (self.shading == Shading::MetallicRoughness) as i32,
+        );
+        shader.set_uniform(format!("{name}.metallic"), self.metallic);
+        shader.set_uniform(format!("{name}.roughness"), self.roughness);
+        shader.set_uniform(format!("{name}.normal_scale"), self.normal_scale);
+        shader.set_uniform(
+            format!("{name}.occlusion_strength"),
+            self.occlusion_strength,
+        );
         shader.register_texture(
             vec![
                 (format!("{name}.diffuse"), &self.diffuse),
@@ -45,6 +92,11 @@ impl Material {
                 (format!("{name}.emission"), &self.emission),
                 (format!("{name}.normal_map"), &self.normal_map),
                 (format!("{name}.ambient_occlusion"), &self.ambient_occlusion),
+                (format!("{name}.opacity_map"), &self.opacity_map),
+                (
+                    format!("{name}.metallic_roughness"),
+                    &self.metallic_roughness,
+                ),
             ]
             .into_iter()
             .map(|(string, tex)| (string, tex as &dyn Texture)),
@@ -54,6 +106,7 @@ impl Material {
 
 #[derive(Default, Debug)]
 pub struct Builder {
+    shading: Shading,
     translucent: bool,
     shininess: Option<f32>,
     diffuse: Option<FlatTexture>,
@@ -61,11 +114,22 @@ pub struct Builder {
     emission: Option<FlatTexture>,
     normal_map: Option<FlatTexture>,
     ambient_occlusion: Option<FlatTexture>,
+    opacity_map: Option<FlatTexture>,
+    diffuse_colour: Option<ColourRGBA>,
+    emission_colour: Option<ColourRGB>,
+    two_sided: bool,
+    metallic: Option<f32>,
+    roughness: Option<f32>,
+    metallic_roughness: Option<FlatTexture>,
+    normal_scale: Option<f32>,
+    occlusion_strength: Option<f32>,
 }
 
 impl Builder {
     new!();
 
+    builder!(shading: Shading);
+
     builder!(diffuse: Option<FlatTexture>);
 
     builder!(specular: Option<FlatTexture>);
@@ -76,15 +140,34 @@ impl Builder {
 
     builder!(ambient_occlusion: Option<FlatTexture>);
 
+    builder!(opacity_map: Option<FlatTexture>);
+
+    builder!(diffuse_colour: Option<ColourRGBA>);
+
+    builder!(emission_colour: Option<ColourRGB>);
+
+    builder!(two_sided: bool);
+
     builder!(shininess: Option<f32>);
 
     builder!(translucent: bool);
+
+    builder!(metallic: Option<f32>);
+
+    builder!(roughness: Option<f32>);
+
+    builder!(metallic_roughness: Option<FlatTexture>);
+
+    builder!(normal_scale: Option<f32>);
+
+    builder!(occlusion_strength: Option<f32>);
 }
 
 impl Builder {
     pub fn build(self) -> Material {
         Material {
-            _translucent: self.translucent,
+            shading: self.shading,
+            translucent: self.translucent,
             shininess: self.shininess.unwrap_or(32.0),
             diffuse: self.diffuse.unwrap_or_default(),
             specular_map: self.specular.unwrap_or_default(),
@@ -93,6 +176,15 @@ impl Builder {
                 .normal_map
                 .unwrap_or_else(|| FlatTexture::monochrome(ColourRGBA::new([0.5, 0.5, 1.0, 1.0]))),
             ambient_occlusion: self.ambient_occlusion.unwrap_or_else(FlatTexture::white),
+            opacity_map: self.opacity_map.unwrap_or_else(FlatTexture::white),
+            diffuse_colour: self.diffuse_colour.unwrap_or(ColourRGBA::new([1.0; 4])),
+            emission_colour: self.emission_colour.unwrap_or(ColourRGB::new([1.0; 3])),
+            two_sided: self.two_sided,
+            metallic: self.metallic.unwrap_or(0.0),
+            roughness: self.roughness.unwrap_or(0.5),
+            metallic_roughness: self.metallic_roughness.unwrap_or_else(FlatTexture::white),
+            normal_scale: self.normal_scale.unwrap_or(1.0),
+            occlusion_strength: self.occlusion_strength.unwrap_or(1.0),
         }
     }
 }
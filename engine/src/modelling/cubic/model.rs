@@ -3,23 +3,168 @@ use std::rc::Rc;
 
 use graphics::framebuffer::ActiveFramebuffer;
 use graphics::framebuffer::traits::FramebufferWithDepth;
-use graphics::linear_algebra::Matrix;
+use graphics::linear_algebra::{Matrix, Vector};
 use graphics::shader_program::{ActiveShaderProgram, CullFace};
 use graphics::vertex_array::VertexArray;
 use russimp::scene::PostProcess;
 
-use super::geometry::YieldsPose;
+use super::geometry::{Orientation, Pose, YieldsPose};
 use super::material::Material;
 use super::{Builder, Skeleton, import};
 use crate::error::Result;
-use crate::modelling::simple_vertex::SimpleVertex;
 use crate::modelling::test_models::vertex_array_cube;
+use crate::modelling::{MAX_JOINTS, SimpleVertex, SkinnedVertex};
+
+/// What a mesh's vertices follow
+#[derive(Debug, Clone)]
+pub enum Vertices {
+    /// Entirely the mesh's bone
+    Rigid(Rc<VertexArray<SimpleVertex>>),
+    /// Each vertex blends up to four `joints`, the indices of the skeleton
+    /// bones that the vertices' own joint indices stand for
+    Skinned {
+        vertex_array: Rc<VertexArray<SkinnedVertex>>,
+        joints: Rc<[usize]>,
+    },
+}
+
+/// Which of a model's meshes a shader draws
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pass {
+    /// Every mesh, holding skinned meshes in their bind pose, for shaders
+    /// without a skinned variant
+    All,
+    Rigid,
+    /// For shaders built with `SKINNED`, which read the `joints` palette
+    Skinned,
+}
+
+impl Pass {
+    fn draws(self, mesh: &Mesh) -> bool {
+        match (self, &mesh.vertices) {
+            (Self::All, _) => true,
+            (Self::Rigid, Vertices::Rigid(_)) => true,
+            (Self::Skinned, Vertices::Skinned { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Which of a model's meshes a draw covers, by whether their material is
+/// translucent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Layer {
+    Opaque,
+    Translucent,
+    Both,
+}
+
+impl Layer {
+    fn draws(self, mesh: &Mesh) -> bool {
+        match self {
+            Self::Opaque => !mesh.material.translucent,
+            Self::Translucent => mesh.material.translucent,
+            Self::Both => true,
+        }
+    }
+}
+
+/// One mesh of a translucent pass, from `back_to_front`
+#[derive(Debug, Clone)]
+pub(crate) struct Translucent<'a> {
+    model: &'a Cubic,
+    /// Index into the model's meshes
+    mesh: usize,
+    animation: usize,
+    time: f32,
+    /// The model's `Skeleton::get_all_bones`, shared by each of its meshes,
+    /// if any of them is skinned
+    palette: Option<Rc<[Matrix<4, 4>]>>,
+    /// Squared, from the camera
+    distance: f32,
+}
+
+impl<'a> Translucent<'a> {
+    pub(crate) fn is_skinned(&self) -> bool {
+        self.model.meshes[self.mesh].is_skinned()
+    }
+
+    /// Drawn whatever its layer
+    pub(crate) fn draw<const OUT: usize, D: FramebufferWithDepth<OUT>, L>(
+        &self,
+        active_shader: &mut ActiveShaderProgram<'_, '_, 'a, (Cubic, L), D::Tex, OUT>,
+        active_framebuffer: &mut ActiveFramebuffer<'_, '_, OUT, D>,
+        pass: Pass,
+    ) -> graphics::Result<()> {
+        let mesh = self
+            .model
+            .meshes
+            .get(self.mesh)
+            .filter(|mesh| pass.draws(mesh));
+        let palette = self.palette.as_deref().filter(|_| pass == Pass::Skinned);
+        self.model.draw_meshes(
+            active_shader,
+            active_framebuffer,
+            mesh,
+            self.animation,
+            self.time,
+            palette,
+        )
+    }
+}
+
+/// The translucent meshes of `opaque` and every mesh of `transparent`,
+/// furthest from `camera` first so that each blends over what is behind it
+pub(crate) fn back_to_front<'a>(
+    opaque: &[(&'a Cubic, usize /* animation */, f32 /* time */)],
+    transparent: &[(&'a Cubic, usize /* animation */, f32 /* time */)],
+    camera: Vector<3>,
+) -> Vec<Translucent<'a>> {
+    let opaque = opaque.iter().map(|&model| (model, Layer::Translucent));
+    let transparent = transparent.iter().map(|&model| (model, Layer::Both));
+
+    let mut meshes: Vec<Translucent> = opaque
+        .chain(transparent)
+        .flat_map(|((model, animation, time), layer)| {
+            let distances: Vec<_> = model.distances(layer, animation, time, camera).collect();
+            let palette: Option<Rc<[_]>> = distances
+                .iter()
+                .any(|&(mesh, _)| model.meshes[mesh].is_skinned())
+                .then(|| model.skeleton.get_all_bones(animation, time).into());
+
+            distances
+                .into_iter()
+                .map(move |(mesh, distance)| Translucent {
+                    model,
+                    mesh,
+                    animation,
+                    time,
+                    palette: palette.clone(),
+                    distance,
+                })
+        })
+        .collect();
+    meshes.sort_by(|a, b| b.distance.total_cmp(&a.distance));
+
+    meshes
+}
 
 #[derive(Debug, Clone)]
 pub struct Mesh {
-    pub vertex_array: Rc<VertexArray<SimpleVertex>>,
+    pub vertices: Vertices,
     pub material: Rc<Material>,
+    /// Unused by skinned meshes, which follow their joints instead
     pub bone: usize,
+    /// The name it was imported under, shared by every mesh one imported
+    /// mesh was split into
+    pub name: String,
+    /// The name of the node it was imported from
+    pub node: String,
+    /// Hidden meshes are neither drawn nor cast shadows
+    pub visible: bool,
+    /// The middle of its vertices, in its bone's space or the model's for
+    /// skinned meshes, which translucent meshes are sorted by
+    pub centre: Vector<3>,
 }
 
 impl Mesh {
@@ -29,33 +174,99 @@ impl Mesh {
         bone: usize,
     ) -> Self {
         Self {
-            vertex_array,
+            vertices: Vertices::Rigid(vertex_array),
             material,
             bone,
+            name: String::new(),
+            node: String::new(),
+            visible: true,
+            centre: Vector::default(),
+        }
+    }
+
+    /// `joints` are skeleton bones, indexed by the vertices' joints. There
+    /// can be at most `MAX_JOINTS`.
+    pub fn skinned(
+        vertex_array: Rc<VertexArray<SkinnedVertex>>,
+        joints: Rc<[usize]>,
+        material: Rc<Material>,
+    ) -> Self {
+        Self {
+            vertices: Vertices::Skinned {
+                vertex_array,
+                joints,
+            },
+            material,
+            bone: 0,
+            name: String::new(),
+            node: String::new(),
+            visible: true,
+            centre: Vector::default(),
+        }
+    }
+
+    pub fn named(self, node: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            node: node.into(),
+            ..self
+        }
+    }
+
+    /// Set `centre` to the mean of `positions`
+    pub fn centred(self, positions: &[Vector<3>]) -> Self {
+        let mut centre = Vector::default();
+        for &position in positions {
+            centre += position;
+        }
+        if !positions.is_empty() {
+            centre = centre.scale(1.0 / positions.len() as f32);
         }
+
+        Self { centre, ..self }
     }
 
+    pub fn is_skinned(&self) -> bool {
+        matches!(self.vertices, Vertices::Skinned { .. })
+    }
+
+    /// `palette` is `Skeleton::get_all_bones`, only needed by skinned meshes
+    /// in a `Pass::Skinned`. A skinned mesh with a joint missing from it is
+    /// left out.
     pub(crate) fn draw<'a, const OUT: usize, D: FramebufferWithDepth<OUT>, L>(
         &'a self,
         active_shader: &mut ActiveShaderProgram<'_, '_, 'a, (Cubic, L), D::Tex, OUT>,
         active_framebuffer: &mut ActiveFramebuffer<'_, '_, OUT, D>,
-        skeleton: &Skeleton,
-        relative: bool,
-        animation: usize,
-        time: f32,
-        scale: f32,
+        model: Matrix<4, 4>,
+        palette: Option<&[Matrix<4, 4>]>,
     ) -> graphics::Result<()> {
         self.material.register_to(active_shader, "material");
+        active_shader.set_uniform("model".into(), model);
 
-        active_shader.set_uniform(
-            "model".into(),
-            skeleton
-                .get_pose((relative, self.bone, animation, time))
-                .as_matrix()
-                * Matrix::transform_scale(scale, scale, scale),
-        );
-
-        self.vertex_array.draw(active_shader, active_framebuffer)
+        match &self.vertices {
+            Vertices::Rigid(vertex_array) => vertex_array.draw(active_shader, active_framebuffer),
+            Vertices::Skinned {
+                vertex_array,
+                joints,
+            } => {
+                if let Some(palette) = palette {
+                    let matrices = joints
+                        .iter()
+                        .take(MAX_JOINTS)
+                        .map(|&bone| palette.get(bone).copied())
+                        .collect::<Option<Vec<_>>>();
+                    // Drawn with the last mesh's joints, it would be torn apart
+                    let Some(matrices) = matrices else {
+                        log::warn!("{}: left out: a joint is not in the skeleton", self.name);
+                        return Ok(());
+                    };
+                    // One upload for the whole array, which only needs the
+                    // name of its first element
+                    active_shader.set_uniform("joints[0]".into(), matrices.as_slice());
+                }
+                vertex_array.draw(active_shader, active_framebuffer)
+            }
+        }
     }
 }
 
@@ -97,34 +308,141 @@ impl Cubic {
             .for_each(|mesh| mesh.material = mat.clone());
     }
 
+    pub fn meshes(&self) -> &[Mesh] {
+        &self.meshes
+    }
+
+    /// Every mesh named `name` or imported from a node of that name, such as
+    /// to hide one part of a model
+    pub fn meshes_named_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item = &'a mut Mesh> {
+        self.meshes
+            .iter_mut()
+            .filter(move |mesh| mesh.name == name || mesh.node == name)
+    }
+
+    /// Whether any visible mesh needs a skinned shader to move with its
+    /// joints
+    pub fn is_skinned(&self) -> bool {
+        self.meshes
+            .iter()
+            .any(|mesh| mesh.visible && mesh.is_skinned())
+    }
+
+    /// The visible meshes drawn by `pass` and `layer`
     pub(crate) fn draw<'a, const OUT: usize, D: FramebufferWithDepth<OUT>, L>(
         &'a self,
         active_shader: &mut ActiveShaderProgram<'_, '_, 'a, (Self, L), D::Tex, OUT>,
         active_framebuffer: &mut ActiveFramebuffer<'_, '_, OUT, D>,
+        pass: Pass,
+        layer: Layer,
         animation: usize,
         time: f32,
     ) -> graphics::Result<()> {
-        // Ignore cull_face error
-        _ = active_shader.cull_face(self.cull_face);
-
-        // active_shader.set_uniform("model".to_string(), self.model_matrix(hint));
-
-        for mesh in &self.meshes {
-            mesh.draw(
-                active_shader,
-                active_framebuffer,
-                &self.skeleton,
-                self.realtive,
-                animation,
-                time,
-                self.scale,
-            )?;
+        let meshes = self
+            .meshes
+            .iter()
+            .filter(|mesh| mesh.visible && pass.draws(mesh) && layer.draws(mesh));
+        let palette = (pass == Pass::Skinned).then(|| self.skeleton.get_all_bones(animation, time));
+        self.draw_meshes(
+            active_shader,
+            active_framebuffer,
+            meshes,
+            animation,
+            time,
+            palette.as_deref(),
+        )
+    }
+
+    /// `palette` as for `Mesh::draw`
+    fn draw_meshes<'a, const OUT: usize, D: FramebufferWithDepth<OUT>, L>(
+        &'a self,
+        active_shader: &mut ActiveShaderProgram<'_, '_, 'a, (Self, L), D::Tex, OUT>,
+        active_framebuffer: &mut ActiveFramebuffer<'_, '_, OUT, D>,
+        meshes: impl IntoIterator<Item = &'a Mesh>,
+        animation: usize,
+        time: f32,
+        palette: Option<&[Matrix<4, 4>]>,
+    ) -> graphics::Result<()> {
+        for mesh in meshes {
+            let cull_face = if mesh.material.two_sided {
+                CullFace::DoNotCull
+            } else {
+                self.cull_face
+            };
+            // Ignore cull_face error
+            _ = active_shader.cull_face(cull_face);
+
+            let model = self.mesh_model(mesh, animation, time);
+            mesh.draw(active_shader, active_framebuffer, model, palette)?;
         }
 
         Ok(())
     }
 
+    fn mesh_model(&self, mesh: &Mesh, animation: usize, time: f32) -> Matrix<4, 4> {
+        let scale = Matrix::transform_scale(self.scale, self.scale, self.scale);
+        match mesh.vertices {
+            Vertices::Rigid(_) => {
+                self.skeleton
+                    .get_pose((self.realtive, mesh.bone, animation, time))
+                    .as_matrix()
+                    * scale
+            }
+            // The joints already carry the root's pose, so skinned meshes are
+            // only scaled about it
+            Vertices::Skinned { .. } => {
+                let root = self.skeleton.get_pose((false, 0, animation, time));
+                root.as_matrix() * scale * root.inverse().as_matrix()
+            }
+        }
+    }
+
+    /// The visible meshes in `layer`, each with the squared distance from
+    /// `camera` to its centre. Skinned meshes are placed by their bind pose.
+    fn distances(
+        &self,
+        layer: Layer,
+        animation: usize,
+        time: f32,
+        camera: Vector<3>,
+    ) -> impl Iterator<Item = (usize, f32)> {
+        let place = |pose: Pose, point: Vector<3>| {
+            let point = Pose::new_from_orientation_translation(Orientation::default(), point);
+            pose.apply_after(point).translation()
+        };
+
+        self.meshes
+            .iter()
+            .enumerate()
+            .filter(move |(_, mesh)| mesh.visible && layer.draws(mesh))
+            .map(move |(index, mesh)| {
+                let centre = match mesh.vertices {
+                    Vertices::Rigid(_) => {
+                        let key = (self.realtive, mesh.bone, animation, time);
+                        place(self.skeleton.get_pose(key), mesh.centre.scale(self.scale))
+                    }
+                    Vertices::Skinned { .. } => {
+                        let root = self.skeleton.get_pose((false, 0, animation, time));
+                        place(root, place(root.inverse(), mesh.centre).scale(self.scale))
+                    }
+                };
+                let offset = centre - camera;
+                (index, offset.inner().iter().map(|x| x * x).sum())
+            })
+    }
+
     pub fn import<PA: AsRef<Path>>(path: PA, post_process: Vec<PostProcess>) -> Result<Builder> {
         import::import(path, post_process)
     }
+
+    /// Load a glTF 2.0 or GLB file natively, keeping the skins and animations
+    /// that assimp's glTF path drops.
+    ///
+    /// Poses cannot scale, so any scale on nodes, skins or animations is
+    /// dropped, with a warning logged. A rig exported with a scaled root, such
+    /// as Blender's 0.01 armature under a 100 object, needs its scale applied
+    /// before export to import at the right size.
+    pub fn import_gltf<PA: AsRef<Path>>(path: PA) -> Result<Builder> {
+        import::gltf::import(path)
+    }
 }
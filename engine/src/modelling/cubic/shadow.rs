@@ -1,15 +1,18 @@
 use std::iter;
 
-use graphics::framebuffer::FramebufferContext;
+use graphics::framebuffer::attachments::WithDepth;
 use graphics::framebuffer::traits::FramebufferWithDepth;
+use graphics::framebuffer::{Framebuffer, FramebufferContext, Viewport};
 use graphics::linear_algebra::{Matrix, Vector};
+use graphics::shader_program::{ActiveShaderProgram, ShaderProgram};
 use graphics::texture::FlatTexture;
 use graphics::{Draw, Result, ShaderProgramContext};
 
 use super::camera::Camera;
 use super::geometry::YieldsPose;
 use super::lighting::shadow::ShadowListLights;
-use super::model::Cubic;
+use super::model::{Cubic, Layer, Pass, Translucent, back_to_front};
+use super::weighted_blended::WeightedBlended;
 use crate::opengl_shaders;
 
 pub const SHADOW_SHADER_MAX_LIGHTS: usize = 2;
@@ -23,8 +26,16 @@ pub struct Group<'a, X: FramebufferWithDepth<2, Tex = FlatTexture>> {
 
     output_framebuffer: &'a X,
 
+    /// Their translucent meshes are drawn with `transparent`
     opaque: Vec<(&'a Cubic, usize /* animation */, f32 /* time */)>,
+    /// Every mesh drawn as translucent, whatever its material
     transparent: Vec<(&'a Cubic, usize /* animation */, f32 /* time */)>,
+    transparent_shadows: bool,
+    /// With `output_framebuffer` again, as the `Framebuffer` whose depth is
+    /// copied
+    weighted_blended: Option<(&'a WeightedBlended, &'a Framebuffer<2, WithDepth>)>,
+
+    viewport: Option<Viewport>,
 }
 
 impl<'a, X: FramebufferWithDepth<2, Tex = FlatTexture>> Group<'a, X> {
@@ -47,8 +58,107 @@ impl<'a, X: FramebufferWithDepth<2, Tex = FlatTexture>> Group<'a, X> {
             output_framebuffer,
             opaque,
             transparent,
+            transparent_shadows: false,
+            weighted_blended: None,
+            viewport: None,
         })
     }
+
+    /// Let translucent meshes cast shadows, as solid as opaque ones
+    pub fn transparent_shadows(mut self: Box<Self>, transparent_shadows: bool) -> Box<Self> {
+        self.transparent_shadows = transparent_shadows;
+        self
+    }
+
+    /// See `Viewport`
+    pub fn viewport(mut self: Box<Self>, viewport: Viewport) -> Box<Self> {
+        self.viewport = Some(viewport);
+        self
+    }
+
+    fn shader(
+        pass: Pass,
+        weighted_blended: bool,
+    ) -> &'static ShaderProgram<(Cubic, ShadowListLights<2>), 2, FlatTexture> {
+        match (pass, weighted_blended) {
+            (Pass::Skinned, false) => opengl_shaders::shadow_skinned(),
+            (Pass::Skinned, true) => opengl_shaders::shadow_oit_skinned(),
+            (_, false) => opengl_shaders::shadow(),
+            (_, true) => opengl_shaders::shadow_oit(),
+        }
+    }
+
+    fn bind<'b>(
+        &'b self,
+        active_shader: &mut ActiveShaderProgram<
+            '_,
+            '_,
+            'b,
+            (Cubic, ShadowListLights<2>),
+            FlatTexture,
+            2,
+        >,
+    ) {
+        // SAFETY: every active shader is dropped before the end of `draw`, so
+        // the references stored cannot leak
+        unsafe {
+            self.list_light.bind(active_shader, self.position);
+        }
+
+        active_shader.set_uniform("projtimesview".to_string(), self.look_at);
+        active_shader.set_uniform("camera_postion".to_string(), self.position.homogeneous());
+    }
+
+    /// Draw `translucent` in order into `framebuffer`, without writing depth,
+    /// either blended as usual or into the targets of `WeightedBlended`
+    fn draw_translucent<'b, F: FramebufferWithDepth<2, Tex = FlatTexture>>(
+        &'b self,
+        fb_context: &mut FramebufferContext,
+        sp_context: &mut ShaderProgramContext,
+        translucent: &[Translucent<'b>],
+        framebuffer: &F,
+        weighted_blended: bool,
+    ) -> Result<()> {
+        // Runs of meshes needing the same shader share one use of it
+        for run in translucent.chunk_by(|a, b| a.is_skinned() == b.is_skinned()) {
+            let pass = if run[0].is_skinned() {
+                Pass::Skinned
+            } else {
+                Pass::Rigid
+            };
+            let shader = Self::shader(pass, weighted_blended);
+            let render_state = if weighted_blended {
+                WeightedBlended::render_state(shader.render_state())
+            } else {
+                shader.render_state().depth_write(false)
+            };
+
+            let mut active_shadow_shader = shader.use_program(sp_context);
+            active_shadow_shader.render_state(render_state);
+            self.bind(&mut active_shadow_shader);
+
+            let mut active_framebuffer = framebuffer.bind_viewport(fb_context, self.viewport);
+            for mesh in run {
+                mesh.draw(&mut active_shadow_shader, &mut active_framebuffer, pass)?;
+            }
+
+            drop(active_shadow_shader);
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Group<'a, Framebuffer<2, WithDepth>> {
+    /// Blend translucent meshes in any order into the targets of
+    /// `weighted_blended` rather than sorting them
+    pub fn weighted_blended(
+        mut self: Box<Self>,
+        weighted_blended: &'a WeightedBlended,
+    ) -> Box<Self> {
+        self.weighted_blended = Some((weighted_blended, self.output_framebuffer));
+        self
+    }
 }
 
 impl<'a, X: FramebufferWithDepth<2, Tex = FlatTexture>> Draw for Group<'a, X> {
@@ -57,34 +167,86 @@ impl<'a, X: FramebufferWithDepth<2, Tex = FlatTexture>> Draw for Group<'a, X> {
         fb_context: &mut FramebufferContext,
         sp_context: &mut ShaderProgramContext,
     ) -> Result<()> {
+        fb_context.profile_section("shadow depth");
+        let casters: Vec<_> = if self.transparent_shadows {
+            iter::chain(&self.opaque, &self.transparent)
+                .map(|&(model, animation, time)| (model, Layer::Both, animation, time))
+                .collect()
+        } else {
+            (self.opaque.iter())
+                .map(|&(model, animation, time)| (model, Layer::Opaque, animation, time))
+                .collect()
+        };
         self.list_light
-            .gen_depth(sp_context, fb_context, &self.opaque, self.position)?;
+            .gen_depth(sp_context, fb_context, &casters, self.position)?;
 
         // At this point, all lights have their framebuffers filled with depth
         // information
-        let mut active_shadow_shader = opengl_shaders::shadow().use_program(sp_context);
-        // SAFETY: because active_shadow_shader is dropped before the end of this
-        // function, the references stored cannot leak
-        unsafe {
-            self.list_light
-                .bind(&mut active_shadow_shader, self.position);
+        fb_context.profile_section("main pass");
+        let passes = if self.opaque.iter().any(|(model, ..)| model.is_skinned()) {
+            &[Pass::Rigid, Pass::Skinned][..]
+        } else {
+            &[Pass::Rigid]
+        };
+
+        for &pass in passes {
+            let mut active_shadow_shader = Self::shader(pass, false).use_program(sp_context);
+            self.bind(&mut active_shadow_shader);
+
+            let mut active_output_framebuffer = self
+                .output_framebuffer
+                .bind_viewport(fb_context, self.viewport);
+            for &(model, animation, time) in &self.opaque {
+                model.draw(
+                    &mut active_shadow_shader,
+                    &mut active_output_framebuffer,
+                    pass,
+                    Layer::Opaque,
+                    animation,
+                    time,
+                )?;
+            }
+
+            drop(active_shadow_shader);
         }
 
-        active_shadow_shader.set_uniform("projtimesview".to_string(), self.look_at);
-        active_shadow_shader.set_uniform("camera_postion".to_string(), self.position.homogeneous());
-
-        let mut active_output_framebuffer = self.output_framebuffer.bind(fb_context);
-        for (model, animation, time) in iter::chain(self.opaque, self.transparent) {
-            model.draw(
-                &mut active_shadow_shader,
-                &mut active_output_framebuffer,
-                animation,
-                time,
-            )?;
+        fb_context.profile_section("transparent pass");
+        let mut translucent = back_to_front(&self.opaque, &self.transparent, self.position);
+        let Some((weighted_blended, scene)) = self.weighted_blended else {
+            return self.draw_translucent(
+                fb_context,
+                sp_context,
+                &translucent,
+                self.output_framebuffer,
+                false,
+            );
+        };
+        if translucent.is_empty() {
+            return Ok(());
         }
 
-        drop(active_shadow_shader);
+        // Any order blends the same, so only the shaders matter
+        translucent.sort_by_key(Translucent::is_skinned);
+        weighted_blended.begin(fb_context, scene, self.viewport);
+        self.draw_translucent(
+            fb_context,
+            sp_context,
+            &translucent,
+            weighted_blended.framebuffer(),
+            true,
+        )?;
 
-        Ok(())
+        fb_context.profile_section("transparent composite");
+        weighted_blended.composite(
+            fb_context,
+            sp_context,
+            opengl_shaders::oit_composite(),
+            self.output_framebuffer,
+            self.viewport,
+        )
+    }
+
+    fn name(&self) -> String {
+        "shadow".to_string()
     }
 }
@@ -45,10 +45,21 @@ impl Skeleton {
         Ok(())
     }
 
-    pub fn get_all_bones(&self, animation: usize, time: f32) -> Vec<Matrix<4, 4>> {
+    /// How many animations the bones have, the one beyond the last valid
+    /// animation index
+    pub fn animation_count(&self) -> usize {
         self.values
             .iter()
-            .map(|bone| bone.get_pose((&self.values, animation, time)).as_matrix())
+            .map(|bone| bone.animation.len())
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Every bone's pose after its inverse bind pose, by bone index. This is
+    /// the matrix palette that skinned meshes are moved by.
+    pub fn get_all_bones(&self, animation: usize, time: f32) -> Vec<Matrix<4, 4>> {
+        (0..self.values.len())
+            .map(|bone| self.get_pose((true, bone, animation, time)).as_matrix())
             .collect()
     }
 }
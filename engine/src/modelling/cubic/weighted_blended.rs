@@ -0,0 +1,105 @@
+use graphics::Result;
+use graphics::colour::ColourRGBA;
+use graphics::framebuffer::attachments::WithDepth;
+use graphics::framebuffer::traits::{FramebufferInternals, FramebufferWithDepth};
+use graphics::framebuffer::{Builder, Framebuffer, FramebufferContext, Viewport};
+use graphics::shader_program::{Blend, RenderState, ShaderProgram, ShaderProgramContext};
+use graphics::texture::FlatTexture;
+use graphics::types::TexDim;
+
+use super::lighting::simple::ListLights;
+use super::model::Cubic;
+use crate::modelling::Quad;
+
+/// The programs `CubicGroup` draws translucent meshes with when it has a
+/// `WeightedBlended`, such as `opengl_shaders::hdr_oit`
+#[derive(Debug, Clone, Copy)]
+pub struct Shaders<'a, const MAX: usize, const OUT: usize> {
+    /// Built with `WEIGHTED_BLENDED`, drawing into the two targets
+    pub shader: &'a ShaderProgram<(Cubic, ListLights<MAX>), 2, FlatTexture>,
+    /// Built with both `WEIGHTED_BLENDED` and `SKINNED`
+    pub skinned_shader: Option<&'a ShaderProgram<(Cubic, ListLights<MAX>), 2, FlatTexture>>,
+    /// Blends the targets over the group's framebuffer, such as
+    /// `opengl_shaders::oit_composite`
+    pub composite: &'a ShaderProgram<Quad<2>, OUT, FlatTexture>,
+}
+
+/// Targets for weighted blended order-independent transparency, which blends
+/// translucent meshes in any order instead of sorting them, so intersecting
+/// ones such as particles inside glass still look right. The result is an
+/// approximation favouring nearer and more opaque surfaces. It can only be
+/// used over a `Framebuffer<_, WithDepth>`, whose depth format its own depth
+/// buffer shares, and should be kept the size of it. The result is blended
+/// over the scene without a depth test, so anything behind, such as a
+/// skybox, has to be drawn first.
+#[derive(Debug)]
+pub struct WeightedBlended {
+    /// Accumulation in the first target and revealage in the second, tested
+    /// against a copy of the scene's depth
+    framebuffer: Framebuffer<2, WithDepth>,
+    /// Reads both targets to blend them over the scene
+    composite: Quad<2>,
+}
+
+impl WeightedBlended {
+    /// `size` should be that of the framebuffer it is composited into
+    #[must_use]
+    pub fn new(size: (TexDim, TexDim)) -> Self {
+        let mut framebuffer = Builder::new_flat().depth().size(size).build();
+        framebuffer.set_label("weighted blended");
+        let composite = Quad::screen(framebuffer.get_all_colour());
+
+        Self {
+            framebuffer,
+            composite,
+        }
+    }
+
+    /// The composite quad shares the targets' textures, so follows along
+    pub fn resize(&mut self, size: (TexDim, TexDim)) {
+        self.framebuffer.resize(size);
+    }
+
+    /// How the variants of the shaders built with `WEIGHTED_BLENDED` draw
+    /// into the targets, from the program's own state
+    pub(crate) fn render_state(render_state: RenderState) -> RenderState {
+        render_state
+            .depth_write(false)
+            .blend(Blend::WEIGHTED_BLENDED)
+    }
+
+    /// Empty the targets within `viewport` and take `scene`'s depth, before
+    /// any translucent mesh is drawn
+    pub(crate) fn begin<const OUT: usize>(
+        &self,
+        fb_context: &mut FramebufferContext,
+        scene: &Framebuffer<OUT, WithDepth>,
+        viewport: Option<Viewport>,
+    ) {
+        let mut active_framebuffer = self.framebuffer.bind_viewport(fb_context, viewport);
+        // Nothing accumulated and everything behind revealed
+        let empty = ColourRGBA::new([0.0, 0.0, 0.0, 1.0]);
+        active_framebuffer.clear_colour(0, empty);
+        active_framebuffer.clear_colour(1, empty);
+        active_framebuffer.copy_depth_from(scene);
+    }
+
+    /// Where the variants of the shaders built with `WEIGHTED_BLENDED` draw
+    pub(crate) fn framebuffer(&self) -> &Framebuffer<2, WithDepth> {
+        &self.framebuffer
+    }
+
+    /// Blend what the translucent meshes left in the targets over `scene`
+    pub(crate) fn composite<const OUT: usize, D: FramebufferWithDepth<OUT, Tex = FlatTexture>>(
+        &self,
+        fb_context: &mut FramebufferContext,
+        sp_context: &mut ShaderProgramContext,
+        shader: &ShaderProgram<Quad<2>, OUT, FlatTexture>,
+        scene: &D,
+        viewport: Option<Viewport>,
+    ) -> Result<()> {
+        let mut active_framebuffer = scene.bind_viewport(fb_context, viewport);
+        self.composite
+            .draw(shader.use_program(sp_context), &mut active_framebuffer)
+    }
+}
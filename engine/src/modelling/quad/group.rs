@@ -1,5 +1,5 @@
-use graphics::framebuffer::FramebufferContext;
 use graphics::framebuffer::traits::{FramebufferInternals, FramebufferWithoutExtra};
+use graphics::framebuffer::{FramebufferContext, Viewport};
 use graphics::shader_program::{ShaderProgram, ShaderProgramContext};
 use graphics::{Draw, Result};
 
@@ -11,6 +11,7 @@ pub struct Group<'a, const N: usize, const OUT: usize, D: FramebufferInternals<O
     framebuffer: &'a D,
 
     quads: Vec<&'a Quad<N>>,
+    viewport: Option<Viewport>,
 }
 
 impl<'a, const N: usize, const OUT: usize, X: FramebufferWithoutExtra<OUT>> Group<'a, N, OUT, X> {
@@ -24,8 +25,15 @@ impl<'a, const N: usize, const OUT: usize, X: FramebufferWithoutExtra<OUT>> Grou
             shader,
             framebuffer,
             quads,
+            viewport: None,
         })
     }
+
+    /// See `Viewport`
+    pub fn viewport(mut self: Box<Self>, viewport: Viewport) -> Box<Self> {
+        self.viewport = Some(viewport);
+        self
+    }
 }
 
 impl<'a, const N: usize, const OUT: usize, D: FramebufferWithoutExtra<OUT>> Draw
@@ -36,7 +44,7 @@ impl<'a, const N: usize, const OUT: usize, D: FramebufferWithoutExtra<OUT>> Draw
         register: &mut FramebufferContext,
         marker: &mut ShaderProgramContext,
     ) -> Result<()> {
-        let mut active_framebuffer = self.framebuffer.bind(register);
+        let mut active_framebuffer = self.framebuffer.bind_viewport(register, self.viewport);
 
         for quad in self.quads {
             let shader = self.shader.use_program(marker);
@@ -45,4 +53,8 @@ impl<'a, const N: usize, const OUT: usize, D: FramebufferWithoutExtra<OUT>> Draw
 
         Ok(())
     }
+
+    fn name(&self) -> String {
+        "quad".to_string()
+    }
 }
@@ -3,8 +3,8 @@ use std::cell::RefCell;
 use std::rc::Rc;
 
 use graphics::Result;
-use graphics::framebuffer::ActiveFramebuffer;
 use graphics::framebuffer::traits::FramebufferWithoutExtra;
+use graphics::framebuffer::{ActiveFramebuffer, DefaultFramebuffer};
 use graphics::shader_program::ActiveShaderProgram;
 use graphics::texture::{FlatTexture, Texture};
 use graphics::vertex_array::VertexArray;
@@ -40,6 +40,28 @@ impl<const N: usize> Quad<N> {
         Self::new(-1.0, 1.0, -1.0, 1.0, texture)
     }
 
+    /// A quad placed in screen coordinates from the bottom left of
+    /// `framebuffer`, so that UI keeps the same size on high-DPI displays.
+    /// Rebuild it on `Event::WindowResize` or `Event::ContentScale`.
+    pub fn logical(
+        (left, bottom): (f32, f32),
+        (width, height): (f32, f32),
+        framebuffer: &DefaultFramebuffer,
+        texture: [Rc<RefCell<FlatTexture>>; N],
+    ) -> Self {
+        let (screen_width, screen_height) = framebuffer.logical_size();
+        let x = |x: f32| x / screen_width * 2.0 - 1.0;
+        let y = |y: f32| y / screen_height * 2.0 - 1.0;
+
+        Self::new(
+            x(left),
+            x(left + width),
+            y(bottom),
+            y(bottom + height),
+            texture,
+        )
+    }
+
     pub fn downcast<const M: usize>(self) -> Quad<M> {
         let mut iter = self.texture.into_iter();
         let texture = array::from_fn(|_| iter.next().unwrap_or_default());
@@ -63,6 +85,7 @@ impl<const N: usize> Quad<N> {
 
         active_shader.register_texture(input);
 
-        self.vertex_array.draw(&active_shader, active_framebuffer)
+        self.vertex_array
+            .draw(&mut active_shader, active_framebuffer)
     }
 }
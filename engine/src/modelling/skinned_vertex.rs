@@ -0,0 +1,131 @@
+use std::mem;
+
+use graphics::linear_algebra::{UnitVector, Vector};
+use graphics::types::VertexAttrType;
+use graphics::vertex::{IncompleteVertex, Vertex};
+
+use super::SimpleVertex;
+
+/// The most joints one skinned mesh can follow, the length of the `joints`
+/// palette in `shaders/skinning/skinning.vert`
+pub const MAX_JOINTS: usize = 64;
+
+/// A `SimpleVertex` moved by up to four joints of its mesh
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SkinnedVertex {
+    // 0
+    pub position: Vector<3>,
+    // 1
+    pub texture: Vector<2>,
+    // 2
+    pub normal: UnitVector<3>,
+    // 3
+    pub tangent: UnitVector<3>,
+    // 4
+    /// Indices into the mesh's joints, stored as floats
+    pub joints: Vector<4>,
+    // 5
+    pub weights: Vector<4>,
+}
+
+impl SkinnedVertex {
+    /// Keeps the four heaviest `(joint, weight)` influences, scaled to sum to
+    /// 1. A vertex without any follows the first joint alone.
+    pub fn new(vertex: SimpleVertex, influences: &[(usize, f32)]) -> Self {
+        let mut heaviest: Vec<(usize, f32)> = influences
+            .iter()
+            .copied()
+            .filter(|&(_, weight)| weight > 0.0)
+            .collect();
+        heaviest.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        heaviest.truncate(4);
+        let total: f32 = heaviest.iter().map(|(_, weight)| weight).sum();
+
+        let mut joints = [0.0; 4];
+        let mut weights = [0.0; 4];
+        if heaviest.is_empty() {
+            weights[0] = 1.0;
+        }
+        for (index, (joint, weight)) in heaviest.into_iter().enumerate() {
+            joints[index] = joint as f32;
+            weights[index] = weight / total;
+        }
+
+        let SimpleVertex {
+            position,
+            texture,
+            normal,
+            tangent,
+        } = vertex;
+
+        Self {
+            position,
+            texture,
+            normal,
+            tangent,
+            joints: Vector::new(joints),
+            weights: Vector::new(weights),
+        }
+    }
+}
+
+impl Vertex for SkinnedVertex {
+    const ELEMENT_COUNT: usize = 6;
+
+    fn offsets() -> [usize; Self::ELEMENT_COUNT] {
+        [
+            mem::offset_of!(Self, position),
+            mem::offset_of!(Self, texture),
+            mem::offset_of!(Self, normal),
+            mem::offset_of!(Self, tangent),
+            mem::offset_of!(Self, joints),
+            mem::offset_of!(Self, weights),
+        ]
+    }
+
+    fn types_of() -> [(VertexAttrType, usize); Self::ELEMENT_COUNT] {
+        use VertexAttrType as V;
+        [
+            (V::f32, 3),
+            (V::f32, 2),
+            (V::f32, 3),
+            (V::f32, 3),
+            (V::f32, 4),
+            (V::f32, 4),
+        ]
+    }
+
+    fn from_incomplete_triangle(incomplete_triangle: &[IncompleteVertex; 3]) -> [Self; 3] {
+        SimpleVertex::from_incomplete_triangle(incomplete_triangle)
+            .map(|vertex| Self::new(vertex, &[]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn values(vector: Vector<4>) -> [f32; 4] {
+        [vector[0], vector[1], vector[2], vector[3]]
+    }
+
+    #[test]
+    fn test_heaviest_influences() {
+        let vertex = SimpleVertex::from_incomplete_triangle(&[IncompleteVertex::default(); 3])[0];
+
+        let skinned = SkinnedVertex::new(
+            vertex,
+            &[(1, 0.1), (2, 0.4), (3, 0.0), (4, 0.2), (5, 0.2), (6, 0.1)],
+        );
+        assert_eq!(values(skinned.joints), [2.0, 4.0, 5.0, 1.0]);
+        let expected = [0.4, 0.2, 0.2, 0.1].map(|weight| weight / 0.9);
+        for (weight, expected) in values(skinned.weights).into_iter().zip(expected) {
+            assert!((weight - expected).abs() < 1e-6);
+        }
+
+        let unweighted = SkinnedVertex::new(vertex, &[(3, 0.0)]);
+        assert_eq!(values(unweighted.joints), [0.0; 4]);
+        assert_eq!(values(unweighted.weights), [1.0, 0.0, 0.0, 0.0]);
+    }
+}
@@ -1,7 +1,7 @@
 use graphics::Draw;
 use graphics::error::Result;
-use graphics::framebuffer::FramebufferContext;
 use graphics::framebuffer::traits::FramebufferWithDepth;
+use graphics::framebuffer::{FramebufferContext, Viewport};
 use graphics::linear_algebra::Matrix;
 use graphics::shader_program::{ShaderProgram, ShaderProgramContext};
 
@@ -10,6 +10,8 @@ use crate::modelling::cubic::Camera;
 use crate::modelling::cubic::geometry::YieldsPose;
 // use crate::error::Result;
 
+/// Fills whatever the depth buffer leaves at the far plane, so it goes before
+/// any group with translucent meshes, which do not write depth
 #[derive(Debug)]
 pub struct Group<'a, const OUT: usize, D: FramebufferWithDepth<OUT>> {
     shader: &'a ShaderProgram<SkyBox, OUT, D::Tex>,
@@ -17,6 +19,7 @@ pub struct Group<'a, const OUT: usize, D: FramebufferWithDepth<OUT>> {
 
     skybox: &'a SkyBox,
     look_at: Matrix<4, 4>,
+    viewport: Option<Viewport>,
 }
 
 impl<'a, const OUT: usize, X: FramebufferWithDepth<OUT>> Group<'a, OUT, X> {
@@ -42,8 +45,15 @@ impl<'a, const OUT: usize, X: FramebufferWithDepth<OUT>> Group<'a, OUT, X> {
             framebuffer,
             skybox,
             look_at,
+            viewport: None,
         })
     }
+
+    /// See `Viewport`
+    pub fn viewport(mut self: Box<Self>, viewport: Viewport) -> Box<Self> {
+        self.viewport = Some(viewport);
+        self
+    }
 }
 
 impl<'a, const OUT: usize, D: FramebufferWithDepth<OUT>> Draw for Group<'a, OUT, D> {
@@ -52,7 +62,7 @@ impl<'a, const OUT: usize, D: FramebufferWithDepth<OUT>> Draw for Group<'a, OUT,
         register: &mut FramebufferContext,
         marker: &mut ShaderProgramContext,
     ) -> Result<()> {
-        let mut active_framebuffer = self.framebuffer.bind(register);
+        let mut active_framebuffer = self.framebuffer.bind_viewport(register, self.viewport);
         let mut active_shader = self.shader.use_program(marker);
 
         active_shader.drawing_skybox(true);
@@ -65,4 +75,8 @@ impl<'a, const OUT: usize, D: FramebufferWithDepth<OUT>> Draw for Group<'a, OUT,
 
         Ok(())
     }
+
+    fn name(&self) -> String {
+        "skybox".to_string()
+    }
 }
@@ -1,6 +1,5 @@
-use std::sync::LazyLock;
-
-use graphics::shader_program::{CullFace, ShaderProgram};
+use graphics::context_local::ContextLocal;
+use graphics::shader_program::{CullFace, DepthFunc, RenderState, ShaderProgram};
 use graphics::texture::{CubeMap, FlatTexture};
 
 use crate::modelling::cubic::lighting::shadow::ShadowListLights;
@@ -10,19 +9,23 @@ use crate::modelling::{Cubic, Quad, SkyBox};
 static ERROR_MESSAGE: &str = "Engine defined shader should exist and not have errors";
 
 macro_rules! make_included {
-    ($(: ($first:ident $(, $others:ident )*) ,)? $typ:ty, $fn_name:ident, $vertex:literal, $fragment:literal $(, $geometry:literal)? $(, cull_face: $cull_face:path)? $(,)?) => {
+    ($(: ($first:ident $(, $others:ident )*) ,)? $typ:ty, $fn_name:ident, $vertex:literal, $fragment:literal $(, $geometry:literal)? $(, define: $define:literal)* $(, vertex_prelude: $prelude:literal)? $(, cull_face: $cull_face:path)? $(, render_state: $render_state:expr)? $(,)?) => {
         //impl$(<$first $(, $others )*>)? $typ {
             pub fn $fn_name() -> &'static $typ {
-                static PROGRAM: LazyLock<$typ> = LazyLock::new(||
+                static PROGRAM: ContextLocal<$typ> = ContextLocal::new(||
                     ShaderProgram::builder()
+                        .label(stringify!($fn_name).to_string())
+                        $(.define($define))*
+                        $(.vertex_prelude($prelude).expect(ERROR_MESSAGE))?
                         .vertex_shader($vertex).expect(ERROR_MESSAGE)
                         .fragment_shader($fragment).expect(ERROR_MESSAGE)
                         $(.geometry_shader($geometry).expect(ERROR_MESSAGE))?
                         $(.force_cull_face($cull_face))?
+                        $(.render_state($render_state))?
                         .build()
                 );
 
-                &PROGRAM
+                PROGRAM.get()
             }
         //}
     };
@@ -42,6 +45,42 @@ make_included! {
     "shaders/hdr_tangent/hdr_tangent.frag",
 }
 
+make_included! {
+    ShaderProgram<(Cubic, ListLights<2>), 2, FlatTexture>,
+    hdr_skinned,
+    "shaders/hdr_tangent/hdr_tangent.vert",
+    "shaders/hdr_tangent/hdr_tangent.frag",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+}
+
+make_included! {
+    ShaderProgram<(Cubic, ListLights<2>), 1, FlatTexture>,
+    hdr_without_bright_skinned,
+    "shaders/hdr_tangent/hdr_tangent.vert",
+    "shaders/hdr_tangent/hdr_tangent.frag",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+}
+
+make_included! {
+    ShaderProgram<(Cubic, ListLights<2>), 2, FlatTexture>,
+    hdr_oit,
+    "shaders/hdr_tangent/hdr_tangent.vert",
+    "shaders/hdr_tangent/hdr_tangent.frag",
+    define: "WEIGHTED_BLENDED",
+}
+
+make_included! {
+    ShaderProgram<(Cubic, ListLights<2>), 2, FlatTexture>,
+    hdr_oit_skinned,
+    "shaders/hdr_tangent/hdr_tangent.vert",
+    "shaders/hdr_tangent/hdr_tangent.frag",
+    define: "WEIGHTED_BLENDED",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+}
+
 make_included! {
     ShaderProgram<SkyBox, 2, FlatTexture>,
     skybox_hdr,
@@ -70,6 +109,33 @@ make_included! {
     "shaders/hdr_tangent_shadow/hdr_tangent_shadow.frag",
 }
 
+make_included! {
+    ShaderProgram<(Cubic, ShadowListLights<2>), 2, FlatTexture>,
+    shadow_skinned,
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.vert",
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.frag",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+}
+
+make_included! {
+    ShaderProgram<(Cubic, ShadowListLights<2>), 2, FlatTexture>,
+    shadow_oit,
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.vert",
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.frag",
+    define: "WEIGHTED_BLENDED",
+}
+
+make_included! {
+    ShaderProgram<(Cubic, ShadowListLights<2>), 2, FlatTexture>,
+    shadow_oit_skinned,
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.vert",
+    "shaders/hdr_tangent_shadow/hdr_tangent_shadow.frag",
+    define: "WEIGHTED_BLENDED",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+}
+
 make_included! {
     ShaderProgram<(Cubic, ()), 0, FlatTexture>,
     far_light_depth,
@@ -78,6 +144,16 @@ make_included! {
     cull_face: CullFace::FrontFace,
 }
 
+make_included! {
+    ShaderProgram<(Cubic, ()), 0, FlatTexture>,
+    far_light_depth_skinned,
+    "shaders/depth_testing/farspot_light_depth/farspot_light_depth.vert",
+    "shaders/depth_testing/farspot_light_depth/farspot_light_depth.frag",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+    cull_face: CullFace::FrontFace,
+}
+
 make_included! {
     ShaderProgram<Quad<1>, 1, FlatTexture>,
     quad,
@@ -99,6 +175,26 @@ make_included! {
     "shaders/bloom/blur_y_merge/blur_y_merge.frag",
 }
 
+make_included! {
+    ShaderProgram<Quad<2>, 2, FlatTexture>,
+    oit_composite,
+    "shaders/oit_composite/oit_composite.vert",
+    "shaders/oit_composite/oit_composite.frag",
+    render_state: RenderState::default()
+        .depth_func(DepthFunc::Always)
+        .depth_write(false),
+}
+
+make_included! {
+    ShaderProgram<Quad<2>, 1, FlatTexture>,
+    oit_composite_without_bright,
+    "shaders/oit_composite/oit_composite.vert",
+    "shaders/oit_composite/oit_composite.frag",
+    render_state: RenderState::default()
+        .depth_func(DepthFunc::Always)
+        .depth_write(false),
+}
+
 make_included! {
     ShaderProgram<(Cubic<>, ()), 0, CubeMap>,
     point_depth,
@@ -107,3 +203,14 @@ make_included! {
     "shaders/depth_testing/point_light_depth/point_light_depth.geom",
     cull_face: CullFace::FrontFace,
 }
+
+make_included! {
+    ShaderProgram<(Cubic<>, ()), 0, CubeMap>,
+    point_depth_skinned,
+    "shaders/depth_testing/point_light_depth/point_light_depth.vert",
+    "shaders/depth_testing/point_light_depth/point_light_depth.frag",
+    "shaders/depth_testing/point_light_depth/point_light_depth.geom",
+    define: "SKINNED",
+    vertex_prelude: "shaders/skinning/skinning.vert",
+    cull_face: CullFace::FrontFace,
+}
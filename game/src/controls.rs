@@ -1,80 +1,170 @@
-use std::collections::HashSet;
+use std::path::Path;
 
-use engine::{Error, Key, Result};
+use engine::input_map::{AxisBinding, Binding, InputMap, MouseAxis};
+use engine::{Error, GamepadAxis, GamepadButton, Key, Result, WindowMode, trace};
 
 use crate::state::State;
 
+/// Where players can rebind the controls, written with the defaults on the
+/// first run
+pub const INPUT_MAP_PATH: &str = "controls.toml";
+
+/// How far the turning keys look per second, as a multiple of how far one
+/// pixel of mouse movement looks
+const TURN_SPEED: f32 = 500.0;
+
+pub fn default_input_map() -> InputMap {
+    let key = Binding::key;
+    let pad = Binding::gamepad;
+    let with_stick = |binding: AxisBinding, axis| AxisBinding {
+        gamepad: Some(axis),
+        ..binding
+    };
+
+    InputMap::new()
+        .bind("quit", key(Key::Escape))
+        .bind("toggle_bloom", key(Key::B))
+        .bind("toggle_bloom", pad(GamepadButton::ButtonLeftBumper))
+        .bind("toggle_weighted_blended", key(Key::O))
+        .bind("toggle_preview", key(Key::I))
+        .bind("next_animation", key(Key::Y))
+        .bind("next_animation", pad(GamepadButton::ButtonRightBumper))
+        .bind("print_profile", key(Key::P))
+        .bind("capture_trace", key(Key::T))
+        .bind("cycle_window_mode", key(Key::F11))
+        .bind_axis(
+            "move_forward",
+            with_stick(
+                AxisBinding::keys(key(Key::W), key(Key::S)),
+                GamepadAxis::LeftY,
+            ),
+        )
+        .bind_axis(
+            "move_right",
+            with_stick(
+                AxisBinding::keys(key(Key::D), key(Key::A)),
+                GamepadAxis::LeftX,
+            ),
+        )
+        .bind_axis(
+            "move_up",
+            AxisBinding {
+                positive: vec![key(Key::Space), pad(GamepadButton::ButtonA)],
+                negative: vec![
+                    key(Key::LeftShift),
+                    key(Key::RightShift),
+                    pad(GamepadButton::ButtonB),
+                ],
+                ..AxisBinding::default()
+            },
+        )
+        .bind_axis("exposure", AxisBinding::keys(key(Key::E), key(Key::R)))
+        .bind_axis(
+            "turn_right",
+            with_stick(
+                AxisBinding::keys(key(Key::Right), key(Key::Left)),
+                GamepadAxis::RightX,
+            ),
+        )
+        .bind_axis(
+            "turn_up",
+            with_stick(
+                AxisBinding::keys(key(Key::Up), key(Key::Down)),
+                GamepadAxis::RightY,
+            ),
+        )
+        .bind_axis("zoom_out", AxisBinding::keys(key(Key::J), key(Key::U)))
+        .bind_axis("zoom_scroll", AxisBinding::mouse(MouseAxis::ScrollY, -0.25))
+        .bind_axis("roll", AxisBinding::keys(key(Key::Comma), key(Key::Period)))
+        .bind_axis("look_x", AxisBinding::mouse(MouseAxis::X, -1.0))
+        .bind_axis("look_y", AxisBinding::mouse(MouseAxis::Y, 1.0))
+}
+
+/// Load the player's bindings, with whether defaults were added that the
+/// file lacks. Without a file the defaults are saved as one, but a file that
+/// does not parse is left for the player to fix.
+pub fn load_input_map() -> (InputMap, bool) {
+    match InputMap::load(INPUT_MAP_PATH) {
+        Ok(loaded) => {
+            let input_map = loaded.clone().or_defaults(default_input_map());
+            let added = input_map != loaded;
+            (input_map, added)
+        }
+        Err(error) => {
+            log::warn!("using default controls: {error:?}");
+            let input_map = default_input_map();
+            if !Path::new(INPUT_MAP_PATH).exists()
+                && let Err(error) = input_map.save(INPUT_MAP_PATH)
+            {
+                log::warn!("{error:?}");
+            }
+            (input_map, false)
+        }
+    }
+}
+
 impl State {
-    pub fn controls(
-        &mut self,
-        mouse_delta: (f32, f32),
-        keyboard: HashSet<Key>,
-        typing_string: String,
-        frame_time: f32,
-    ) -> Result<()> {
-        if keyboard.contains(&Key::Escape) {
+    pub fn controls(&mut self) -> Result<()> {
+        if self.actions.is_held("quit") {
             return Err(Error::Close);
         }
-        if keyboard.contains(&Key::W) {
-            self.camera.pose.move_forward(self.speed[2] * frame_time);
-        }
-        if keyboard.contains(&Key::A) {
-            self.camera.pose.move_left(self.speed[0] * frame_time);
-            // self.camera.move_right(-self.speed[0] * frame_time);
-        }
-        if keyboard.contains(&Key::D) {
-            self.camera.pose.move_left(-self.speed[0] * frame_time)
-            // self.camera.move_right(self.speed[0] * frame_time);
-        }
-        if typing_string.to_lowercase().contains('b') {
+        if self.actions.is_pressed("toggle_bloom") {
             self.do_bloom = !self.do_bloom;
         }
-        if keyboard.contains(&Key::S) {
-            self.camera.pose.move_forward(-self.speed[2] * frame_time);
-        }
-        if keyboard.contains(&Key::LeftShift) | keyboard.contains(&Key::RightShift) {
-            self.camera.pose.move_up(-self.speed[2] * frame_time);
+        if self.actions.is_pressed("toggle_preview") {
+            self.show_preview = !self.show_preview;
         }
-        if keyboard.contains(&Key::Space) {
-            self.camera.pose.move_up(self.speed[2] * frame_time);
+        if self.actions.is_pressed("toggle_weighted_blended") {
+            self.do_weighted_blended = !self.do_weighted_blended;
         }
-        if keyboard.contains(&Key::R) {
-            self.exposure -= 1.2 * frame_time;
+        if self.actions.is_pressed("next_animation") {
+            let count = self.imported.skeleton.animation_count().max(1);
+            self.which_animation = (self.which_animation + 1) % count;
         }
-        if keyboard.contains(&Key::E) {
-            self.exposure += 1.2 * frame_time;
+        if self.actions.is_pressed("print_profile")
+            && let Some(profile) = &self.last_profile
+        {
+            log::info!("{profile}");
         }
-        if keyboard.contains(&Key::Right) {
-            self.camera
-                .pose
-                .look_left(-self.sensitivity * frame_time * 500.0);
+        if self.actions.is_pressed("capture_trace") {
+            trace::capture_next_frame("frame_trace.json");
         }
-        if keyboard.contains(&Key::Left) {
-            self.camera
-                .pose
-                .look_left(self.sensitivity * frame_time * 500.0);
-        }
-        if keyboard.contains(&Key::U) {
-            self.camera.radius_out(-0.5 * frame_time);
-        }
-        if keyboard.contains(&Key::J) {
-            self.camera.radius_out(0.5 * frame_time);
-        }
-        if typing_string.contains('y') {
-            self.which_animation = (self.which_animation + 1) % 2
-        }
-        if keyboard.contains(&Key::Comma) {
-            self.camera.pose.roll_ccw(frame_time);
-        }
-        if keyboard.contains(&Key::Period) {
-            self.camera.pose.roll_ccw(-frame_time);
+        if self.actions.is_pressed("cycle_window_mode") {
+            self.requested_window_mode = Some(match self.window_mode {
+                WindowMode::Windowed => WindowMode::Borderless { monitor: 0 },
+                WindowMode::Borderless { .. } => WindowMode::Fullscreen {
+                    monitor: 0,
+                    video_mode: None,
+                },
+                WindowMode::Fullscreen { .. } => WindowMode::Windowed,
+            });
         }
 
+        self.camera.radius_out(self.actions.axis("zoom_scroll"));
+        self.camera
+            .pose
+            .look_left(self.actions.axis("look_x") * self.sensitivity);
         self.camera
             .pose
-            .look_left(-mouse_delta.0 * self.sensitivity);
-        self.camera.pose.look_up(mouse_delta.1 * self.sensitivity);
+            .look_up(self.actions.axis("look_y") * self.sensitivity);
 
         Ok(())
     }
+
+    /// Apply held actions over one fixed update of `timestep` seconds
+    pub fn movement(&mut self, timestep: f32) {
+        let actions = &self.actions;
+        let pose = &mut self.camera.pose;
+
+        pose.move_forward(actions.axis("move_forward") * self.speed[2] * timestep);
+        pose.move_left(-actions.axis("move_right") * self.speed[0] * timestep);
+        pose.move_up(actions.axis("move_up") * self.speed[2] * timestep);
+        pose.look_left(-actions.axis("turn_right") * self.sensitivity * TURN_SPEED * timestep);
+        pose.look_up(actions.axis("turn_up") * self.sensitivity * TURN_SPEED * timestep);
+        pose.roll_ccw(actions.axis("roll") * timestep);
+
+        self.exposure += actions.axis("exposure") * 1.2 * timestep;
+        self.camera
+            .radius_out(actions.axis("zoom_out") * 0.5 * timestep);
+    }
 }
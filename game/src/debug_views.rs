@@ -0,0 +1,33 @@
+use engine::modelling::Quad;
+
+use crate::state::State;
+
+impl State {
+    /// A grid of quads over the whole window showing each shadow map, then
+    /// the HDR scene and its bright pass
+    pub fn make_debug_views(&self) -> Vec<Quad<1>> {
+        let textures: Vec<_> = self
+            .light_group
+            .far
+            .iter()
+            .map(|light| light.depth_map())
+            .chain(self.light_group.spot.iter().map(|light| light.depth_map()))
+            .chain(self.hdr_fb.get_all_colour())
+            .collect();
+
+        let columns = (textures.len() as f32).sqrt().ceil().max(1.0) as usize;
+        let rows = textures.len().div_ceil(columns);
+        let width = 2.0 / columns as f32;
+        let height = 2.0 / rows as f32;
+
+        textures
+            .into_iter()
+            .enumerate()
+            .map(|(index, texture)| {
+                let left = -1.0 + (index % columns) as f32 * width;
+                let top = 1.0 - (index / columns) as f32 * height;
+                Quad::new(left, left + width, top - height, top, [texture])
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,42 @@
+use std::env;
+
 use engine::Environment;
 
 mod controls;
+mod debug_views;
 mod new;
 mod state;
 
 fn main() {
-    Environment::<state::State>::new((3, 3), (1920, 1080), "Window", true)
-        .unwrap()
-        .run()
+    env_logger::init();
+
+    let mut environment = Environment::<state::State>::new((3, 3), (1920, 1080), "Window", true)
         .unwrap()
+        .vsync(true)
+        .fixed_timestep(state::TIMESTEP)
+        .profiling(true);
+
+    // `--record <file>` saves this session's input, `--replay <file>` plays
+    // one back and `--debug-views` opens a window showing the shadow maps and
+    // HDR buffers
+    let usage = "usage: game [--record <file>] [--replay <file>] [--debug-views]";
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--record" => {
+                let path = args.next().expect(usage);
+                environment = environment.record_input(path).unwrap();
+            }
+            "--replay" => {
+                let path = args.next().expect(usage);
+                environment = environment.replay_input(path).unwrap();
+            }
+            "--debug-views" => {
+                environment.add_window("Debug views", (960, 540)).unwrap();
+            }
+            _ => panic!("{usage}"),
+        }
+    }
+
+    environment.run().unwrap()
 }
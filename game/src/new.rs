@@ -3,6 +3,7 @@ use std::rc::Rc;
 
 use engine::array_vec::ArrayVec;
 use engine::framebuffer::Builder;
+use engine::input_map::Actions;
 use engine::linear_algebra::{UnitVector, Vector};
 use engine::modelling::cubic::camera;
 use engine::modelling::cubic::camera::CameraPose;
@@ -15,24 +16,27 @@ use engine::modelling::cubic::lighting::shadow::{
 };
 use engine::modelling::cubic::lighting::simple::{FarLight, ListLights, PointLight, SpotLight};
 use engine::modelling::cubic::material::Material;
-use engine::modelling::{Bloom, Bone, Cubic, Quad, Skeleton, SkyBox};
+use engine::modelling::{Bloom, Bone, Cubic, Quad, Skeleton, SkyBox, WeightedBlended};
 use engine::shader_program::CullFace;
 use engine::texture::{CubeMap, FlatTexture, TextureHasBuilder};
 use engine::types::TexDim;
-use engine::{ColourRGB, ColourRGBA, Error, Result};
+use engine::{ColourRGB, ColourRGBA, Error, Result, WindowMode};
 
+use crate::controls;
 use crate::state::State;
 
 impl State {
     pub fn new_(size: (TexDim, TexDim)) -> Result<Self> {
         let speed = [1.0, 1.0, 1.0];
+        let (input_map, input_map_changed) = controls::load_input_map();
         let exposure = 1.0;
 
         let screen_dims = size;
 
         let sensitivity = 0.001;
 
-        let hdr_fb = Builder::new_flat().depth().size(screen_dims).build();
+        let mut hdr_fb = Builder::new_flat().depth().size(screen_dims).build();
+        hdr_fb.set_label("hdr");
 
         let skybox = SkyBox::new(
             CubeMap::builder()
@@ -299,11 +303,19 @@ impl State {
             .scale(0.2)
             .build();
 
+        let render_camera = camera.clone();
+
         Ok(Self {
             time: 0.0,
+            previous_time: 0.0,
             string,
 
+            previous_position: camera.pose.position(),
             camera,
+            render_camera,
+            input_map,
+            input_map_changed,
+            actions: Actions::default(),
             exposure,
             sensitivity,
             speed,
@@ -317,7 +329,15 @@ impl State {
 
             hdr_fb,
             do_bloom: false,
+            preview: None,
+            show_preview: false,
+            do_weighted_blended: false,
+            paused: false,
+            window_mode: WindowMode::Windowed,
+            requested_window_mode: None,
+            last_profile: None,
             bloom,
+            weighted_blended: WeightedBlended::new(screen_dims),
 
             light_group: ShadowListLights {
                 point: ArrayVec::try_from([point_light])
@@ -332,6 +352,7 @@ impl State {
             },
 
             quad_to_draw,
+            debug_views: Vec::new(),
         })
     }
 }
@@ -1,7 +1,9 @@
 use std::collections::HashSet;
+use std::path::PathBuf;
 
 use engine::framebuffer::attachments::WithDepth;
 use engine::framebuffer::{DefaultFramebuffer, Framebuffer};
+use engine::input_map::{Actions, InputMap, InputSnapshot};
 use engine::linear_algebra::Vector;
 use engine::modelling::cubic::Camera;
 use engine::modelling::cubic::camera::{CameraPose, Projection};
@@ -19,23 +21,60 @@ use engine::modelling::{
     ShadowGroup,
     SkyBox,
     SkyBoxGroup,
+    WeightedBlended,
 };
+use engine::profiler::FrameProfile;
 use engine::shader_program::ShaderProgram;
 use engine::types::TexDim;
-use engine::{Draw, Event, GlobalState, Result};
+use engine::{Draw, Event, GamepadEvent, GlobalState, Result, WindowId, WindowMode};
+
+use crate::controls;
+
+/// Seconds simulated by each `fixed_update`
+pub const TIMESTEP: f64 = 1.0 / 120.0;
+
 pub struct State {
     pub string: String,
+    /// Simulated time, advanced by `fixed_update`
     pub time: f32,
+    pub previous_time: f32,
+    /// Simulated camera, moved by `fixed_update`
     pub camera: Camera<CameraPose>,
+    pub previous_position: Vector<3>,
+    /// `camera` blended between the last two fixed updates
+    pub render_camera: Camera<CameraPose>,
+    pub input_map: InputMap,
+    /// Bindings were added or rebound since `controls.toml` was read, so it
+    /// is rewritten on shutdown
+    pub input_map_changed: bool,
+    /// Actions resolved during the last `poll`, applied in `fixed_update`
+    pub actions: Actions,
 
     pub exposure: f32,
     pub sensitivity: f32,
 
     pub do_bloom: bool,
+    /// Blend translucent meshes with `weighted_blended` instead of sorting
+    pub do_weighted_blended: bool,
+    /// Set while the window is unfocused or minimised, stopping `time`
+    pub paused: bool,
+    /// As reported by the `Environment`, which may refuse a request
+    pub window_mode: WindowMode,
+    /// Picked up by the `Environment` before the next `poll`
+    pub requested_window_mode: Option<WindowMode>,
+    /// Most recent GPU timings, printed with 'p'
+    pub last_profile: Option<FrameProfile>,
 
     pub speed: [f32; 3],
 
     pub quad_to_draw: Quad<1>,
+    /// The bright pass inset at a fixed size in screen coordinates, made
+    /// when first shown and after the window's size or content scale changes
+    pub preview: Option<Quad<1>>,
+    pub show_preview: bool,
+    /// Made in the debug window's context, as quads cannot be drawn in
+    /// another
+    pub debug_views: Vec<Quad<1>>,
 
     pub light_group: ShadowListLights<SHADOW_SHADER_MAX_LIGHTS>,
     pub ns_light_group: ListLights<SHADOW_SHADER_MAX_LIGHTS>,
@@ -50,6 +89,7 @@ pub struct State {
     pub hdr_fb: Framebuffer<2, WithDepth>,
 
     pub bloom: Bloom,
+    pub weighted_blended: WeightedBlended,
 }
 
 impl GlobalState for State {
@@ -58,58 +98,207 @@ impl GlobalState for State {
         events: Vec<Event>,
         default_framebuffer: &'a DefaultFramebuffer,
     ) -> Result<Vec<Box<dyn Draw + 'a>>> {
-        let mut frame_time = 0.0;
+        let mut alpha = 1.0;
         let mut keyboard = HashSet::new();
+        let mut mouse_buttons = HashSet::new();
         let mut mouse_delta = (0.0, 0.0);
-        let mut typing_string = String::new();
+        let mut scroll = (0.0, 0.0);
+        let mut inputs = Vec::new();
+        let mut gamepads = Vec::new();
 
         for event in events {
             match event {
                 Event::CriticalFault => return Err(engine::Error::Close),
-                Event::FrameTime(ft) => frame_time = ft as f32,
-                Event::ActualTime(at) => self.time = at as f32,
-                Event::WindowResize(size) => {
-                    self.hdr_fb.resize(size);
-                    // self.bloom.resize(size);
-                    self.camera.projection = Projection::Perspective {
-                        fov: (90.0_f32).to_radians(),
-                        aspect: self.hdr_fb.aspect_ratio(),
-                        near: 0.1,
-                        far: 100.0,
-                    };
-                }
+                Event::FrameTime(_) | Event::ActualTime(_) => {}
+                Event::Interpolation(a) => alpha = a as f32,
                 Event::Keyboard(kb) => keyboard = kb,
-                Event::TextBuffer(string) => typing_string = string,
+                Event::TextBuffer(string) => self.string.push_str(&string),
+                Event::Input(input) => inputs.push(input),
                 Event::Mouse {
-                    buttons: _,
+                    buttons,
                     position: _,
                     delta,
                 } => {
-                    mouse_delta = (delta.0 as f32, delta.1 as f32);
+                    mouse_buttons = buttons;
+                    mouse_delta = delta;
+                }
+                Event::Gamepad(GamepadEvent::State(gamepad)) => gamepads.push(gamepad),
+                Event::Gamepad(GamepadEvent::Connected { id, name }) => {
+                    log::info!("controller {id} connected: {name}");
                 }
+                Event::Gamepad(GamepadEvent::Disconnected { id }) => {
+                    log::info!("controller {id} disconnected");
+                }
+                Event::Scroll((x, y)) => {
+                    scroll.0 += x;
+                    scroll.1 += y;
+                }
+                Event::Minimised => self.paused = true,
+                Event::Restored => self.paused = false,
+                // Also handled by `resized`
+                Event::WindowResize(_) | Event::ContentScale(_) => self.preview = None,
+                // Handled by `focus_changed`
+                Event::FocusLost | Event::FocusGained => {}
+                Event::CursorEnter | Event::CursorLeave => {}
+                Event::Monitors(monitors) => {
+                    for monitor in monitors {
+                        log::info!("monitor {}: {}", monitor.index, monitor.name);
+                    }
+                }
+                Event::FilesDropped(paths) => self.load_dropped(paths),
+                Event::Profile(profile) => self.last_profile = Some(profile),
             }
         }
 
-        // println!("framerate: {}", 1.0 / frame_time);
+        self.actions = self.input_map.resolve(InputSnapshot {
+            keys: &keyboard,
+            buttons: &mouse_buttons,
+            mouse_delta,
+            scroll,
+            gamepads: &gamepads,
+            events: &inputs,
+        });
 
-        self.string.push_str(&typing_string);
+        self.controls()?;
 
-        self.controls(mouse_delta, keyboard, typing_string, frame_time)?;
+        let time = self.previous_time + (self.time - self.previous_time) * alpha;
+        self.interpolate_camera(alpha);
+        self.physics(time);
 
-        self.physics(frame_time);
-
-        self.prep_draw(default_framebuffer)
+        self.prep_draw(default_framebuffer, time)
     }
 
     fn new(initial_size: (TexDim, TexDim)) -> Result<Self> {
         Self::new_(initial_size)
     }
+
+    fn fixed_update(&mut self, timestep: f64) -> Result<()> {
+        let timestep = timestep as f32;
+
+        self.previous_time = self.time;
+        self.previous_position = self.camera.pose.position();
+        if self.paused {
+            return Ok(());
+        }
+
+        self.time += timestep;
+        self.movement(timestep);
+
+        Ok(())
+    }
+
+    fn resized(&mut self, size: (TexDim, TexDim)) -> Result<()> {
+        self.hdr_fb.resize(size);
+        self.weighted_blended.resize(size);
+        // self.bloom.resize(size);
+        self.camera.projection = Projection::Perspective {
+            fov: (90.0_f32).to_radians(),
+            aspect: self.hdr_fb.aspect_ratio(),
+            near: 0.1,
+            far: 100.0,
+        };
+        Ok(())
+    }
+
+    fn focus_changed(&mut self, focused: bool) {
+        self.paused = !focused;
+    }
+
+    /// Keep any bindings added since `controls.toml` was written
+    fn shutdown(&mut self) {
+        if self.input_map_changed
+            && let Err(error) = self.input_map.save(controls::INPUT_MAP_PATH)
+        {
+            log::warn!("{error:?}");
+        }
+    }
+
+    fn window_mode(&mut self) -> Option<WindowMode> {
+        self.requested_window_mode.take()
+    }
+
+    fn window_mode_changed(&mut self, mode: WindowMode) {
+        self.window_mode = mode;
+    }
+
+    fn init_window(&mut self, _window: WindowId) -> Result<()> {
+        self.debug_views = self.make_debug_views();
+        Ok(())
+    }
+
+    fn draw_window<'a>(
+        &'a mut self,
+        _window: WindowId,
+        default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+        let mut out: Vec<Box<dyn Draw>> = Vec::new();
+
+        out.push(QuadGroup::new(
+            engine::opengl_shaders::quad(),
+            default_framebuffer,
+            self.debug_views.iter().collect(),
+        ));
+
+        Ok(out)
+    }
+
+    fn close_window(&mut self, _window: WindowId) {
+        self.debug_views.clear();
+    }
 }
 
 impl State {
-    fn physics(&mut self, _frame_time: f32) {
-        let time = self.time;
+    /// Replace the imported model with the first `.obj`, `.gltf` or `.glb`
+    /// dropped on the window
+    fn load_dropped(&mut self, paths: Vec<PathBuf>) {
+        use engine::PostProcess as P;
+
+        let extension = |path: &PathBuf| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .map(str::to_ascii_lowercase)
+        };
+        let Some((path, extension)) = paths.iter().find_map(|path| {
+            extension(path)
+                .filter(|ext| ["obj", "gltf", "glb"].contains(&ext.as_str()))
+                .map(|ext| (path, ext))
+        }) else {
+            return;
+        };
 
+        let imported = if extension == "obj" {
+            let post_process = vec![
+                P::Triangulate,
+                P::GenerateNormals,
+                P::CalculateTangentSpace,
+                P::FlipUVs,
+                P::OptimizeGraph,
+                P::OptimizeMeshes,
+            ];
+            Cubic::import(path, post_process)
+        } else {
+            Cubic::import_gltf(path)
+        };
+
+        match imported {
+            Ok(builder) => {
+                self.imported = builder.build();
+                self.which_animation = 0;
+            }
+            Err(error) => log::warn!("could not load {}: {error}", path.display()),
+        }
+    }
+
+    fn interpolate_camera(&mut self, alpha: f32) {
+        let current = self.camera.pose.position();
+        let mut position = self.previous_position;
+        position += (current - self.previous_position).scale(alpha);
+
+        self.render_camera = self.camera.clone();
+        self.render_camera.pose.set_position(position);
+    }
+
+    fn physics(&mut self, time: f32) {
         let light_pos = Vector::new([
             5.0 * (time / 10.0).sin(),
             5.0 * (time / 10.0).sin(),
@@ -128,17 +317,16 @@ impl State {
         }
 
         if let Some(light) = self.light_group.spot.get_mut(0) {
-            light.light.direction = self.camera.direction(());
-            light.light.position = self.camera.position(());
+            light.light.direction = self.render_camera.direction(());
+            light.light.position = self.render_camera.position(());
         }
     }
 
     fn prep_draw<'a>(
         &'a mut self,
         default_framebuffer: &'a DefaultFramebuffer,
+        time: f32,
     ) -> Result<Vec<Box<dyn Draw + 'a>>> {
-        let time = self.time;
-
         let mut out: Vec<Box<dyn Draw>> = Vec::new();
 
         let all_models = {
@@ -151,23 +339,30 @@ impl State {
 
         let transparent_models = vec![]; // vec![(&self.light, time)];
 
-        out.push(ShadowGroup::new(
-            &self.camera,
-            (),
-            &self.light_group,
-            all_models,
-            transparent_models,
-            &self.hdr_fb,
-        ));
-
+        // The sky goes first, as translucent meshes leave no depth for it to
+        // be hidden behind and the weighted blended result covers everything
         out.push(SkyBoxGroup::new(
             engine::opengl_shaders::skybox_hdr(),
             &self.hdr_fb,
             &self.skybox,
-            &self.camera,
+            &self.render_camera,
             (),
         ));
 
+        let shadow_group = ShadowGroup::new(
+            &self.render_camera,
+            (),
+            &self.light_group,
+            all_models,
+            transparent_models,
+            &self.hdr_fb,
+        );
+        out.push(if self.do_weighted_blended {
+            shadow_group.weighted_blended(&self.weighted_blended)
+        } else {
+            shadow_group
+        });
+
         if self.do_bloom {
             // println!("bloom_on");
             out.push(BloomGroup::new(default_framebuffer, &self.bloom));
@@ -180,6 +375,18 @@ impl State {
             ));
         }
 
+        if self.show_preview {
+            let bright = self.hdr_fb.get_colour(1).expect("hdr has two targets");
+            let preview = self.preview.get_or_insert_with(|| {
+                Quad::logical((16.0, 16.0), (320.0, 180.0), default_framebuffer, [bright])
+            });
+            out.push(QuadGroup::new(
+                engine::opengl_shaders::quad(),
+                default_framebuffer,
+                vec![&*preview],
+            ));
+        }
+
         Ok(out)
     }
 }
@@ -7,5 +7,9 @@ uniform mat4 projtimesview;
 
 void main()
 {
-    gl_Position = projtimesview * model * vec4(in_position, 1.0);
+    mat4 world = model;
+#ifdef SKINNED
+    world = model * skin();
+#endif
+    gl_Position = projtimesview * world * vec4(in_position, 1.0);
 }  
\ No newline at end of file
@@ -6,5 +6,9 @@ uniform mat4 model;
 
 void main()
 {
-    gl_Position = model * vec4(in_position, 1.0);
+    mat4 world = model;
+#ifdef SKINNED
+    world = model * skin();
+#endif
+    gl_Position = world * vec4(in_position, 1.0);
 }  
\ No newline at end of file
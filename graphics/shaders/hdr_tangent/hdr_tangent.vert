@@ -43,16 +43,20 @@ out vec2 texture_coord;
 
 
 void main() {
+    mat4 world = model;
+#ifdef SKINNED
+    world = model * skin();
+#endif
     texture_coord = in_texture_coord;
     
     // Normal matrix adjusts normals after non-uniform transformation
-    mat3 normal_matrix = mat3(transpose(inverse(model))); // TODO: replace with CPU-side calc
-    //mat3 normal_matrix = mat3(model);
+    mat3 normal_matrix = mat3(transpose(inverse(world))); // TODO: replace with CPU-side calc
+    //mat3 normal_matrix = mat3(world);
     vec3 world_normal = normal_matrix * normalize(in_normal);
-    vec3 world_tangent = mat3(model) * normalize(in_tangent); // unsure if this is the right correction matrix
+    vec3 world_tangent = mat3(world) * normalize(in_tangent); // unsure if this is the right correction matrix
 
     // Vertex Position in world space
-    vec4 vertex_position = model * vec4(in_position, 1.0);
+    vec4 vertex_position = world * vec4(in_position, 1.0);
 
     // Vertex position in screen space
     gl_Position = projtimesview * vertex_position;
@@ -13,8 +13,19 @@ struct Material {
     sampler2D emission;
     sampler2D normal_map; // dictates the direction, in tangent space, of the normal
     sampler2D ambient_occlusion;
+    sampler2D opacity_map; // red is the opacity
+    sampler2D metallic_roughness; // green is the roughness, blue the metallic
+
+    // Multiply their maps
+    vec4 diffuse_colour;
+    vec3 emission_colour;
+    float metallic;
+    float roughness;
+    float normal_scale;
+    float occlusion_strength;
 
     float shininess;
+    bool metallic_roughness_shading; // Cook-Torrance instead of Blinn-Phong
 };
 
 uniform Material material;
@@ -102,17 +113,29 @@ float calculate_shadow(mat4 matrix, vec4 frag_pos_light_space, sampler2D depth_m
 float calculate_shadow_point(vec3 frag_to_light, samplerCube depth, float far_plane);
 
 GenericOutput generic_light(GenericLight);
+GenericOutput cook_torrance(GenericLight);
 float attenuation(vec3);
 vec4 PointLight_illuminate(PointLight, PointLightVarying);
 vec4 FarLight_illuminate(FarLight, FarLightVarying);
 vec4 SpotLight_illuminate(SpotLight, SpotLightVarying);
 
 
-vec4 diffuse_map = texture(material.diffuse, texture_coord);
+vec4 diffuse_map = texture(material.diffuse, texture_coord)
+    * material.diffuse_colour
+    * vec4(1.0, 1.0, 1.0, texture(material.opacity_map, texture_coord).r);
 vec4 specular_map = texture(material.specular_map, texture_coord);
-vec4 emission = texture(material.emission, texture_coord);
-vec3 ambient_occlusion = texture(material.ambient_occlusion, texture_coord).rgb;
-vec3 normal = normalize(texture(material.normal_map, texture_coord).rgb * 2.0 - 1.0);
+vec4 emission = texture(material.emission, texture_coord) * vec4(material.emission_colour, 1.0);
+vec3 ambient_occlusion = mix(
+    vec3(1.0),
+    texture(material.ambient_occlusion, texture_coord).rgb,
+    material.occlusion_strength
+);
+vec3 normal = normalize(
+    (texture(material.normal_map, texture_coord).rgb * 2.0 - 1.0)
+    * vec3(material.normal_scale, material.normal_scale, 1.0)
+);
+vec2 metallic_roughness = texture(material.metallic_roughness, texture_coord).bg
+    * vec2(material.metallic, material.roughness);
 
 void main() {
     
@@ -139,15 +162,30 @@ void main() {
 
     //colour = vec4(frag_pos_world_space, 1.0);
 
+#ifdef WEIGHTED_BLENDED
+    // Accumulation and revealage targets for `Blend::WEIGHTED_BLENDED`, where
+    // colours sum and alphas multiply. The weight favours nearer and more
+    // opaque fragments (McGuire and Bavoil 2013, equation 10).
+    float weight = clamp(colour.a * max(1e-2, 3e3 * pow(1.0 - gl_FragCoord.z, 3.0)), 1e-2, 3e3);
+    frag_colour = vec4(colour.rgb * colour.a * weight, colour.a);
+    // The summed weights ride along in red, the alpha becoming the revealage
+    bright_colour = vec4(colour.a * weight, 0.0, 0.0, colour.a);
+#else
     frag_colour = colour;
     if (dot(colour.rgb, vec3(0.2126, 0.7152, 0.0722)) > 1.0) {
         bright_colour = vec4(colour.rgb, 1.0);
     } else {
         bright_colour = vec4(0.0, 0.0, 0.0, 1.0);
     }
+#endif
+}
 }
 
 GenericOutput generic_light(GenericLight light) {
+    if (material.metallic_roughness_shading) {
+        return cook_torrance(light);
+    }
+
     // Ambient
     vec4 ambient = vec4(light.ambient, 1.0) * diffuse_map * vec4(ambient_occlusion, 1.0);
     
@@ -173,6 +211,56 @@ GenericOutput generic_light(GenericLight light) {
     );
 }
 
+const float PI = 3.14159265359;
+
+// Trowbridge-Reitz GGX normal distribution
+float distribution_ggx(float n_dot_h, float roughness) {
+    float a = roughness * roughness;
+    float a2 = a * a;
+    float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * denominator * denominator);
+}
+
+// Schlick-GGX masking of one direction, k remapped for direct light
+float geometry_schlick_ggx(float n_dot_x, float roughness) {
+    float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+GenericOutput cook_torrance(GenericLight light) {
+    vec3 albedo = diffuse_map.rgb;
+    float metallic = clamp(metallic_roughness.x, 0.0, 1.0);
+    // Fully smooth surfaces have an infinitely sharp highlight
+    float roughness = clamp(metallic_roughness.y, 0.04, 1.0);
+
+    vec3 view_dir = normalize(tangent_view_direction);
+    vec3 halfway_dir = normalize(light.light_dir + view_dir);
+    float n_dot_l = max(dot(normal, light.light_dir), 0.0);
+    float n_dot_v = max(dot(normal, view_dir), 0.0);
+    float n_dot_h = max(dot(normal, halfway_dir), 0.0);
+
+    // Dielectrics reflect about 4% head on, metals tint it with their colour
+    vec3 f0 = mix(vec3(0.04), albedo, metallic);
+    vec3 fresnel = fresnel_schlick(max(dot(halfway_dir, view_dir), 0.0), f0);
+
+    float distribution = distribution_ggx(n_dot_h, roughness);
+    float geometry = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+    vec3 specular = distribution * geometry * fresnel / max(4.0 * n_dot_v * n_dot_l, 0.0001);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - metallic) * albedo / PI;
+
+    // Light colours are in Phong's terms, where white fully lights a white
+    // surface facing it, so scale them by PI to match the Lambertian term
+    return GenericOutput (
+        vec4(light.ambient * albedo * ambient_occlusion, 1.0),
+        vec4(diffuse * light.diffuse * PI * n_dot_l, 1.0),
+        vec4(specular * light.specular * PI * n_dot_l, 1.0)
+    );
+}
+
 vec4 attenuation(vec3 factors, float light_dist) {
     float x =  1.0 / (
         factors.x
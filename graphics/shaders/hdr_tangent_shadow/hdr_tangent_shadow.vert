@@ -81,16 +81,20 @@ out vec3 frag_pos_world_space;
 
 
 void main() {
+    mat4 world = model;
+#ifdef SKINNED
+    world = model * skin();
+#endif
     texture_coord = in_texture_coord;
     
     // Normal matrix adjusts normals after non-uniform transformation
-    mat3 normal_matrix = mat3(transpose(inverse(model))); // TODO: replace with CPU-side calc
-    //mat3 normal_matrix = mat3(model);
+    mat3 normal_matrix = mat3(transpose(inverse(world))); // TODO: replace with CPU-side calc
+    //mat3 normal_matrix = mat3(world);
     vec3 world_normal = normal_matrix * normalize(in_normal);
-    vec3 world_tangent = mat3(model) * normalize(in_tangent); // unsure if this is the right correction matrix
+    vec3 world_tangent = mat3(world) * normalize(in_tangent); // unsure if this is the right correction matrix
 
     // Vertex Position in world space
-    vec4 vertex_position = model * vec4(in_position, 1.0);
+    vec4 vertex_position = world * vec4(in_position, 1.0);
 
     // Vertex position in screen space
     gl_Position = projtimesview * vertex_position;
@@ -124,7 +128,7 @@ void main() {
     for (int x = 0; x < num_point; ++x) {
         out_point_vary[x].position = to_tangent * point_vary[x].position; 
         // SHADOW
-        out_point_vary[x].frag_to_light = (model * vec4(in_position, 1.0)).xyz - point_vary[x].position.xyz;
+        out_point_vary[x].frag_to_light = (world * vec4(in_position, 1.0)).xyz - point_vary[x].position.xyz;
     }
 
     for (int x = 0; x < num_far; ++x) {
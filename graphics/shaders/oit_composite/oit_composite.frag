@@ -0,0 +1,28 @@
+#version 330 core
+
+layout (location = 0) out vec4 frag_colour;
+layout (location = 1) out vec4 bright_colour;
+
+uniform sampler2D in_texture0; // accumulation
+uniform sampler2D in_texture1; // revealage, with the summed weights in red
+
+void main() {
+    // Read texel for texel, so that the composite lines up within a viewport
+    ivec2 texel = ivec2(gl_FragCoord.xy);
+    vec4 accumulation = texelFetch(in_texture0, texel, 0);
+    vec4 revealage = texelFetch(in_texture1, texel, 0);
+
+    float alpha = 1.0 - revealage.a;
+    if (alpha < 0.001) {
+        discard;
+    }
+
+    vec3 colour = accumulation.rgb / max(revealage.r, 1e-5);
+
+    frag_colour = vec4(colour, alpha);
+    if (dot(colour, vec3(0.2126, 0.7152, 0.0722)) > 1.0) {
+        bright_colour = vec4(colour, alpha);
+    } else {
+        bright_colour = vec4(0.0, 0.0, 0.0, alpha);
+    }
+}
@@ -0,0 +1,14 @@
+#define MAX_JOINTS 64
+
+layout (location = 4) in vec4 in_joints;
+layout (location = 5) in vec4 in_weights;
+
+// Each joint's pose after its inverse bind pose
+uniform mat4 joints[MAX_JOINTS];
+
+mat4 skin() {
+    return in_weights.x * joints[int(in_joints.x)]
+        + in_weights.y * joints[int(in_joints.y)]
+        + in_weights.z * joints[int(in_joints.z)]
+        + in_weights.w * joints[int(in_joints.w)];
+}
@@ -0,0 +1,61 @@
+//! View or compare frame traces written by
+//! `graphics::trace::capture_next_frame`
+//!
+//! ```text
+//! trace show <trace.json>
+//! trace diff <old.json> <new.json>
+//! ```
+
+use std::env;
+use std::process::ExitCode;
+
+use graphics::trace::{self, Change, Trace};
+
+fn usage() -> ExitCode {
+    eprintln!("usage:\n  trace show <trace.json>\n  trace diff <old.json> <new.json>");
+    ExitCode::FAILURE
+}
+
+fn load(path: &str) -> Option<Trace> {
+    Trace::load(path)
+        .inspect_err(|error| eprintln!("{error:?}"))
+        .ok()
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["show", path] => {
+            let Some(trace) = load(path) else {
+                return ExitCode::FAILURE;
+            };
+            println!("{trace}");
+            ExitCode::SUCCESS
+        }
+        ["diff", old, new] => {
+            let (Some(old), Some(new)) = (load(old), load(new)) else {
+                return ExitCode::FAILURE;
+            };
+
+            let changes = trace::diff(&old, &new);
+            let changed = changes
+                .iter()
+                .filter(|change| !matches!(change, Change::Same(_)))
+                .count();
+
+            println!("--- frame {}\n+++ frame {}", old.frame, new.frame);
+            for change in &changes {
+                println!("{change}");
+            }
+            println!("{changed} events differ");
+
+            if changed == 0 {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            }
+        }
+        _ => usage(),
+    }
+}
@@ -0,0 +1,129 @@
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The generation of the live `Environment`'s GL context, or 0 when there is
+/// none. Every object made in an earlier one is gone.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+static LAST_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The values of every `ContextLocal`, with the generation each was made in
+static SLOTS: Mutex<Vec<(u64, Box<dyn Send>)>> = Mutex::new(Vec::new());
+
+/// Owned by the `Window` whose GL context the `ContextLocal` values made
+/// while it lives belong to. Dropping it drops them, so it must be dropped
+/// with that context current.
+#[derive(Debug)]
+pub(crate) struct Generation(u64);
+
+impl Generation {
+    pub(crate) fn new() -> Self {
+        let generation = LAST_GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+        GENERATION.store(generation, Ordering::Relaxed);
+        Self(generation)
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        let _ = GENERATION.compare_exchange(self.0, 0, Ordering::Relaxed, Ordering::Relaxed);
+
+        let ended = {
+            let mut slots = SLOTS.lock().expect("values do not panic while moved");
+            let (ended, kept): (Vec<_>, Vec<_>) =
+                slots.drain(..).partition(|(made_in, _)| *made_in == self.0);
+            *slots = kept;
+            ended
+        };
+        // Outside the lock, as dropping a value may use another `ContextLocal`
+        drop(ended);
+    }
+}
+
+/// A GL object made on first use and shared for as long as its GL context
+/// lives, then made again for the next one. Use in place of a `LazyLock`,
+/// which would keep handing out a dead object once the `Environment` that
+/// made it is dropped.
+///
+/// Each value is dropped with the `Window` whose context made it, so a
+/// reference from `get` must not be kept past its `Environment`.
+#[derive(Debug)]
+pub struct ContextLocal<T: Send + 'static> {
+    init: fn() -> T,
+    value: Mutex<Option<(u64, &'static T)>>,
+}
+
+impl<T: Send + 'static> ContextLocal<T> {
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            init,
+            value: Mutex::new(None),
+        }
+    }
+
+    /// # Panics
+    /// Panics without a live `Environment`, or if `init` panicked on an
+    /// earlier call
+    pub fn get(&self) -> &'static T {
+        let generation = GENERATION.load(Ordering::Relaxed);
+        assert!(
+            generation != 0,
+            "a `ContextLocal` needs a live `Environment`"
+        );
+        let mut value = self.value.lock().expect("`init` does not panic");
+
+        match *value {
+            Some((made_in, value)) if made_in == generation => value,
+            _ => {
+                let made = Box::new((self.init)());
+                // SAFETY: the box is kept in `SLOTS` until this generation's
+                // `Generation` is dropped, and moving it does not move its
+                // contents. `get` stops handing the reference out then.
+                let made_ref = unsafe { &*ptr::from_ref(&*made) };
+                SLOTS
+                    .lock()
+                    .expect("values do not panic while moved")
+                    .push((generation, made));
+                *value = Some((generation, made_ref));
+                made_ref
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicU32;
+
+    use super::*;
+
+    #[test]
+    fn test_owned_per_generation() {
+        static MADE: AtomicU32 = AtomicU32::new(0);
+        static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+        struct Counted;
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                DROPPED.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        static LOCAL: ContextLocal<Counted> = ContextLocal::new(|| {
+            MADE.fetch_add(1, Ordering::Relaxed);
+            Counted
+        });
+
+        let generation = Generation::new();
+        let first = LOCAL.get();
+        assert!(std::ptr::eq(first, LOCAL.get()));
+        drop(generation);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 1);
+
+        let generation = Generation::new();
+        LOCAL.get();
+        assert_eq!(MADE.load(Ordering::Relaxed), 2);
+        drop(generation);
+        assert_eq!(DROPPED.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, c_void};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{LazyLock, Mutex};
+
+use crate::gl_call;
+use crate::types::{GLenum, GLsizei, GLuint};
+
+/// How important an OpenGL debug message is, in increasing order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl DebugSeverity {
+    fn from_enum(severity: GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH => Self::High,
+            gl::DEBUG_SEVERITY_MEDIUM => Self::Medium,
+            gl::DEBUG_SEVERITY_LOW => Self::Low,
+            _ => Self::Notification,
+        }
+    }
+
+    fn level(self) -> log::Level {
+        match self {
+            Self::High => log::Level::Error,
+            Self::Medium => log::Level::Warn,
+            Self::Low => log::Level::Info,
+            Self::Notification => log::Level::Debug,
+        }
+    }
+}
+
+static MIN_SEVERITY: AtomicU8 = AtomicU8::new(DebugSeverity::Low as u8);
+
+/// Occurrences of each message, keyed by source, type and id
+static SEEN: LazyLock<Mutex<HashMap<(GLenum, GLenum, GLuint), u64>>> =
+    LazyLock::new(Default::default);
+
+/// Drop debug messages less important than `severity`. Notifications are
+/// dropped by default.
+pub fn set_min_severity(severity: DebugSeverity) {
+    MIN_SEVERITY.store(severity as u8, Ordering::Relaxed);
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn type_name(gltype: GLenum) -> &'static str {
+    match gltype {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behaviour",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behaviour",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
+
+/// Whether the `count`th repeat of a message is logged, being 1, 10, 100...
+fn is_power_of_ten(count: u64) -> bool {
+    let mut n = count;
+    while n != 0 && n % 10 == 0 {
+        n /= 10;
+    }
+    n == 1
+}
+
+/// Forwards OpenGL debug messages to the `log` facade under the `gl` target.
+/// A message is logged the first time it is seen, then again after 10, 100,
+/// 1000... repeats so that per-frame warnings do not flood the log.
+pub(crate) extern "system" fn debug_callback(
+    source: GLenum,
+    gltype: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const i8,
+    _user_param: *mut c_void,
+) {
+    // Our own debug groups are only for external frame debuggers
+    if matches!(gltype, gl::DEBUG_TYPE_PUSH_GROUP | gl::DEBUG_TYPE_POP_GROUP) {
+        return;
+    }
+
+    let severity = DebugSeverity::from_enum(severity);
+    if (severity as u8) < MIN_SEVERITY.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let count = {
+        let Ok(mut seen) = SEEN.lock() else { return };
+        let count = seen.entry((source, gltype, id)).or_insert(0);
+        *count += 1;
+        *count
+    };
+
+    if !is_power_of_ten(count) {
+        return;
+    }
+
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    let level = if gltype == gl::DEBUG_TYPE_ERROR {
+        log::Level::Error
+    } else {
+        severity.level()
+    };
+
+    if count == 1 {
+        log::log!(
+            target: "gl",
+            level,
+            "[{}, {}, {id}] {message}",
+            source_name(source),
+            type_name(gltype),
+        );
+    } else {
+        log::log!(
+            target: "gl",
+            level,
+            "[{}, {}, {id}] (repeated {count} times) {message}",
+            source_name(source),
+            type_name(gltype),
+        );
+    }
+}
+
+/// Name an OpenGL object so that it is readable in debug messages and frame
+/// debuggers. Does nothing if `glObjectLabel` is unavailable.
+pub(crate) fn label(identifier: GLenum, name: GLuint, label: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+
+    gl_call! {
+        gl::ObjectLabel(identifier, name, label.len() as GLsizei, label.as_ptr().cast());
+    }
+}
+
+/// A named group of commands shown in external frame debuggers, which ends
+/// when dropped. Does nothing if `glPushDebugGroup` is unavailable.
+#[derive(Debug)]
+pub struct DebugGroup {
+    pushed: bool,
+}
+
+impl DebugGroup {
+    pub fn push(label: &str) -> Self {
+        let pushed = gl::PushDebugGroup::is_loaded();
+        if pushed {
+            gl_call! {
+                gl::PushDebugGroup(
+                    gl::DEBUG_SOURCE_APPLICATION,
+                    0,
+                    label.len() as GLsizei,
+                    label.as_ptr().cast(),
+                );
+            }
+        }
+
+        Self { pushed }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.pushed {
+            gl_call! {
+                gl::PopDebugGroup();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_power_of_ten() {
+        let logged: Vec<u64> = (0..=10_000)
+            .filter(|&count| is_power_of_ten(count))
+            .collect();
+        assert_eq!(logged, [1, 10, 100, 1000, 10_000]);
+    }
+}
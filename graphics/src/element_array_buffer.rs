@@ -2,7 +2,6 @@ use std::mem;
 
 use utils::getter;
 
-use crate::gl_call;
 use crate::types::{
     ElementArrayElem,
     ElementArrayId,
@@ -10,6 +9,7 @@ use crate::types::{
     GLsizei,
     GLsizeiptr,
     };
+use crate::{debug, gl_call};
 
 /// Tells OpenGL in which order the vertices of the VertexBuffer should be
 /// drawn. Internally (inside GPU memory) is an array of ElementArrayElem.
@@ -64,6 +64,10 @@ impl ElementArrayBuffer {
     }
 
     /// Bind ElementArrayBuffer
+    pub(crate) fn set_label(&self, label: &str) {
+        debug::label(gl::BUFFER, self.id.to_primitive(), label);
+    }
+
     pub(crate) fn bind(&self) {
         gl_call! {
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.id.to_primitive());
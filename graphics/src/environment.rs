@@ -1,22 +1,40 @@
 use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 mod draw;
+mod frame_loop;
 mod global_state;
 mod input;
+mod monitor;
+mod recording;
+mod state_stack;
 mod window;
 
 pub use draw::Draw;
+pub use frame_loop::FrameLimiter;
+use frame_loop::{FixedTimestep, FrameCap};
 use glfw::fail_on_errors;
 pub use global_state::GlobalState;
+pub use input::Action;
+pub(crate) use input::gamepad::BUTTONS as GAMEPAD_BUTTONS;
+use input::gamepad::Gamepads;
+pub use input::gamepad::{Gamepad, GamepadAxis, GamepadButton, GamepadEvent};
 pub use input::keyboard::Key;
 pub use input::mouse::Button;
+pub use monitor::{Monitor, VideoMode, WindowMode};
+use recording::{InputRecorder, InputReplay};
+use serde::{Deserialize, Serialize};
+pub use state_stack::{SharedResources, StackedState, StateStack, Transition};
 use utils::error_boilerplate;
-use window::Window;
+pub use window::WindowId;
+use window::{ExtraWindow, Window};
 
+use crate::debug::{self, DebugGroup, DebugSeverity};
 use crate::error::Result;
-use crate::framebuffer::FramebufferContext;
-use crate::gl_call;
-use crate::shader_program::ShaderProgramContext;
+use crate::input_map::{InputEvent, input_set};
+use crate::profiler::{FrameProfile, Profiler};
+use crate::state_cache::StateCacheStats;
+use crate::trace::{self, TraceEvent};
 use crate::types::TexDim;
 
 #[derive(Debug, Clone)]
@@ -24,6 +42,8 @@ pub enum Error {
     ScreenDimsStaticPosion,
     GlfwInit { glfw_error: glfw::InitError },
     GlfwWindow,
+    NoMonitor(usize),
+    NoVideoMode,
 }
 
 error_boilerplate!(Error);
@@ -34,67 +54,49 @@ impl From<Error> for crate::error::Error {
     }
 }
 
-use std::ffi::{CStr, c_void};
-use std::ptr;
-#[expect(unused_variables)]
-extern "system" fn debug_callback(
-    source: u32,
-    gltype: u32,
-    id: u32,
-    severity: u32,
-    length: i32,
-    message: *const i8,
-    user_param: *mut c_void,
-) {
-    let message = unsafe { CStr::from_ptr(message) };
-    let source = match source {
-        gl::DEBUG_SOURCE_API => "DEBUG_SOURCE_API",
-        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "DEBUG_SOURCE_WINDOW_SYSTEM",
-        gl::DEBUG_SOURCE_SHADER_COMPILER => "DEBUG_SOURCE_SHADER_COMPILER",
-        gl::DEBUG_SOURCE_THIRD_PARTY => "DEBUG_SOURCE_THIRD_PARTY",
-        gl::DEBUG_SOURCE_APPLICATION => "DEBUG_SOURCE_APPLICATION",
-        gl::DEBUG_SOURCE_OTHER => "DEBUG_SOURCE_OTHER",
-        _ => "ERROR_SOURCE_NOT_FOUND",
-    };
-    let gltype = match gltype {
-        gl::DEBUG_TYPE_ERROR => "DEBUG_TYPE_ERROR",
-        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "DEBUG_TYPE_DEPRECATED_BEHAVIOR",
-        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "DEBUG_TYPE_UNDEFINED_BEHAVIOR",
-        gl::DEBUG_TYPE_PORTABILITY => "DEBUG_TYPE_PORTABILITY",
-        gl::DEBUG_TYPE_PERFORMANCE => "DEBUG_TYPE_PERFORMANCE",
-        gl::DEBUG_TYPE_MARKER => "DEBUG_TYPE_MARKER",
-        gl::DEBUG_TYPE_PUSH_GROUP => "DEBUG_TYPE_PUSH_GROUP",
-        gl::DEBUG_TYPE_POP_GROUP => "DEBUG_TYPE_POP_GROUP",
-        gl::DEBUG_TYPE_OTHER => "DEBUG_TYPE_OTHER",
-        _ => "ERROR TYPE NOT FOUND",
-    };
-    let severity = match severity {
-        gl::DEBUG_SEVERITY_HIGH => "DEBUG_SEVERITY_HIGH",
-        gl::DEBUG_SEVERITY_MEDIUM => "DEBUG_SEVERITY_MEDIUM",
-        gl::DEBUG_SEVERITY_LOW => "DEBUG_SEVERITY_LOW",
-        gl::DEBUG_SEVERITY_NOTIFICATION => "DEBUG_SEVERITY_NOTIFICATION",
-        _ => "ERROR SEVERITY NOT FOUND",
-    };
-
-    println!(
-        "GL Callback:\n\tsource =  \t{source}\n\ttype =    \t{gltype}\n\tseverity \
-         =\t{severity}\n\tmessage = \t{message:#?}",
-    );
-}
-
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Event {
     CriticalFault,
     FrameTime(f64),
     ActualTime(f64),
     WindowResize((TexDim, TexDim)),
+    /// The window moved to a display with a different DPI, also available
+    /// from `DefaultFramebuffer::content_scale`
+    ContentScale((f32, f32)),
+    /// Every connected monitor, sent on the first frame and after each change
+    /// of `WindowMode`
+    #[serde(skip)]
+    Monitors(Vec<Monitor>),
     TextBuffer(String),
-    Keyboard(HashSet<Key>),
+    /// A key or mouse button changed, sent in the order they happened
+    Input(InputEvent),
+    /// Keys currently held
+    Keyboard(#[serde(with = "input_set")] HashSet<Key>),
+    Gamepad(GamepadEvent),
     Mouse {
+        #[serde(with = "input_set")]
         buttons: HashSet<Button>,
         position: (f64, f64),
         delta: (f64, f64),
     },
+    /// The scroll wheel or touchpad moved, with up and right positive
+    Scroll((f64, f64)),
+    CursorEnter,
+    CursorLeave,
+    FocusGained,
+    FocusLost,
+    Minimised,
+    Restored,
+    /// Files dragged from outside and dropped onto the window
+    FilesDropped(Vec<PathBuf>),
+    /// Timings of a recent frame, only sent when profiling is enabled
+    #[serde(skip)]
+    Profile(FrameProfile),
+    /// How far this frame falls between the last fixed update and the next,
+    /// from 0 to 1, for blending between the last two simulated states. Only
+    /// sent when a fixed timestep is set.
+    #[serde(skip)]
+    Interpolation(f64),
 }
 
 #[derive(Debug)]
@@ -103,6 +105,16 @@ pub struct Environment<G: GlobalState> {
     window: Window,
     old_frame: f64,
     glfw: glfw::Glfw,
+    profiling: bool,
+    frame_profile: Option<FrameProfile>,
+    frame_cap: Option<FrameCap>,
+    fixed_timestep: Option<FixedTimestep>,
+    gamepads: Gamepads,
+    recorder: Option<InputRecorder>,
+    replay: Option<InputReplay>,
+    monitors_changed: bool,
+    extra_windows: Vec<ExtraWindow>,
+    next_window_id: usize,
 }
 
 impl<G: GlobalState> Environment<G> {
@@ -133,67 +145,236 @@ impl<G: GlobalState> Environment<G> {
 
         glfw.set_swap_interval(glfw::SwapInterval::None);
 
-        // we need a GL context before we can load OpenGL functions
-        gl::load_with(|s| glfw.get_proc_address_raw(s));
-
-        gl_call! {
-            gl::Enable(gl::BLEND);
-        }
-        gl_call! {
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        }
-
-        gl_call! {
-            gl::Enable(gl::DEBUG_OUTPUT);
-        }
-        gl_call! {
-            gl::DebugMessageCallback(Some(debug_callback), ptr::null());
-        }
-
-        gl_call! {
-            gl::ClearColor(0.1, 0.0, 0.1, 1.0);
-        }
-
         let global_state = G::new(window.get_framebuffer_size())?;
 
         Ok(Self {
             glfw,
             window,
-            // window2,
             global_state,
 
             old_frame: 0.0,
+            profiling: false,
+            frame_profile: None,
+            frame_cap: None,
+            fixed_timestep: None,
+            gamepads: Gamepads::default(),
+            recorder: None,
+            replay: None,
+            monitors_changed: true,
+            extra_windows: Vec::new(),
+            next_window_id: 1,
         })
     }
 
-    fn poll(&mut self) -> Result<Vec<Box<dyn Draw + '_>>> {
-        match self.calculate_events() {
-            Ok(events) => self
-                .global_state
-                .poll(events, &self.window.default_framebuffer),
-            Err(error) => Err(error),
+    /// Time every `Draw` on the GPU and CPU, delivering the breakdown to the
+    /// `GlobalState` as `Event::Profile`
+    pub fn profiling(mut self, profiling: bool) -> Self {
+        self.profiling = profiling;
+        self
+    }
+
+    /// Wait for the monitor's vertical blank before presenting each frame,
+    /// which is off by default
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.glfw.set_swap_interval(if vsync {
+            glfw::SwapInterval::Sync(1)
+        } else {
+            glfw::SwapInterval::None
+        });
+        self
+    }
+
+    /// Present at most `frame_rate` frames per second, or remove the cap with
+    /// `None`
+    /// # Panics
+    /// Panics if `frame_rate` is not a finite number above zero
+    pub fn frame_rate_limit(mut self, frame_rate: Option<f64>, limiter: FrameLimiter) -> Self {
+        self.frame_cap = frame_rate.map(|frame_rate| FrameCap::new(frame_rate, limiter));
+        self
+    }
+
+    /// Call `GlobalState::fixed_update` every `timestep` seconds of real time,
+    /// however long each frame takes
+    /// # Panics
+    /// Panics if `timestep` is not a finite number above zero
+    pub fn fixed_timestep(mut self, timestep: f64) -> Self {
+        self.fixed_timestep = Some(FixedTimestep::new(timestep));
+        self
+    }
+
+    /// Treat gamepad sticks and triggers deflected less than `dead_zone`,
+    /// from 0 to 1, as at rest. Defaults to 0.15.
+    pub fn gamepad_dead_zone(mut self, dead_zone: f32) -> Self {
+        self.gamepads.set_dead_zone(dead_zone);
+        self
+    }
+
+    /// Write the events given to the `GlobalState` each frame to `path`, to
+    /// be fed back later with `replay_input`
+    pub fn record_input<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.recorder = Some(InputRecorder::create(path)?);
+        Ok(self)
+    }
+
+    /// Give the `GlobalState` the events recorded to `path` instead of live
+    /// input, closing once the recording runs out. Window resizes, content
+    /// scale and monitors still come from the live window, as they describe
+    /// the screen rather than play.
+    pub fn replay_input<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        self.replay = Some(InputReplay::open(path)?);
+        Ok(self)
+    }
+
+    /// Start in `mode` rather than windowed
+    pub fn window_mode(mut self, mode: WindowMode) -> Result<Self> {
+        self.set_window_mode(mode)?;
+        Ok(self)
+    }
+
+    /// Switch between windowed, borderless and fullscreen. The `GlobalState`
+    /// can do the same mid-run through `GlobalState::window_mode`.
+    pub fn set_window_mode(&mut self, mode: WindowMode) -> Result<()> {
+        if mode != self.window.mode() {
+            self.window.set_mode(&mut self.glfw, mode)?;
+            self.monitors_changed = true;
         }
+        Ok(())
     }
 
+    /// Open another window sharing the main window's textures, buffers and
+    /// shader programs, drawn through `GlobalState::draw_window` after the
+    /// main window each frame. Input to it is ignored and closing it leaves
+    /// the rest running.
+    pub fn add_window(&mut self, title: &str, dims: (u32, u32)) -> Result<WindowId> {
+        let window = Window::builder()
+            .dims(dims)
+            .title(title)
+            .glfw(&mut self.glfw)
+            .build_shared(&self.window)?;
+
+        // The new context is now current. Only the main window waits for
+        // vsync, or each extra window would cost a whole refresh.
+        self.glfw.set_swap_interval(glfw::SwapInterval::None);
+
+        let id = WindowId(self.next_window_id);
+        self.next_window_id += 1;
+        self.extra_windows.push(ExtraWindow { id, window });
+        let result = self.global_state.init_window(id);
+
+        self.window.make_current();
+        result.map(|()| id)
+    }
+
+    /// Every connected monitor, the primary first
+    pub fn monitors(&mut self) -> Vec<Monitor> {
+        monitor::monitors(&mut self.glfw)
+    }
+
+    /// Ignore OpenGL debug messages less important than `severity`, which is
+    /// `DebugSeverity::Low` by default
+    pub fn debug_severity(self, severity: DebugSeverity) -> Self {
+        debug::set_min_severity(severity);
+        self
+    }
+
+    /// This frame's events, after switching window mode if the `GlobalState`
+    /// asked to and calling its resize and focus hooks. A failed switch, such
+    /// as to an unplugged monitor, leaves the window as it was.
+    fn poll_events(&mut self) -> Result<Vec<Event>> {
+        if let Some(mode) = self.global_state.window_mode() {
+            if let Err(error) = self.set_window_mode(mode) {
+                log::warn!("could not switch to {mode:?}: {error:?}");
+            }
+            self.global_state.window_mode_changed(self.window.mode());
+        }
+
+        let events = self.calculate_events()?;
+        for event in &events {
+            match event {
+                Event::WindowResize(size) => self.global_state.resized(*size)?,
+                Event::FocusGained => self.global_state.focus_changed(true),
+                Event::FocusLost => self.global_state.focus_changed(false),
+                _ => {}
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Run frames until the main window closes or the `GlobalState` returns
+    /// `Error::Close`, then call `GlobalState::shutdown`
     pub fn run(&mut self) -> Result<()> {
-        // Substitute for `for to_draw in env.iter() {`
-        let mut frame_iter = self.iter();
-        let mut shader_program_marker =
-            ShaderProgramContext::new().expect("First invocation means only one marker");
-        let mut framebuffer_register =
-            FramebufferContext::new().expect("First invocation means only one register");
-
-        while let Some(result) = frame_iter.next() {
-            match result {
-                Ok(to_draw) => {
-                    framebuffer_register.clear();
-                    // Begin rendering code
-                    for draw in to_draw {
-                        draw.draw(&mut framebuffer_register, &mut shader_program_marker)?;
-                    }
+        self.window
+            .framebuffer_context
+            .set_profiler(self.profiling.then(Profiler::new));
+
+        let result = self.run_frames();
+        self.global_state.shutdown();
+        result
+    }
+
+    fn run_frames(&mut self) -> Result<()> {
+        loop {
+            if self.window.should_close() {
+                if self.global_state.close_requested() {
+                    break;
                 }
-                Err(error) => return Err(error),
+                self.window.cancel_close();
+            }
+
+            self.end_render();
+
+            let events = match self.poll_events() {
+                Err(crate::error::Error::Close) => break,
+                result => result?,
+            };
+            let to_draw = match self
+                .global_state
+                .poll(events, &self.window.default_framebuffer)
+            {
+                Err(crate::error::Error::Close) => break,
+                result => result?,
+            };
+
+            let framebuffer_register = &mut self.window.framebuffer_context;
+            let shader_program_marker = &mut self.window.shader_program_context;
+            framebuffer_register.clear();
+            if !self.extra_windows.is_empty() {
+                // The other contexts may have taken notice of changes to
+                // shared objects
+                framebuffer_register.invalidate();
+                shader_program_marker.invalidate();
+            }
+            if let Some(profiler) = framebuffer_register.profiler_mut() {
+                profiler.begin_frame();
             }
+            // Begin rendering code
+            trace::begin_frame();
+            for draw in to_draw {
+                let name = draw.name();
+                trace::record(|| TraceEvent::Draw { name: name.clone() });
+                let _debug_group = DebugGroup::push(&name);
+                if let Some(profiler) = framebuffer_register.profiler_mut() {
+                    profiler.begin_draw(name);
+                }
+                draw.draw(framebuffer_register, shader_program_marker)?;
+            }
+            trace::end_frame();
+            let state_cache = StateCacheStats {
+                framebuffer: framebuffer_register.cache_stats().framebuffer,
+                viewport: framebuffer_register.cache_stats().viewport,
+                ..shader_program_marker.cache_stats()
+            };
+            framebuffer_register.reset_cache_stats();
+            shader_program_marker.reset_cache_stats();
+
+            if let Some(profiler) = framebuffer_register.profiler_mut() {
+                profiler.record_state_cache(state_cache);
+                profiler.end_frame();
+                self.frame_profile = profiler.take_latest();
+            }
+
+            self.draw_extra_windows()?;
         }
 
         Ok(())
@@ -202,16 +383,117 @@ impl<G: GlobalState> Environment<G> {
     fn end_render(&mut self) {
         // Poll for and process events
         self.glfw.poll_events();
+        if let Some(frame_cap) = &mut self.frame_cap {
+            frame_cap.wait();
+        }
         // Swap front and back buffers
         self.window.swap_buffers();
-        // self.window2.swap_buffers();
+    }
+
+    /// Draw each extra window with its own context current, dropping any
+    /// that have been closed, then make the main window's context current
+    /// again
+    fn draw_extra_windows(&mut self) -> Result<()> {
+        if self.extra_windows.is_empty() {
+            return Ok(());
+        }
+
+        for extra in &mut self.extra_windows {
+            extra.window.make_current();
+            // Keeps the default framebuffer's size up to date, the input is
+            // only wanted from the main window
+            extra.window.take_events();
+
+            if extra.window.should_close() {
+                self.global_state.close_window(extra.id);
+                continue;
+            }
+
+            let window = &mut extra.window;
+            window.shader_program_context.invalidate();
+            window.framebuffer_context.invalidate();
+            window.framebuffer_context.clear();
+
+            let to_draw = self
+                .global_state
+                .draw_window(extra.id, &window.default_framebuffer)?;
+            for draw in to_draw {
+                let _debug_group = DebugGroup::push(&draw.name());
+                draw.draw(
+                    &mut window.framebuffer_context,
+                    &mut window.shader_program_context,
+                )?;
+            }
+            extra.window.swap_buffers();
+        }
+
+        self.extra_windows
+            .retain(|extra| !extra.window.should_close());
+        self.window.make_current();
+
+        Ok(())
     }
 
     fn calculate_events(&mut self) -> Result<Vec<Event>> {
+        // Live input is always drained, even when replaying, so that it does
+        // not build up
+        let live = self.live_events();
+        let mut event_buffer = match &mut self.replay {
+            None => live,
+            Some(replay) => {
+                let Some(mut recorded) = replay.next_frame()? else {
+                    return Err(crate::error::Error::Close);
+                };
+                let describes_screen = |event: &Event| {
+                    matches!(
+                        event,
+                        Event::WindowResize(_) | Event::ContentScale(_) | Event::Monitors(_)
+                    )
+                };
+                recorded.retain(|event| !describes_screen(event));
+                recorded.extend(live.into_iter().filter(describes_screen));
+                recorded
+            }
+        };
+
+        if let Some(recorder) = &mut self.recorder
+            && let Err(error) = recorder.record(&event_buffer)
+        {
+            log::error!("stopped recording input: {error:?}");
+            self.recorder = None;
+        }
+
+        if let Some(fixed_timestep) = &mut self.fixed_timestep {
+            let frametime = event_buffer
+                .iter()
+                .find_map(|event| match event {
+                    Event::FrameTime(frametime) => Some(*frametime),
+                    _ => None,
+                })
+                .unwrap_or(0.0);
+
+            for _ in 0..fixed_timestep.advance(frametime) {
+                self.global_state.fixed_update(fixed_timestep.timestep())?;
+            }
+            event_buffer.push(Event::Interpolation(fixed_timestep.alpha()));
+        }
+
+        if let Some(profile) = self.frame_profile.take() {
+            event_buffer.push(Event::Profile(profile));
+        }
+
+        Ok(event_buffer)
+    }
+
+    fn live_events(&mut self) -> Vec<Event> {
         let curr_time = self.glfw.get_time();
         let frametime = curr_time - self.old_frame;
         self.old_frame = curr_time;
 
+        // Drained first, so that the held keys and mouse state below include
+        // everything that happened up to now
+        let queued = self.window.take_events();
+
         let mut event_buffer = vec![
             Event::FrameTime(frametime),
             Event::ActualTime(curr_time),
@@ -222,47 +504,37 @@ impl<G: GlobalState> Environment<G> {
                 delta: self.window.mouse_mut().get_delta(),
             },
         ];
+        event_buffer.extend(queued);
+        // Gamepads are polled now, so their changes come after any earlier
+        // window events
+        self.gamepads.poll(&self.glfw, curr_time, &mut event_buffer);
 
         let typing_buffer = self.window.keyboard_mut().get_reset_buffer();
         if !typing_buffer.is_empty() {
             event_buffer.push(Event::TextBuffer(typing_buffer))
         }
 
-        if self.window.window_resized() {
-            event_buffer.push(Event::WindowResize(self.window.get_framebuffer_size()));
+        if self.window.take_window_resized() {
+            event_buffer.push(Event::WindowResize(self.window.default_framebuffer.size()));
         }
 
-        Ok(event_buffer)
-    }
-
-    #[expect(clippy::iter_not_returning_iterator)]
-    fn iter(&mut self) -> FrameIter<G> {
-        FrameIter::new(self)
-    }
-}
-
-struct FrameIter<'a, G: GlobalState> {
-    env: &'a mut Environment<G>,
-}
+        if std::mem::take(&mut self.monitors_changed) {
+            event_buffer.push(Event::Monitors(self.monitors()));
+        }
 
-impl<'env, G: GlobalState> FrameIter<'env, G> {
-    fn new(env: &'env mut Environment<G>) -> Self {
-        Self { env }
+        event_buffer
     }
 }
 
-impl<'env, G: GlobalState> FrameIter<'env, G> {
-    pub fn next(&mut self) -> Option<Result<Vec<Box<dyn Draw + '_>>>> {
-        if !self.env.window.should_close() {
-            self.env.end_render();
-
-            match self.env.poll() {
-                Ok(vec) => Some(Ok(vec)),
-                Err(crate::error::Error::Close) => None,
-                Err(err) => Some(Err(err)),
-            }
-        } else {
-            None
+impl<G: GlobalState> Drop for Environment<G> {
+    /// Close the extra windows before anything else, so that what
+    /// `GlobalState::init_window` made for each is dropped with that window's
+    /// context current rather than the main window's
+    fn drop(&mut self) {
+        for mut extra in self.extra_windows.drain(..) {
+            extra.window.make_current();
+            self.global_state.close_window(extra.id);
         }
+        self.window.make_current();
     }
 }
@@ -8,4 +8,12 @@ pub trait Draw {
         register: &mut FramebufferContext,
         marker: &mut ShaderProgramContext,
     ) -> Result<()>;
+
+    /// Label used when profiling, defaults to the type name without its path
+    /// or generic parameters
+    fn name(&self) -> String {
+        let full = std::any::type_name::<Self>();
+        let path = full.split('<').next().unwrap_or(full);
+        path.rsplit("::").next().unwrap_or(path).to_string()
+    }
 }
@@ -0,0 +1,158 @@
+use std::time::{Duration, Instant};
+use std::{hint, thread};
+
+/// Fixed updates run in a single frame before the remaining time is dropped,
+/// so that a long stall does not leave the simulation permanently behind.
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+/// How `Environment` waits out the rest of a frame when its frame rate is
+/// capped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrameLimiter {
+    /// Give the time back to the OS, which may oversleep by a millisecond or
+    /// so
+    #[default]
+    Sleep,
+    /// Busy-wait for precise frame pacing at the cost of a CPU core
+    Spin,
+}
+
+#[derive(Debug)]
+pub(crate) struct FrameCap {
+    period: Duration,
+    limiter: FrameLimiter,
+    last: Instant,
+}
+
+impl FrameCap {
+    /// # Panics
+    /// Panics if `frame_rate` is not a finite number above zero
+    pub(crate) fn new(frame_rate: f64, limiter: FrameLimiter) -> Self {
+        assert!(
+            frame_rate.is_finite() && frame_rate > 0.0,
+            "frame rate must be finite and positive, not {frame_rate}"
+        );
+
+        Self {
+            period: Duration::from_secs_f64(1.0 / frame_rate),
+            limiter,
+            last: Instant::now(),
+        }
+    }
+
+    /// Block until a whole period has passed since the end of the last wait
+    pub(crate) fn wait(&mut self) {
+        let deadline = self.last + self.period;
+        match self.limiter {
+            FrameLimiter::Sleep => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if !remaining.is_zero() {
+                    thread::sleep(remaining);
+                }
+            }
+            FrameLimiter::Spin => {
+                while Instant::now() < deadline {
+                    hint::spin_loop();
+                }
+            }
+        }
+
+        // Schedule from the deadline so oversleeping does not drift the
+        // frame rate, unless we have fallen a whole frame behind
+        let now = Instant::now();
+        self.last = if now.duration_since(deadline) > self.period {
+            now
+        } else {
+            deadline
+        };
+    }
+}
+
+/// Splits variable frame times into whole steps of `timestep` seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct FixedTimestep {
+    timestep: f64,
+    accumulator: f64,
+}
+
+impl FixedTimestep {
+    /// # Panics
+    /// Panics if `timestep` is not a finite number above zero
+    pub(crate) fn new(timestep: f64) -> Self {
+        assert!(
+            timestep.is_finite() && timestep > 0.0,
+            "timestep must be finite and positive, not {timestep}"
+        );
+
+        Self {
+            timestep,
+            accumulator: 0.0,
+        }
+    }
+
+    pub(crate) fn timestep(&self) -> f64 {
+        self.timestep
+    }
+
+    /// Add a frame's worth of time, returning how many updates to run
+    pub(crate) fn advance(&mut self, frame_time: f64) -> u32 {
+        self.accumulator += frame_time.max(0.0);
+
+        let steps = (self.accumulator / self.timestep).floor();
+        if steps > MAX_STEPS_PER_FRAME as f64 {
+            self.accumulator %= self.timestep;
+            return MAX_STEPS_PER_FRAME;
+        }
+
+        self.accumulator -= steps * self.timestep;
+        steps as u32
+    }
+
+    /// How far the frame is between the last update and the next, from 0 to 1
+    pub(crate) fn alpha(&self) -> f64 {
+        (self.accumulator / self.timestep).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_advance() {
+        let mut fixed = FixedTimestep::new(0.25);
+
+        assert_eq!(fixed.advance(0.1), 0);
+        assert!((fixed.alpha() - 0.4).abs() < 1e-9);
+
+        assert_eq!(fixed.advance(0.5), 2);
+        assert!((fixed.alpha() - 0.4).abs() < 1e-9);
+
+        assert_eq!(fixed.advance(0.2), 1);
+        assert!((fixed.alpha() - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_advance_drops_stalls() {
+        let mut fixed = FixedTimestep::new(0.25);
+
+        assert_eq!(fixed.advance(10.1), MAX_STEPS_PER_FRAME);
+        assert!((fixed.alpha() - 0.4).abs() < 1e-9);
+        assert_eq!(fixed.advance(0.0), 0);
+    }
+
+    #[test]
+    fn test_rejects_invalid_timesteps() {
+        for timestep in [0.0, -0.25, f64::NAN, f64::INFINITY] {
+            assert!(std::panic::catch_unwind(|| FixedTimestep::new(timestep)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_frame_rates() {
+        for frame_rate in [0.0, -60.0, f64::NAN, f64::INFINITY] {
+            let cap = std::panic::catch_unwind(|| FrameCap::new(frame_rate, FrameLimiter::Sleep));
+            assert!(cap.is_err());
+        }
+    }
+}
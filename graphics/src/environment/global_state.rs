@@ -1,5 +1,5 @@
 use super::Draw;
-use crate::environment::Event;
+use crate::environment::{Event, WindowId, WindowMode};
 use crate::error::Result;
 use crate::framebuffer::DefaultFramebuffer;
 use crate::types::TexDim;
@@ -12,4 +12,59 @@ pub trait GlobalState: Sized {
     ) -> Result<Vec<Box<dyn Draw + 'a>>>;
 
     fn new(initial_size: (TexDim, TexDim)) -> Result<Self>;
+
+    /// Advance the simulation by exactly `timestep` seconds. Runs zero or more
+    /// times before each `poll` when `Environment::fixed_timestep` is set.
+    fn fixed_update(&mut self, _timestep: f64) -> Result<()> {
+        Ok(())
+    }
+
+    /// The main window's framebuffer changed size, called before the `poll`
+    /// given the matching `Event::WindowResize`
+    fn resized(&mut self, _size: (TexDim, TexDim)) -> Result<()> {
+        Ok(())
+    }
+
+    /// The main window gained or lost keyboard focus, called before the
+    /// `poll` given the matching event
+    fn focus_changed(&mut self, _focused: bool) {}
+
+    /// The user asked to close the main window. Return `false` to keep it
+    /// open, for example to ask about unsaved changes first.
+    fn close_requested(&mut self) -> bool {
+        true
+    }
+
+    /// Called once as `Environment::run` returns, however it ends
+    fn shutdown(&mut self) {}
+
+    /// A window mode to switch to, checked before each `poll`
+    fn window_mode(&mut self) -> Option<WindowMode> {
+        None
+    }
+
+    /// The mode the main window is in after each switch asked for by
+    /// `window_mode`, which is the old one if switching failed
+    fn window_mode_changed(&mut self, _mode: WindowMode) {}
+
+    /// Called once for each window from `Environment::add_window`, with its
+    /// context current. Vertex arrays and framebuffers belong to a single
+    /// context, so any drawn into the window must be made here.
+    fn init_window(&mut self, _window: WindowId) -> Result<()> {
+        Ok(())
+    }
+
+    /// The draws for an extra window, made with its context current after
+    /// the main window's
+    fn draw_window<'a>(
+        &'a mut self,
+        _window: WindowId,
+        _default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+        Ok(Vec::new())
+    }
+
+    /// Called with the window's context current as it closes, to drop
+    /// whatever `init_window` made for it
+    fn close_window(&mut self, _window: WindowId) {}
 }
@@ -1,3 +1,4 @@
+pub mod gamepad;
 pub mod keyboard;
 pub mod mouse;
 
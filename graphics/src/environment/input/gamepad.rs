@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+
+pub use glfw::GamepadButton;
+use glfw::{Glfw, JoystickId};
+use serde::{Deserialize, Serialize};
+
+use super::Action;
+use crate::environment::Event;
+use crate::input_map::{Input, InputEvent, Modifiers, input_set};
+
+/// Stick deflection below which input is ignored, as a fraction of the full
+/// range
+const DEFAULT_DEAD_ZONE: f32 = 0.15;
+
+const JOYSTICKS: [JoystickId; 16] = [
+    JoystickId::Joystick1,
+    JoystickId::Joystick2,
+    JoystickId::Joystick3,
+    JoystickId::Joystick4,
+    JoystickId::Joystick5,
+    JoystickId::Joystick6,
+    JoystickId::Joystick7,
+    JoystickId::Joystick8,
+    JoystickId::Joystick9,
+    JoystickId::Joystick10,
+    JoystickId::Joystick11,
+    JoystickId::Joystick12,
+    JoystickId::Joystick13,
+    JoystickId::Joystick14,
+    JoystickId::Joystick15,
+    JoystickId::Joystick16,
+];
+
+/// Every button of GLFW's standard gamepad layout
+pub(crate) const BUTTONS: [GamepadButton; 15] = [
+    GamepadButton::ButtonA,
+    GamepadButton::ButtonB,
+    GamepadButton::ButtonX,
+    GamepadButton::ButtonY,
+    GamepadButton::ButtonLeftBumper,
+    GamepadButton::ButtonRightBumper,
+    GamepadButton::ButtonBack,
+    GamepadButton::ButtonStart,
+    GamepadButton::ButtonGuide,
+    GamepadButton::ButtonLeftThumb,
+    GamepadButton::ButtonRightThumb,
+    GamepadButton::ButtonDpadUp,
+    GamepadButton::ButtonDpadRight,
+    GamepadButton::ButtonDpadDown,
+    GamepadButton::ButtonDpadLeft,
+];
+
+/// An axis of GLFW's standard gamepad layout. Stick axes run from -1 to 1
+/// with up and right positive, triggers from 0 at rest to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GamepadAxis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl GamepadAxis {
+    const ALL: [Self; 6] = [
+        Self::LeftX,
+        Self::LeftY,
+        Self::RightX,
+        Self::RightY,
+        Self::LeftTrigger,
+        Self::RightTrigger,
+    ];
+
+    fn to_glfw(self) -> glfw::GamepadAxis {
+        match self {
+            Self::LeftX => glfw::GamepadAxis::AxisLeftX,
+            Self::LeftY => glfw::GamepadAxis::AxisLeftY,
+            Self::RightX => glfw::GamepadAxis::AxisRightX,
+            Self::RightY => glfw::GamepadAxis::AxisRightY,
+            Self::LeftTrigger => glfw::GamepadAxis::AxisLeftTrigger,
+            Self::RightTrigger => glfw::GamepadAxis::AxisRightTrigger,
+        }
+    }
+}
+
+/// The state of one connected controller during a frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Gamepad {
+    /// GLFW's joystick slot, from 0 to 15, stable while connected
+    pub id: usize,
+    #[serde(with = "input_set")]
+    pub buttons: HashSet<GamepadButton>,
+    /// Indexed by `GamepadAxis`, with the dead zone applied
+    axes: [f32; 6],
+}
+
+impl Gamepad {
+    pub fn axis(&self, axis: GamepadAxis) -> f32 {
+        self.axes[axis as usize]
+    }
+}
+
+/// A controller connecting, disconnecting or reporting its state
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GamepadEvent {
+    Connected {
+        id: usize,
+        name: String,
+    },
+    Disconnected {
+        id: usize,
+    },
+    /// Sent every frame for each connected controller
+    State(Gamepad),
+}
+
+/// Rescale a stick so that deflections inside the dead zone read 0 and the
+/// rest of the range still reaches 1. The dead zone is circular so that
+/// diagonals are not snapped to the axes.
+fn stick_dead_zone(x: f32, y: f32, dead_zone: f32) -> (f32, f32) {
+    let magnitude = x.hypot(y);
+    if magnitude <= dead_zone {
+        return (0.0, 0.0);
+    }
+
+    let scaled = ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0);
+    (x / magnitude * scaled, y / magnitude * scaled)
+}
+
+/// Map a trigger from GLFW's -1 to 1 onto 0 to 1, then apply the dead zone
+fn trigger_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let value = (value + 1.0) / 2.0;
+    if value <= dead_zone {
+        0.0
+    } else {
+        ((value - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+/// Tracks every joystick slot that GLFW recognises as a gamepad, turning
+/// their state into `Event`s once per frame
+#[derive(Debug)]
+pub(crate) struct Gamepads {
+    connected: [Option<HashSet<GamepadButton>>; 16],
+    dead_zone: f32,
+}
+
+impl Default for Gamepads {
+    fn default() -> Self {
+        Self {
+            connected: Default::default(),
+            dead_zone: DEFAULT_DEAD_ZONE,
+        }
+    }
+}
+
+impl Gamepads {
+    pub(crate) fn set_dead_zone(&mut self, dead_zone: f32) {
+        self.dead_zone = dead_zone.clamp(0.0, 0.99);
+    }
+
+    /// Push connections, button changes as `Event::Input` and each
+    /// controller's state onto `events`
+    pub(crate) fn poll(&mut self, glfw: &Glfw, time: f64, events: &mut Vec<Event>) {
+        for (id, joystick_id) in JOYSTICKS.into_iter().enumerate() {
+            let joystick = glfw.get_joystick(joystick_id);
+            let state = joystick
+                .is_gamepad()
+                .then(|| joystick.get_gamepad_state())
+                .flatten();
+
+            let Some(state) = state else {
+                if self.connected[id].take().is_some() {
+                    events.push(Event::Gamepad(GamepadEvent::Disconnected { id }));
+                }
+                continue;
+            };
+
+            let previous = match self.connected[id].take() {
+                Some(previous) => previous,
+                None => {
+                    let name = joystick.get_gamepad_name().unwrap_or_default();
+                    events.push(Event::Gamepad(GamepadEvent::Connected { id, name }));
+                    HashSet::new()
+                }
+            };
+
+            let buttons: HashSet<GamepadButton> = BUTTONS
+                .into_iter()
+                .filter(|button| state.get_button_state(*button) == Action::Press)
+                .collect();
+
+            for (button, action) in buttons
+                .difference(&previous)
+                .map(|button| (button, Action::Press))
+                .chain(previous.difference(&buttons).map(|b| (b, Action::Release)))
+            {
+                events.push(Event::Input(InputEvent {
+                    input: Input::Gamepad(*button),
+                    action,
+                    modifiers: Modifiers::default(),
+                    time,
+                }));
+            }
+
+            let raw = GamepadAxis::ALL.map(|axis| state.get_axis(axis.to_glfw()));
+            // GLFW reports stick y as positive downwards
+            let (left_x, left_y) = stick_dead_zone(raw[0], -raw[1], self.dead_zone);
+            let (right_x, right_y) = stick_dead_zone(raw[2], -raw[3], self.dead_zone);
+            let axes = [
+                left_x,
+                left_y,
+                right_x,
+                right_y,
+                trigger_dead_zone(raw[4], self.dead_zone),
+                trigger_dead_zone(raw[5], self.dead_zone),
+            ];
+
+            events.push(Event::Gamepad(GamepadEvent::State(Gamepad {
+                id,
+                buttons: buttons.clone(),
+                axes,
+            })));
+            self.connected[id] = Some(buttons);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{a} != {b}");
+    }
+
+    #[test]
+    fn test_stick_dead_zone() {
+        assert_eq!(stick_dead_zone(0.1, -0.1, 0.2), (0.0, 0.0));
+
+        let (x, y) = stick_dead_zone(0.6, 0.0, 0.2);
+        assert_close(x, 0.5);
+        assert_close(y, 0.0);
+
+        let (x, y) = stick_dead_zone(1.0, 1.0, 0.2);
+        assert_close(x.hypot(y), 1.0);
+        assert_close(x, y);
+    }
+
+    #[test]
+    fn test_trigger_dead_zone() {
+        assert_eq!(trigger_dead_zone(-1.0, 0.1), 0.0);
+        assert_close(trigger_dead_zone(0.0, 0.0), 0.5);
+        assert_close(trigger_dead_zone(1.0, 0.1), 1.0);
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashSet;
 
-use glfw::PWindow;
+use glfw::Modifiers;
 pub use glfw::{CursorMode, MouseButton as Button};
 
 use super::Action;
+use crate::input_map::{Input, InputEvent};
 
 // First mouse means no movement this check
 // Upon change, set the change flag to true
@@ -26,13 +27,38 @@ pub enum MouseState {
     },
 }
 
+impl MouseState {
+    fn moved_to(self, x: f64, y: f64) -> Self {
+        match self {
+            Self::FirstMouse => Self::Stationary {
+                current_location: (x, y),
+            },
+            Self::Stationary { current_location } => Self::Moved {
+                current_location: (x, y),
+                delta: (x - current_location.0, y - current_location.1),
+            },
+            Self::Moved {
+                current_location,
+                delta,
+            } => Self::Moved {
+                current_location: (x, y),
+                delta: (
+                    x - current_location.0 + delta.0,
+                    y - current_location.1 + delta.1,
+                ),
+            },
+        }
+    }
+}
+
+/// Cursor movement and held buttons, updated from the window's event queue
 #[derive(Debug)]
-struct Inner {
+pub struct Mouse {
     state: MouseState,
     buttons: HashSet<Button>,
 }
 
-impl Default for Inner {
+impl Default for Mouse {
     fn default() -> Self {
         Self {
             state: MouseState::FirstMouse,
@@ -41,69 +67,47 @@ impl Default for Inner {
     }
 }
 
-#[derive(Debug)]
-pub struct Mouse(Box<Inner>);
-
 impl Mouse {
-    pub(crate) fn new(window: &mut PWindow, fix_to_centre: bool) -> Self {
-        let mut out = Mouse(Box::default());
-
-        let state_ptr = &raw mut out.0.state;
-
-        window.set_cursor_pos_callback(move |_, x, y| {
-            let y = -y;
-            // SAFETY: unsure
-            if let Some(data) = unsafe { state_ptr.as_mut() } {
-                // TODO: as method on MouseState
-                *data = match *data {
-                    MouseState::FirstMouse => MouseState::Stationary {
-                        current_location: (x, y),
-                    },
-                    MouseState::Stationary { current_location } => MouseState::Moved {
-                        current_location: (x, y),
-                        delta: (x - current_location.0, y - current_location.1),
-                    },
-                    MouseState::Moved {
-                        current_location,
-                        delta,
-                    } => MouseState::Moved {
-                        current_location: (x, y),
-                        delta: (
-                            x - current_location.0 + delta.0,
-                            y - current_location.1 + delta.1,
-                        ),
-                    },
-                }
-            }
-        });
-
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
 
-        let buttons_ptr = &raw mut out.0.buttons;
+    pub(crate) fn cursor_pos(&mut self, x: f64, y: f64) {
+        self.state = self.state.moved_to(x, -y);
+    }
 
-        window.set_mouse_button_callback(move |_, mouse_button, action, _| match action {
+    pub(crate) fn button(
+        &mut self,
+        time: f64,
+        button: Button,
+        action: Action,
+        modifiers: Modifiers,
+    ) -> InputEvent {
+        match action {
             Action::Press => {
-                unsafe { buttons_ptr.as_mut() }.map(|data| data.insert(mouse_button));
+                self.buttons.insert(button);
             }
             Action::Release => {
-                unsafe { buttons_ptr.as_mut() }.map(|data| data.remove(&mouse_button));
+                self.buttons.remove(&button);
             }
             Action::Repeat => {}
-        });
-
-        if fix_to_centre {
-            window.set_cursor_mode(CursorMode::Disabled);
         }
 
-        out
+        InputEvent {
+            input: Input::Mouse(button),
+            action,
+            modifiers: modifiers.into(),
+            time,
+        }
     }
 
     pub(crate) fn get_delta(&mut self) -> (f64, f64) {
-        let out = match self.0.state {
+        let out = match self.state {
             MouseState::Moved { delta, .. } => delta,
             _ => (0.0, 0.0),
         };
 
-        self.0.state = match self.0.state {
+        self.state = match self.state {
             MouseState::Moved {
                 current_location, ..
             } => MouseState::Stationary { current_location },
@@ -114,7 +118,7 @@ impl Mouse {
     }
 
     pub(crate) fn get_position(&self) -> (f64, f64) {
-        match self.0.state {
+        match self.state {
             MouseState::FirstMouse => (0.0, 0.0),
             MouseState::Stationary { current_location } => current_location,
             MouseState::Moved {
@@ -124,6 +128,24 @@ impl Mouse {
     }
 
     pub(crate) fn get_buttons_depressed(&self) -> HashSet<Button> {
-        self.0.buttons.clone()
+        self.buttons.clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_delta_accumulates() {
+        let mut mouse = Mouse::new();
+        mouse.cursor_pos(10.0, 10.0);
+        assert_eq!(mouse.get_delta(), (0.0, 0.0));
+
+        mouse.cursor_pos(12.0, 9.0);
+        mouse.cursor_pos(15.0, 5.0);
+        assert_eq!(mouse.get_position(), (15.0, -5.0));
+        assert_eq!(mouse.get_delta(), (5.0, 5.0));
+        assert_eq!(mouse.get_delta(), (0.0, 0.0));
     }
 }
@@ -0,0 +1,123 @@
+use glfw::{Glfw, VidMode};
+
+/// A resolution and refresh rate that a monitor can be driven at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VideoMode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: u32,
+}
+
+impl From<VidMode> for VideoMode {
+    fn from(value: VidMode) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+            refresh_rate: value.refresh_rate,
+        }
+    }
+}
+
+impl VideoMode {
+    /// The mode in `modes` nearest to `self`, preferring a matching
+    /// resolution over a matching refresh rate
+    pub(crate) fn closest(self, modes: impl IntoIterator<Item = Self>) -> Option<Self> {
+        modes.into_iter().min_by_key(|mode| {
+            let resolution = mode.width.abs_diff(self.width) + mode.height.abs_diff(self.height);
+            (resolution, mode.refresh_rate.abs_diff(self.refresh_rate))
+        })
+    }
+}
+
+/// A connected display, as it was when the list was taken
+#[derive(Debug, Clone, PartialEq)]
+pub struct Monitor {
+    /// Position in the list, which `WindowMode` uses to pick a monitor. The
+    /// primary monitor is always 0.
+    pub index: usize,
+    pub name: String,
+    /// Top left corner on the virtual desktop, in screen coordinates
+    pub position: (i32, i32),
+    pub physical_size_mm: (i32, i32),
+    /// The ratio of pixels to screen coordinates the OS asks for, such as 2.0
+    /// on a high-DPI display
+    pub content_scale: (f32, f32),
+    pub current_mode: Option<VideoMode>,
+    pub video_modes: Vec<VideoMode>,
+}
+
+/// How the window sits on the desktop
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowMode {
+    /// A decorated window at the size and place it last had as one
+    #[default]
+    Windowed,
+    /// An undecorated window covering a monitor, leaving its video mode alone
+    /// so that switching away is instant
+    Borderless { monitor: usize },
+    /// Take over a monitor, driving it at the closest mode to `video_mode`,
+    /// or its current one when `None`
+    Fullscreen {
+        monitor: usize,
+        video_mode: Option<VideoMode>,
+    },
+}
+
+pub(crate) fn monitors(glfw: &mut Glfw) -> Vec<Monitor> {
+    glfw.with_connected_monitors(|_, monitors| {
+        monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| Monitor {
+                index,
+                name: monitor.get_name().unwrap_or_default(),
+                position: monitor.get_pos(),
+                physical_size_mm: monitor.get_physical_size(),
+                content_scale: monitor.get_content_scale(),
+                current_mode: monitor.get_video_mode().map(VideoMode::from),
+                video_modes: monitor
+                    .get_video_modes()
+                    .into_iter()
+                    .map(VideoMode::from)
+                    .collect(),
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn mode(width: u32, height: u32, refresh_rate: u32) -> VideoMode {
+        VideoMode {
+            width,
+            height,
+            refresh_rate,
+        }
+    }
+
+    #[test]
+    fn test_closest() {
+        let modes = [
+            mode(1280, 720, 60),
+            mode(1920, 1080, 60),
+            mode(1920, 1080, 144),
+            mode(2560, 1440, 165),
+        ];
+
+        assert_eq!(
+            mode(1920, 1080, 120).closest(modes),
+            Some(mode(1920, 1080, 144))
+        );
+        assert_eq!(
+            mode(2560, 1440, 60).closest(modes),
+            Some(mode(2560, 1440, 165))
+        );
+        assert_eq!(
+            mode(1366, 768, 60).closest(modes),
+            Some(mode(1280, 720, 60))
+        );
+        assert_eq!(mode(1920, 1080, 60).closest([]), None);
+    }
+}
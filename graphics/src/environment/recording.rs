@@ -0,0 +1,144 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Lines, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::Event;
+use crate::error::{Error, Result};
+
+/// One line of a recording
+#[derive(Debug, Serialize)]
+struct FrameRef<'a> {
+    frame: u64,
+    events: Vec<&'a Event>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Frame {
+    frame: u64,
+    events: Vec<Event>,
+}
+
+/// Whether an event is input to the `GlobalState`, rather than something the
+/// engine reports about itself and will report again on replay
+fn is_recorded(event: &Event) -> bool {
+    !matches!(
+        event,
+        Event::Profile(_) | Event::Interpolation(_) | Event::Monitors(_)
+    )
+}
+
+/// Writes the events of each frame to a file as a line of JSON, flushing
+/// every frame so that a crash keeps everything up to it
+#[derive(Debug)]
+pub(crate) struct InputRecorder {
+    writer: BufWriter<File>,
+    frame: u64,
+}
+
+impl InputRecorder {
+    pub(crate) fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|error| Error::Other(format!("creating {}: {error}", path.display())))?;
+
+        Ok(Self {
+            writer: BufWriter::new(file),
+            frame: 0,
+        })
+    }
+
+    pub(crate) fn record(&mut self, events: &[Event]) -> Result<()> {
+        let frame = FrameRef {
+            frame: self.frame,
+            events: events.iter().filter(|event| is_recorded(event)).collect(),
+        };
+        self.frame += 1;
+
+        serde_json::to_writer(&mut self.writer, &frame)
+            .map_err(|error| Error::Other(format!("recording input: {error}")))?;
+        writeln!(self.writer)
+            .and_then(|()| self.writer.flush())
+            .map_err(|error| Error::Other(format!("recording input: {error}")))
+    }
+}
+
+/// Reads back the frames written by `InputRecorder`
+#[derive(Debug)]
+pub(crate) struct InputReplay {
+    lines: Lines<BufReader<File>>,
+}
+
+impl InputReplay {
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|error| Error::Other(format!("opening {}: {error}", path.display())))?;
+
+        Ok(Self {
+            lines: BufReader::new(file).lines(),
+        })
+    }
+
+    /// The events of the next recorded frame, or `None` once the recording
+    /// has finished
+    pub(crate) fn next_frame(&mut self) -> Result<Option<Vec<Event>>> {
+        let Some(line) = self.lines.next() else {
+            return Ok(None);
+        };
+        let line = line.map_err(|error| Error::Other(format!("replaying input: {error}")))?;
+
+        let frame: Frame = serde_json::from_str(&line)
+            .map_err(|error| Error::Other(format!("replaying input: {error}")))?;
+        log::trace!("replaying frame {}", frame.frame);
+
+        Ok(Some(frame.events))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::environment::{Action, Key};
+    use crate::input_map::{Input, InputEvent, Modifiers};
+    use crate::profiler::FrameProfile;
+
+    #[test]
+    fn test_round_trip() {
+        let path = std::env::temp_dir().join("graphics_test_recording.jsonl");
+
+        let frame = vec![
+            Event::FrameTime(0.016),
+            Event::Keyboard(HashSet::from([Key::LeftShift])),
+            Event::Input(InputEvent {
+                input: Input::Key(Key::W),
+                action: Action::Press,
+                modifiers: Modifiers {
+                    shift: true,
+                    ..Modifiers::default()
+                },
+                time: 1.5,
+            }),
+            Event::Profile(FrameProfile::default()),
+            Event::Interpolation(0.5),
+        ];
+
+        let mut recorder = InputRecorder::create(&path).unwrap();
+        recorder.record(&frame).unwrap();
+        recorder.record(&[Event::CriticalFault]).unwrap();
+        drop(recorder);
+
+        let mut replay = InputReplay::open(&path).unwrap();
+        let first = replay.next_frame().unwrap().unwrap();
+        let second = replay.next_frame().unwrap().unwrap();
+        assert!(replay.next_frame().unwrap().is_none());
+
+        assert_eq!(format!("{first:?}"), format!("{:?}", &frame[..3]));
+        assert!(matches!(second[..], [Event::CriticalFault]));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
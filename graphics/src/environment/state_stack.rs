@@ -0,0 +1,367 @@
+use super::{Draw, Event, GlobalState, WindowId, WindowMode};
+use crate::error::{Error, Result};
+use crate::framebuffer::DefaultFramebuffer;
+use crate::types::TexDim;
+
+/// What a `StackedState` wants done to the stack after its update
+pub enum Transition<R> {
+    None,
+    /// Put a state on top, such as a pause menu over gameplay
+    Push(Box<dyn StackedState<R>>),
+    /// Remove this state, returning to the one below. The program closes once
+    /// the stack is empty.
+    Pop,
+    /// Swap this state for another, such as a loading screen for the level
+    /// it loaded
+    Replace(Box<dyn StackedState<R>>),
+}
+
+/// Whatever the states of a `StateStack` share, such as framebuffers,
+/// models and settings
+pub trait SharedResources: Sized + 'static {
+    fn new(initial_size: (TexDim, TexDim)) -> Result<Self>;
+
+    /// The state at the bottom of the stack when the program starts
+    fn initial_state(&mut self) -> Result<Box<dyn StackedState<Self>>>;
+
+    fn resized(&mut self, _size: (TexDim, TexDim)) -> Result<()> {
+        Ok(())
+    }
+
+    /// See `GlobalState::window_mode`
+    fn window_mode(&mut self) -> Option<WindowMode> {
+        None
+    }
+
+    /// See `GlobalState::window_mode_changed`
+    fn window_mode_changed(&mut self, _mode: WindowMode) {}
+
+    /// Make whatever the states draw into `window`, see
+    /// `GlobalState::init_window`
+    fn init_window(&mut self, _window: WindowId) -> Result<()> {
+        Ok(())
+    }
+
+    /// See `GlobalState::close_window`
+    fn close_window(&mut self, _window: WindowId) {}
+}
+
+/// One screen of a `StateStack`. Only the top state is updated and given
+/// events, while it and the states under it up to the first that is not an
+/// overlay are drawn, bottom first.
+pub trait StackedState<R> {
+    fn update(&mut self, resources: &mut R, events: Vec<Event>) -> Result<Transition<R>>;
+
+    fn draw<'a>(
+        &'a self,
+        resources: &'a R,
+        default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>>;
+
+    /// Runs for the top state only, see `GlobalState::fixed_update`
+    fn fixed_update(&mut self, _resources: &mut R, _timestep: f64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether the state below still draws, as gameplay does under a pause
+    /// menu
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Put on the stack
+    fn enter(&mut self, _resources: &mut R) {}
+
+    /// Taken off the stack
+    fn exit(&mut self, _resources: &mut R) {}
+
+    /// Another state was pushed on top
+    fn pause(&mut self, _resources: &mut R) {}
+
+    /// The state on top was popped
+    fn resume(&mut self, _resources: &mut R) {}
+
+    /// Only asked of the top state, see `GlobalState::close_requested`
+    fn close_requested(&mut self, _resources: &mut R) -> bool {
+        true
+    }
+
+    /// Only told to the top state, which may push a pause menu, see
+    /// `GlobalState::focus_changed`
+    fn focus_changed(&mut self, _resources: &mut R, _focused: bool) -> Transition<R> {
+        Transition::None
+    }
+
+    /// The draws for an extra window, made by the same states as `draw`,
+    /// see `GlobalState::draw_window`
+    fn draw_window<'a>(
+        &'a self,
+        _resources: &'a R,
+        _window: WindowId,
+        _default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+        Ok(Vec::new())
+    }
+}
+
+/// A `GlobalState` made of a stack of `StackedState`s over shared resources
+pub struct StateStack<R: SharedResources> {
+    resources: R,
+    states: Vec<Box<dyn StackedState<R>>>,
+}
+
+impl<R: SharedResources> StateStack<R> {
+    pub fn resources(&self) -> &R {
+        &self.resources
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn push(&mut self, mut state: Box<dyn StackedState<R>>) {
+        if let Some(top) = self.states.last_mut() {
+            top.pause(&mut self.resources);
+        }
+        state.enter(&mut self.resources);
+        self.states.push(state);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<dyn StackedState<R>>> {
+        let mut state = self.states.pop()?;
+        state.exit(&mut self.resources);
+        if let Some(top) = self.states.last_mut() {
+            top.resume(&mut self.resources);
+        }
+        Some(state)
+    }
+
+    /// Swap the top state for `state`, without pausing or resuming the one
+    /// below
+    pub fn replace(&mut self, mut state: Box<dyn StackedState<R>>) {
+        if let Some(mut old) = self.states.pop() {
+            old.exit(&mut self.resources);
+        }
+        state.enter(&mut self.resources);
+        self.states.push(state);
+    }
+
+    /// The top state and those under it up to the first that is not an
+    /// overlay, bottom first
+    fn visible(&self) -> &[Box<dyn StackedState<R>>] {
+        let bottom = self
+            .states
+            .iter()
+            .rposition(|state| !state.is_overlay())
+            .unwrap_or(0);
+        &self.states[bottom..]
+    }
+
+    fn apply(&mut self, transition: Transition<R>) {
+        match transition {
+            Transition::None => {}
+            Transition::Push(state) => self.push(state),
+            Transition::Pop => {
+                self.pop();
+            }
+            Transition::Replace(state) => self.replace(state),
+        }
+    }
+}
+
+impl<R: SharedResources> GlobalState for StateStack<R> {
+    fn poll<'a>(
+        &'a mut self,
+        events: Vec<Event>,
+        default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+        let top = self.states.last_mut().ok_or(Error::Close)?;
+        let transition = top.update(&mut self.resources, events)?;
+        self.apply(transition);
+        if self.states.is_empty() {
+            return Err(Error::Close);
+        }
+
+        let this: &'a Self = self;
+        let mut out = Vec::new();
+        for state in this.visible() {
+            out.extend(state.draw(&this.resources, default_framebuffer)?);
+        }
+        Ok(out)
+    }
+
+    fn new(initial_size: (TexDim, TexDim)) -> Result<Self> {
+        let mut resources = R::new(initial_size)?;
+        let initial_state = resources.initial_state()?;
+
+        let mut stack = Self {
+            resources,
+            states: Vec::new(),
+        };
+        stack.push(initial_state);
+        Ok(stack)
+    }
+
+    fn fixed_update(&mut self, timestep: f64) -> Result<()> {
+        match self.states.last_mut() {
+            Some(top) => top.fixed_update(&mut self.resources, timestep),
+            None => Ok(()),
+        }
+    }
+
+    fn resized(&mut self, size: (TexDim, TexDim)) -> Result<()> {
+        self.resources.resized(size)
+    }
+
+    fn focus_changed(&mut self, focused: bool) {
+        if let Some(top) = self.states.last_mut() {
+            let transition = top.focus_changed(&mut self.resources, focused);
+            self.apply(transition);
+        }
+    }
+
+    fn close_requested(&mut self) -> bool {
+        self.states
+            .last_mut()
+            .is_none_or(|top| top.close_requested(&mut self.resources))
+    }
+
+    /// Exit every state, top first
+    fn shutdown(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    fn window_mode(&mut self) -> Option<WindowMode> {
+        self.resources.window_mode()
+    }
+
+    fn window_mode_changed(&mut self, mode: WindowMode) {
+        self.resources.window_mode_changed(mode);
+    }
+
+    fn init_window(&mut self, window: WindowId) -> Result<()> {
+        self.resources.init_window(window)
+    }
+
+    fn draw_window<'a>(
+        &'a mut self,
+        window: WindowId,
+        default_framebuffer: &'a DefaultFramebuffer,
+    ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+        let this: &'a Self = self;
+        let mut out = Vec::new();
+        for state in this.visible() {
+            out.extend(state.draw_window(&this.resources, window, default_framebuffer)?);
+        }
+        Ok(out)
+    }
+
+    fn close_window(&mut self, window: WindowId) {
+        self.resources.close_window(window);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct Resources(Log);
+
+    impl SharedResources for Resources {
+        fn new(_initial_size: (TexDim, TexDim)) -> Result<Self> {
+            Ok(Self(Log::default()))
+        }
+
+        fn initial_state(&mut self) -> Result<Box<dyn StackedState<Self>>> {
+            Ok(Box::new(Named("game")))
+        }
+    }
+
+    struct Named(&'static str);
+
+    impl StackedState<Resources> for Named {
+        fn update(&mut self, _: &mut Resources, _: Vec<Event>) -> Result<Transition<Resources>> {
+            Ok(Transition::None)
+        }
+
+        fn draw<'a>(
+            &'a self,
+            _: &'a Resources,
+            _: &'a DefaultFramebuffer,
+        ) -> Result<Vec<Box<dyn Draw + 'a>>> {
+            Ok(Vec::new())
+        }
+
+        fn enter(&mut self, resources: &mut Resources) {
+            resources.0.borrow_mut().push(format!("enter {}", self.0));
+        }
+
+        fn exit(&mut self, resources: &mut Resources) {
+            resources.0.borrow_mut().push(format!("exit {}", self.0));
+        }
+
+        fn pause(&mut self, resources: &mut Resources) {
+            resources.0.borrow_mut().push(format!("pause {}", self.0));
+        }
+
+        fn resume(&mut self, resources: &mut Resources) {
+            resources.0.borrow_mut().push(format!("resume {}", self.0));
+        }
+
+        fn focus_changed(&mut self, _: &mut Resources, focused: bool) -> Transition<Resources> {
+            if focused {
+                Transition::None
+            } else {
+                Transition::Push(Box::new(Named("pause")))
+            }
+        }
+    }
+
+    #[test]
+    fn test_transitions() {
+        let size = (TexDim::new(1), TexDim::new(1));
+        let mut stack = StateStack::<Resources>::new(size).unwrap();
+
+        stack.apply(Transition::Push(Box::new(Named("menu"))));
+        stack.apply(Transition::Replace(Box::new(Named("options"))));
+        assert_eq!(stack.len(), 2);
+        stack.apply(Transition::Pop);
+        stack.shutdown();
+        assert!(stack.is_empty());
+
+        assert_eq!(
+            *stack.resources().0.borrow(),
+            [
+                "enter game",
+                "pause game",
+                "enter menu",
+                "exit menu",
+                "enter options",
+                "exit options",
+                "resume game",
+                "exit game",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_focus_changed() {
+        let size = (TexDim::new(1), TexDim::new(1));
+        let mut stack = StateStack::<Resources>::new(size).unwrap();
+
+        stack.focus_changed(true);
+        assert_eq!(stack.len(), 1);
+        stack.focus_changed(false);
+        assert_eq!(stack.len(), 2);
+        assert_eq!(stack.resources().0.borrow().last().unwrap(), "enter pause");
+    }
+}
@@ -1,22 +1,48 @@
+use std::ptr;
+
 use glfw::{Context, Glfw, GlfwReceiver, PWindow, WindowEvent};
 use utils::{builder, new};
 
 use super::input::keyboard::Keyboard;
 use super::input::mouse::{CursorMode, Mouse};
-use crate::environment::Error;
+use super::monitor::{VideoMode, WindowMode};
+use crate::context_local::Generation;
+use crate::environment::{Error, Event};
 use crate::error::Result;
-use crate::framebuffer::DefaultFramebuffer;
+use crate::framebuffer::{DefaultFramebuffer, FramebufferContext};
+use crate::shader_program::ShaderProgramContext;
 use crate::types::TexDim;
+use crate::{debug, gl_call};
+
+/// Names a window opened by `Environment`, stable for as long as it is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(pub(crate) usize);
+
+impl WindowId {
+    /// The window created by `Environment::new`, which ends the program when
+    /// closed
+    pub const MAIN: Self = Self(0);
+}
 
-#[expect(dead_code)]
 #[derive(Debug)]
 pub struct Window {
+    /// What every `ContextLocal` made for this context and those sharing it,
+    /// so only held by a window that shares no other's. Declared first to
+    /// drop while the context still exists.
+    _context_locals: Option<Generation>,
+    /// State caches for this window's GL context, declared before
+    /// `glfw_window` so that they drop while the context still exists
+    pub(crate) shader_program_context: ShaderProgramContext,
+    pub(crate) framebuffer_context: FramebufferContext,
     glfw_window: PWindow,
     pub(crate) default_framebuffer: DefaultFramebuffer,
     events: GlfwReceiver<(f64, WindowEvent)>,
-    window_resized: Box<bool>,
+    window_resized: bool,
     keyboard: Keyboard,
     mouse: Mouse,
+    mode: WindowMode,
+    /// Position and size to return to when going back to `Windowed`
+    windowed_rect: ((i32, i32), (i32, i32)),
 }
 
 impl Window {
@@ -45,23 +71,175 @@ impl Window {
         &mut self.keyboard
     }
 
-    pub(crate) fn window_resized(&self) -> bool {
-        *self.window_resized
+    /// Whether the framebuffer changed size since the last call
+    pub(crate) fn take_window_resized(&mut self) -> bool {
+        std::mem::take(&mut self.window_resized)
+    }
+
+    pub(crate) fn mode(&self) -> WindowMode {
+        self.mode
+    }
+
+    /// Move the window between windowed, borderless and fullscreen, on any
+    /// monitor. A `WindowResize` follows once the OS has caught up.
+    pub(crate) fn set_mode(&mut self, glfw: &mut Glfw, mode: WindowMode) -> Result<()> {
+        if self.mode == WindowMode::Windowed {
+            self.windowed_rect = (self.glfw_window.get_pos(), self.glfw_window.get_size());
+        }
+
+        let glfw_window = &mut self.glfw_window;
+        let windowed_rect = self.windowed_rect;
+        glfw.with_connected_monitors(|_, monitors| {
+            let monitor = |index: usize| {
+                monitors
+                    .get(index)
+                    .map(|monitor| &**monitor)
+                    .ok_or(Error::NoMonitor(index))
+            };
+            let current_mode = |monitor: &glfw::Monitor| {
+                monitor
+                    .get_video_mode()
+                    .map(VideoMode::from)
+                    .ok_or(Error::NoVideoMode)
+            };
+
+            match mode {
+                WindowMode::Windowed => {
+                    let ((x, y), (width, height)) = windowed_rect;
+                    glfw_window.set_decorated(true);
+                    glfw_window.set_monitor(
+                        glfw::WindowMode::Windowed,
+                        x,
+                        y,
+                        width as u32,
+                        height as u32,
+                        None,
+                    );
+                }
+                WindowMode::Borderless { monitor: index } => {
+                    let monitor = monitor(index)?;
+                    let (x, y) = monitor.get_pos();
+                    let video_mode = current_mode(monitor)?;
+                    glfw_window.set_decorated(false);
+                    glfw_window.set_monitor(
+                        glfw::WindowMode::Windowed,
+                        x,
+                        y,
+                        video_mode.width,
+                        video_mode.height,
+                        None,
+                    );
+                }
+                WindowMode::Fullscreen {
+                    monitor: index,
+                    video_mode,
+                } => {
+                    let monitor = monitor(index)?;
+                    let video_mode = match video_mode {
+                        Some(wanted) => wanted
+                            .closest(monitor.get_video_modes().into_iter().map(VideoMode::from))
+                            .ok_or(Error::NoVideoMode)?,
+                        None => current_mode(monitor)?,
+                    };
+                    glfw_window.set_monitor(
+                        glfw::WindowMode::FullScreen(monitor),
+                        0,
+                        0,
+                        video_mode.width,
+                        video_mode.height,
+                        Some(video_mode.refresh_rate),
+                    );
+                }
+            }
+            Ok::<_, Error>(())
+        })?;
+
+        self.mode = mode;
+        Ok(())
     }
 
     pub(crate) fn should_close(&self) -> bool {
         self.glfw_window.should_close()
     }
 
+    /// Keep the window open after the user tried to close it
+    pub(crate) fn cancel_close(&mut self) {
+        self.glfw_window.set_should_close(false);
+    }
+
     pub(crate) fn swap_buffers(&mut self) {
         self.glfw_window.swap_buffers();
     }
 
+    pub(crate) fn make_current(&mut self) {
+        self.glfw_window.make_current();
+    }
+
+    /// Drain everything GLFW queued for this window since the last call,
+    /// updating the keyboard and mouse state as it goes. Key and button
+    /// changes come out as `Event::Input`, interleaved with the rest in the
+    /// order they happened.
+    pub(crate) fn take_events(&mut self) -> Vec<Event> {
+        let mut events = Vec::new();
+        for (time, event) in glfw::flush_messages(&self.events) {
+            let event = match event {
+                WindowEvent::Key(key, _, action, modifiers) => {
+                    let input = self.keyboard.key(time, key, action, modifiers);
+                    Event::Input(input)
+                }
+                WindowEvent::Char(char) => {
+                    self.keyboard.char(char);
+                    continue;
+                }
+                WindowEvent::MouseButton(button, action, modifiers) => {
+                    let input = self.mouse.button(time, button, action, modifiers);
+                    Event::Input(input)
+                }
+                WindowEvent::CursorPos(x, y) => {
+                    self.mouse.cursor_pos(x, y);
+                    continue;
+                }
+                WindowEvent::FramebufferSize(width, height) => {
+                    // Zero while minimised, which nothing can be resized to
+                    if let (Some(width), Some(height)) =
+                        (TexDim::try_new(width), TexDim::try_new(height))
+                    {
+                        self.default_framebuffer.set_size((width, height));
+                        self.window_resized = true;
+                    }
+                    continue;
+                }
+                WindowEvent::ContentScale(x, y) => {
+                    self.default_framebuffer.set_content_scale((x, y));
+                    Event::ContentScale((x, y))
+                }
+                WindowEvent::Scroll(x, y) => Event::Scroll((x, y)),
+                WindowEvent::CursorEnter(true) => Event::CursorEnter,
+                WindowEvent::CursorEnter(false) => Event::CursorLeave,
+                WindowEvent::Focus(true) => Event::FocusGained,
+                WindowEvent::Focus(false) => Event::FocusLost,
+                WindowEvent::Iconify(true) => Event::Minimised,
+                WindowEvent::Iconify(false) => Event::Restored,
+                WindowEvent::FileDrop(paths) => Event::FilesDropped(paths),
+                _ => continue,
+            };
+            events.push(event);
+        }
+        events
+    }
+
     pub fn set_cursor_mode(&mut self, mode: CursorMode) {
         self.glfw_window.set_cursor_mode(mode);
     }
 }
 
+/// A window beyond the main one
+#[derive(Debug)]
+pub(crate) struct ExtraWindow {
+    pub(crate) id: WindowId,
+    pub(crate) window: Window,
+}
+
 #[derive(Debug, Default)]
 pub struct MissingGlfw;
 #[derive(Debug)]
@@ -74,7 +252,7 @@ pub struct Builder<G> {
     mouse_fix_to_centre: bool,
     title: String,
     raw_motion: bool,
-    windowed: bool,
+    mode: WindowMode,
 }
 
 impl Default for Builder<MissingGlfw> {
@@ -85,7 +263,7 @@ impl Default for Builder<MissingGlfw> {
             mouse_fix_to_centre: false,
             title: String::new(),
             raw_motion: false,
-            windowed: true,
+            mode: WindowMode::Windowed,
         }
     }
 }
@@ -110,55 +288,116 @@ impl<G> Builder<G> {
 
     builder!(raw_motion: bool);
 
-    builder!(windowed: bool);
+    builder!(mode: WindowMode);
 }
 
 impl Builder<HasGlfw<'_>> {
     pub(crate) fn build(self) -> Result<Window> {
-        // Create a windowed mode window and its OpenGL context
-        let (mut glfw_window, events) = self
+        // Size windows in screen coordinates scaled for the monitor's DPI, as
+        // macOS already does
+        self.glfw
+            .0
+            .window_hint(glfw::WindowHint::ScaleToMonitor(true));
+
+        // Create a windowed mode window and its OpenGL context, moved to its
+        // real mode once it exists
+        let created = self
             .glfw
             .0
             .create_window(
                 self.dims.0,
                 self.dims.1,
                 &self.title,
-                if self.windowed {
-                    glfw::WindowMode::Windowed
-                } else {
-                    todo!("Full Screen not yet supported")
-                },
+                glfw::WindowMode::Windowed,
             )
             .ok_or(Error::GlfwWindow)?;
 
-        glfw_window.make_current();
-        glfw_window.set_key_polling(true);
+        self.finish(created, Some(Generation::new()))
+    }
+
+    /// Build a window whose context shares textures, buffers and shader
+    /// programs with that of `share`
+    pub(crate) fn build_shared(self, share: &Window) -> Result<Window> {
+        self.glfw
+            .0
+            .window_hint(glfw::WindowHint::ScaleToMonitor(true));
+
+        let created = share
+            .glfw_window
+            .create_shared(
+                self.dims.0,
+                self.dims.1,
+                &self.title,
+                glfw::WindowMode::Windowed,
+            )
+            .ok_or(Error::GlfwWindow)?;
 
-        let mut window_resized = Box::new(false);
+        self.finish(created, None)
+    }
 
-        let resized_ptr = Box::as_mut_ptr(&mut window_resized);
-        glfw_window.set_framebuffer_size_callback(move |_window, _width, _height| unsafe {
-            *resized_ptr = true
-        });
+    /// Make the new window's context current and set up its input and state
+    /// caches
+    fn finish(
+        self,
+        (mut glfw_window, events): (PWindow, GlfwReceiver<(f64, WindowEvent)>),
+        context_locals: Option<Generation>,
+    ) -> Result<Window> {
+        glfw_window.make_current();
+        // we need a GL context before we can load OpenGL functions
+        gl::load_with(|s| self.glfw.0.get_proc_address_raw(s));
+        // Debug output is per context, even when objects are shared
+        gl_call! {
+            gl::Enable(gl::DEBUG_OUTPUT);
+        }
+        gl_call! {
+            gl::DebugMessageCallback(Some(debug::debug_callback), ptr::null());
+        }
+        gl_call! {
+            gl::ClearColor(0.1, 0.0, 0.1, 1.0);
+        }
+
+        glfw_window.set_key_polling(true);
+        glfw_window.set_char_polling(true);
+        glfw_window.set_mouse_button_polling(true);
+        glfw_window.set_cursor_pos_polling(true);
+        glfw_window.set_framebuffer_size_polling(true);
+        glfw_window.set_content_scale_polling(true);
+        glfw_window.set_scroll_polling(true);
+        glfw_window.set_cursor_enter_polling(true);
+        glfw_window.set_focus_polling(true);
+        glfw_window.set_iconify_polling(true);
+        glfw_window.set_drag_and_drop_polling(true);
 
         if self.glfw.0.supports_raw_motion() && self.raw_motion {
             glfw_window.set_raw_mouse_motion(true);
         }
 
-        let keyboard = Keyboard::new(&mut glfw_window);
-        let mouse = Mouse::new(&mut glfw_window, self.mouse_fix_to_centre);
+        if self.mouse_fix_to_centre {
+            glfw_window.set_cursor_mode(CursorMode::Disabled);
+        }
 
         let size = glfw_window.get_framebuffer_size();
         let size = (TexDim::new(size.0), TexDim::new(size.1));
-        let default_framebuffer = DefaultFramebuffer::new(size);
+        let default_framebuffer = DefaultFramebuffer::new(size, glfw_window.get_content_scale());
+        let windowed_rect = (glfw_window.get_pos(), glfw_window.get_size());
 
-        Ok(Window {
-            window_resized,
+        let mut window = Window {
+            _context_locals: context_locals,
+            window_resized: false,
             default_framebuffer,
             events,
             glfw_window,
-            keyboard,
-            mouse,
-        })
+            keyboard: Keyboard::new(),
+            mouse: Mouse::new(),
+            mode: WindowMode::Windowed,
+            windowed_rect,
+            shader_program_context: ShaderProgramContext::new(),
+            framebuffer_context: FramebufferContext::new(),
+        };
+        if self.mode != WindowMode::Windowed {
+            window.set_mode(self.glfw.0, self.mode)?;
+        }
+
+        Ok(window)
     }
 }
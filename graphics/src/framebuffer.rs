@@ -11,18 +11,20 @@ use crate::framebuffer::traits::{
     FramebufferWithStencil,
     FramebufferWithoutExtra,
 };
-use crate::gl_call;
 use crate::texture::{FlatTexture, Texture};
 use crate::types::{FrameBufferId, TexDim, };
+use crate::{debug, gl_call, state_cache};
 
 mod active_framebuffer;
 mod builder;
 mod size;
 pub mod traits;
+mod viewport;
 
 pub use active_framebuffer::{ActiveFramebuffer, FramebufferContext};
 pub use builder::Builder;
 use builder::MissingSize;
+pub use viewport::{Rectangle, Viewport};
 
 pub fn flat_builder<const N: usize>() -> Builder<N, MissingSize, WithoutExtra> {
     Builder::new_flat()
@@ -44,6 +46,7 @@ use attachments::{CubeWithDepth, CubeWithoutExtra, WithStencil, WithoutExtra};
 #[derive(Debug)]
 pub struct DefaultFramebuffer {
     size: (TexDim, TexDim),
+    content_scale: (f32, f32),
 }
 
 pub static DEFAULT_FB_ID: FrameBufferId = FrameBufferId::new(0);
@@ -95,6 +98,8 @@ pub struct Framebuffer<const OUT: usize, X: Attachment> {
     stencil_or_depth: X,
     /// A default screen-size quad whose texture(s) are those drawn onto.
     textures: [Rc<RefCell<X::Tex>>; OUT],
+    /// Debug label, kept so that it can be reapplied after a resize
+    label: Option<String>,
     // quad: Quad<OUT>,
 }
 
@@ -104,6 +109,7 @@ impl<const OUT: usize, X: Attachment> Drop for Framebuffer<OUT, X> {
         gl_call! {
             gl::DeleteFramebuffers(1, &raw const id);
         }
+        state_cache::invalidate_framebuffer();
     }
 }
 
@@ -145,11 +151,14 @@ impl<const OUT: usize, X: AttachmentWithStencil> FramebufferWithStencil<OUT>
 impl DefaultFramebuffer {
     /// Internal function to generate the default (screen) `FrameBuffer`
     // pub(crate) fn new(window: &Window) -> Self {
-    pub(crate) fn new(size: (TexDim, TexDim)) -> Self {
+    pub(crate) fn new(size: (TexDim, TexDim), content_scale: (f32, f32)) -> Self {
         // let size = window.get_framebuffer_size();
         // let size = (TexDim::new(size.0), TexDim::new(size.1));
 
-        Self { size }
+        Self {
+            size,
+            content_scale,
+        }
     }
 }
 
@@ -174,6 +183,19 @@ impl<const N: usize, X: Attachment> Framebuffer<N, X> {
     pub fn get_all_colour(&self) -> [Rc<RefCell<X::Tex>>; N] {
         self.textures.clone()
     }
+
+    /// Name the framebuffer and its colour textures in debug messages and
+    /// frame debuggers
+    pub fn set_label(&mut self, label: &str) {
+        debug::label(gl::FRAMEBUFFER, self.id.to_primitive(), label);
+        for (index, texture) in self.textures.iter().enumerate() {
+            texture
+                .borrow()
+                .set_label(&format!("{label} colour {index}"));
+        }
+
+        self.label = Some(label.to_string());
+    }
 }
 
 // impl<const N: usize, X: Attachment<Tex = FlatTexture>> Framebuffer<N, X> {
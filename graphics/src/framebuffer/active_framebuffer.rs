@@ -1,16 +1,32 @@
 use std::collections::HashSet;
 use std::marker::PhantomData;
-use std::sync::Mutex;
 
-use super::{FramebufferInternals, FramebufferWithDepth, FramebufferWithStencil};
+use colour::ColourRGBA;
+
+use super::traits::AttachmentWithDepth;
+use super::{
+    Framebuffer,
+    FramebufferInternals,
+    FramebufferWithDepth,
+    FramebufferWithStencil,
+    Rectangle,
+    Viewport,
+};
 use crate::gl_call;
-use crate::types::{FrameBufferId};
+use crate::profiler::Profiler;
+use crate::state_cache::{self, StateCacheStats};
+use crate::trace::{self, TraceEvent};
+use crate::types::{FrameBufferId, GLint, GLuint, TexDim};
 
 #[derive(Debug)]
 pub struct FramebufferContext {
     cleared: HashSet<gl::types::GLuint>,
+    scissor: Option<Rectangle>,
+    current_framebuffer: Option<GLuint>,
+    current_viewport: Option<Rectangle>,
+    profiler: Option<Profiler>,
+    stats: StateCacheStats,
 }
-static IS_INIT: Mutex<bool> = Mutex::new(false);
 
 macro_rules! enable_disable {
     ($name:ident, $glenum:ident) => {
@@ -31,18 +47,28 @@ impl FramebufferContext {
 
     enable_disable!(srgb_framebuffer, FRAMEBUFFER_SRGB);
 
-    pub fn new() -> Option<Self> {
-        let mut is_init = IS_INIT.lock().ok()?;
-        if *is_init {
-            None
-        } else {
-            *is_init = true;
-            Some(Self {
-                cleared: HashSet::new(),
-            })
+    /// Made by each `Window` for its GL context, which must be current, and
+    /// dropped with it
+    pub(crate) fn new() -> Self {
+        gl_call! { gl::Disable(gl::SCISSOR_TEST); }
+
+        Self {
+            cleared: HashSet::new(),
+            scissor: None,
+            current_framebuffer: None,
+            current_viewport: None,
+            profiler: None,
+            stats: StateCacheStats::default(),
         }
     }
 
+    /// Forget the bound framebuffer and viewport, as another window's GL
+    /// context may have taken the notice that they changed
+    pub(crate) fn invalidate(&mut self) {
+        self.current_framebuffer = None;
+        self.current_viewport = None;
+    }
+
     pub fn register<'a, 'b, const OUT: usize, D: FramebufferInternals<OUT>>(
         &'b mut self,
         framebuffer: &'a D,
@@ -56,47 +82,197 @@ impl FramebufferContext {
         }
     }
 
+    /// Bind `framebuffer`, drawing only into `viewport`. The whole
+    /// framebuffer is cleared on its first bind of a frame regardless of the
+    /// scissor, so several views may share one framebuffer.
+    pub fn register_viewport<'a, 'b, const OUT: usize, D: FramebufferInternals<OUT>>(
+        &'b mut self,
+        framebuffer: &'a D,
+        viewport: Viewport,
+    ) -> ActiveFramebuffer<'a, 'b, OUT, D> {
+        let mut active = self.register(framebuffer);
+        active.set_viewport(viewport);
+        active
+    }
+
     pub fn clear(&mut self) {
         self.cleared.clear();
     }
 
+    /// Split the timing of the current `Draw` so that the commands from here
+    /// on are reported as `label`. Does nothing unless profiling is enabled.
+    pub fn profile_section(&mut self, label: &str) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.section(label);
+        }
+    }
+
+    pub(crate) fn set_profiler(&mut self, profiler: Option<Profiler>) {
+        self.profiler = profiler;
+    }
+
+    pub(crate) fn profiler_mut(&mut self) -> Option<&mut Profiler> {
+        self.profiler.as_mut()
+    }
+
+    /// Framebuffer binds and viewport changes made and skipped since the last
+    /// reset
+    pub fn cache_stats(&self) -> StateCacheStats {
+        self.stats
+    }
+
+    pub fn reset_cache_stats(&mut self) {
+        self.stats = StateCacheStats::default();
+    }
+
+    fn bind_framebuffer(&mut self, id: GLuint) {
+        if state_cache::take_framebuffer_dirty() {
+            self.current_framebuffer = None;
+        }
+
+        let needed = self
+            .stats
+            .framebuffer
+            .record(self.current_framebuffer != Some(id));
+        trace::record(|| TraceEvent::BindFramebuffer {
+            framebuffer: id,
+            cached: !needed,
+        });
+
+        if needed {
+            gl_call! {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, id);
+            }
+            self.current_framebuffer = Some(id);
+        }
+    }
+
+    fn viewport(&mut self, area: Rectangle) {
+        let needed = self
+            .stats
+            .viewport
+            .record(self.current_viewport != Some(area));
+        trace::record(|| TraceEvent::Viewport {
+            x: area.x,
+            y: area.y,
+            width: area.width.to_primitive(),
+            height: area.height.to_primitive(),
+            cached: !needed,
+        });
+
+        if needed {
+            gl_call! {
+                gl::Viewport(area.x, area.y, area.width.to_primitive(), area.height.to_primitive());
+            }
+            self.current_viewport = Some(area);
+        }
+    }
+
+    fn scissor(&mut self, scissor: Option<Rectangle>) {
+        if scissor == self.scissor {
+            return;
+        }
+
+        match scissor {
+            Some(rectangle) => {
+                if self.scissor.is_none() {
+                    gl_call! { gl::Enable(gl::SCISSOR_TEST); }
+                }
+                gl_call! {
+                    gl::Scissor(
+                        rectangle.x,
+                        rectangle.y,
+                        rectangle.width.to_primitive(),
+                        rectangle.height.to_primitive(),
+                    );
+                }
+            }
+            None => {
+                gl_call! { gl::Disable(gl::SCISSOR_TEST); }
+            }
+        }
+
+        self.scissor = scissor;
+    }
+
     /// Clear the currently bound Framebuffer and ALL buffer bits
     pub fn clear_framebuffer(&mut self) {
+        unmask();
         gl_call! {
             gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
         }
     }
 }
 
+/// Clears are masked like draws, so let them reach every buffer whatever the
+/// last program's `RenderState` left set
+fn unmask() {
+    gl_call! { gl::DepthMask(gl::TRUE); }
+    gl_call! { gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE); }
+    state_cache::invalidate_render_state();
+}
+
 #[derive(Debug)]
 pub struct ActiveFramebuffer<'a, 'b, const OUT: usize, D: FramebufferInternals<OUT>> {
     _framebuffer: PhantomData<&'a D>,
+    size: (TexDim, TexDim),
+    viewport: Viewport,
     context: &'b mut FramebufferContext,
 }
 
 impl<'a, 'b, const OUT: usize, D: FramebufferInternals<OUT>> ActiveFramebuffer<'a, 'b, OUT, D> {
     pub fn new(framebuffer: &'a D, context: &'b mut FramebufferContext) -> Self {
-        let (width, height) = framebuffer.size();
+        let size = framebuffer.size();
+        let viewport = Viewport::full(size);
 
-        gl_call! {
-            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id().to_primitive());
-        }
-        gl_call! {
-            gl::Viewport(0, 0, width.to_primitive(), height.to_primitive());
-        }
+        context.bind_framebuffer(framebuffer.id().to_primitive());
+        context.viewport(viewport.area);
+        context.scissor(None);
 
         D::enables(context);
 
         Self {
             _framebuffer: PhantomData,
+            size,
+            viewport,
             context,
         }
     }
 
+    /// Restrict drawing to part of the framebuffer, see `Viewport`
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.context.viewport(viewport.area);
+        self.context.scissor(viewport.scissor);
+
+        self.viewport = viewport;
+    }
+
+    /// Draw to the whole framebuffer again
+    pub fn reset_viewport(&mut self) {
+        self.set_viewport(Viewport::full(self.size));
+    }
+
+    pub fn viewport(&self) -> Viewport {
+        self.viewport
+    }
+
     /// Clear the currently bound Framebuffer and ALL buffer bits
     pub fn clear(&mut self) {
         self.context.clear_framebuffer()
     }
+
+    /// Clear only colour buffer `index`, within the viewport's scissor, to
+    /// `colour` rather than the shared clear colour
+    pub fn clear_colour(&mut self, index: usize, colour: ColourRGBA) {
+        if index >= OUT {
+            return;
+        }
+
+        unmask();
+        gl_call! {
+            gl::ClearBufferfv(gl::COLOR, index as GLint, colour.as_array().as_ptr());
+        }
+    }
 }
 
 impl<const OUT: usize, X: FramebufferWithDepth<OUT>> ActiveFramebuffer<'_, '_, OUT, X> {
@@ -105,6 +281,43 @@ impl<const OUT: usize, X: FramebufferWithDepth<OUT>> ActiveFramebuffer<'_, '_, O
     }
 }
 
+impl<const OUT: usize, X: AttachmentWithDepth> ActiveFramebuffer<'_, '_, OUT, Framebuffer<OUT, X>> {
+    /// Copy the depth buffer of `source`, so that what was drawn there hides
+    /// what is drawn here. Copying needs both depth buffers in the same
+    /// format, so `source` has the same attachment. Keep the two the same
+    /// size, as depth cannot be filtered and a different size is stretched to
+    /// the nearest texel, shifting the edges of what it hides.
+    pub fn copy_depth_from<const N: usize>(&mut self, source: &Framebuffer<N, X>) {
+        let (source_width, source_height) = source.size();
+        let (width, height) = self.size;
+
+        gl_call! {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, source.id().to_primitive());
+        }
+        gl_call! {
+            gl::BlitFramebuffer(
+                0,
+                0,
+                source_width.to_primitive(),
+                source_height.to_primitive(),
+                0,
+                0,
+                width.to_primitive(),
+                height.to_primitive(),
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+        // Binding `source` for reading left this framebuffer bound only for
+        // drawing
+        if let Some(id) = self.context.current_framebuffer {
+            gl_call! {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, id);
+            }
+        }
+    }
+}
+
 impl<const OUT: usize, X: FramebufferWithStencil<OUT>> ActiveFramebuffer<'_, '_, OUT, X> {
     pub fn stencil_testing(&mut self, stencil_testing: bool) {
         X::stencil_testing(stencil_testing, self.context);
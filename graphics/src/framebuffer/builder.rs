@@ -7,9 +7,9 @@ use std::rc::Rc;
 use super::attachments::{CubeWithoutExtra, WithDepth, WithStencil, WithoutExtra};
 use super::traits::OptTexBuilderMap;
 use super::{Attachment, CubeWithDepth, Framebuffer};
-use crate::gl_call;
 use crate::texture::{TexBuilder, TexBuilderCanBuild, Texture, TextureHasBuilder};
 use crate::types::{self, FrameBufferId, TexDim};
+use crate::{gl_call, state_cache};
 
 #[derive(Debug, Default)]
 pub struct MissingSize;
@@ -124,6 +124,7 @@ impl<const N: usize, B: Attachment> Builder<N, HasSize, B> {
         gl_call! {
             gl::BindFramebuffer(gl::FRAMEBUFFER, id.to_primitive());
         }
+        state_cache::invalidate_framebuffer();
 
         let colour: [Rc<RefCell<B::Tex>>; N] = array::from_fn(|_| {
             Rc::new(RefCell::new(
@@ -162,6 +163,7 @@ impl<const N: usize, B: Attachment> Builder<N, HasSize, B> {
             id,
             stencil_or_depth,
             textures: colour,
+            label: None,
         }
     }
 }
@@ -37,6 +37,10 @@ impl<const N: usize, D: Attachment> Framebuffer<N, D> {
         }
 
         mem::swap(self, &mut new);
+
+        if let Some(label) = new.label.take() {
+            self.set_label(&label);
+        }
     }
 }
 
@@ -51,4 +55,28 @@ impl DefaultFramebuffer {
         let (x, y) = self.size();
         x.to_primitive() as f32 / y.to_primitive() as f32
     }
+
+    /// Pixels per screen coordinate the OS asks for, above 1.0 on high-DPI
+    /// displays
+    pub fn content_scale(&self) -> (f32, f32) {
+        self.content_scale
+    }
+
+    /// The size in screen coordinates, for laying out UI that should look the
+    /// same size whatever the DPI
+    pub fn logical_size(&self) -> (f32, f32) {
+        let (x, y) = self.size();
+        (
+            x.to_primitive() as f32 / self.content_scale.0,
+            y.to_primitive() as f32 / self.content_scale.1,
+        )
+    }
+
+    pub(crate) fn set_size(&mut self, size: (TexDim, TexDim)) {
+        self.size = size;
+    }
+
+    pub(crate) fn set_content_scale(&mut self, content_scale: (f32, f32)) {
+        self.content_scale = content_scale;
+    }
 }
@@ -3,7 +3,7 @@ use std::rc::Rc;
 
 /// Helpful traits to ensure that certain draw operations cannot be applied to
 /// framebuffer's which are missing particular attachments
-use super::{ActiveFramebuffer, Framebuffer, FramebufferContext};
+use super::{ActiveFramebuffer, Framebuffer, FramebufferContext, Viewport};
 use crate::texture::{Texture, TextureHasBuilder};
 use crate::types::{self, FrameBufferId, TexDim, };
 
@@ -82,6 +82,18 @@ pub trait FramebufferInternals<const OUT: usize>: Sized {
     ) -> ActiveFramebuffer<'a, 'b, OUT, Self> {
         register.register(self)
     }
+
+    /// Bind, drawing only into `viewport` if one is given
+    fn bind_viewport<'a, 'b>(
+        &'a self,
+        register: &'b mut FramebufferContext,
+        viewport: Option<Viewport>,
+    ) -> ActiveFramebuffer<'a, 'b, OUT, Self> {
+        match viewport {
+            Some(viewport) => register.register_viewport(self, viewport),
+            None => register.register(self),
+        }
+    }
 }
 
 pub trait FramebufferWithoutExtra<const OUT: usize>: FramebufferInternals<OUT> {}
@@ -0,0 +1,206 @@
+use crate::types::TexDim;
+
+/// An axis-aligned area of a framebuffer in pixels, measured from the
+/// bottom-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rectangle {
+    pub x: i32,
+    pub y: i32,
+    pub width: TexDim,
+    pub height: TexDim,
+}
+
+impl Rectangle {
+    pub fn new(x: i32, y: i32, width: TexDim, height: TexDim) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole of a framebuffer of the given size
+    pub fn full(size: (TexDim, TexDim)) -> Self {
+        Self::new(0, 0, size.0, size.1)
+    }
+
+    /// A rectangle given as proportions `[0.0, 1.0]` of a framebuffer of the
+    /// given size. Each edge is rounded to the nearest pixel, so rectangles
+    /// sharing an edge neither overlap nor leave a gap. Dimensions are never
+    /// rounded below one pixel.
+    pub fn fraction(
+        size: (TexDim, TexDim),
+        left: f32,
+        bottom: f32,
+        width: f32,
+        height: f32,
+    ) -> Self {
+        let (full_width, full_height) =
+            (size.0.to_primitive() as f32, size.1.to_primitive() as f32);
+
+        let edges = |start: f32, length: f32, full: f32| {
+            let near = (start * full).round() as i32;
+            let far = ((start + length) * full).round() as i32;
+            (near, TexDim::new((far - near).max(1)))
+        };
+        let (x, width) = edges(left, width, full_width);
+        let (y, height) = edges(bottom, height, full_height);
+
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Calculate the aspect ratio (x/y)
+    pub fn aspect_ratio(&self) -> f32 {
+        self.width.to_primitive() as f32 / self.height.to_primitive() as f32
+    }
+}
+
+/// Where, inside a framebuffer, drawing takes place. The viewport maps
+/// normalised device coordinates onto `area`; when a scissor rectangle is
+/// given, fragments (and clears) outside of it are discarded.
+///
+/// Groups given one draw into only that part of their framebuffer, such as
+/// one player's view of a split-screen or an inset preview.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Viewport {
+    pub area: Rectangle,
+    pub scissor: Option<Rectangle>,
+}
+
+impl Viewport {
+    pub fn new(area: Rectangle) -> Self {
+        Self {
+            area,
+            scissor: None,
+        }
+    }
+
+    /// The whole of a framebuffer of the given size, without a scissor
+    pub fn full(size: (TexDim, TexDim)) -> Self {
+        Self::new(Rectangle::full(size))
+    }
+
+    pub fn scissor(self, scissor: Rectangle) -> Self {
+        Self {
+            scissor: Some(scissor),
+            ..self
+        }
+    }
+
+    /// Clip drawing to exactly the viewport's own area
+    pub fn scissored(self) -> Self {
+        self.scissor(self.area)
+    }
+
+    pub fn aspect_ratio(&self) -> f32 {
+        self.area.aspect_ratio()
+    }
+
+    /// Divide a framebuffer into `count` scissored views for local
+    /// multiplayer, in reading order from the top-left.
+    ///   * 1: the whole framebuffer
+    ///   * 2: left and right halves
+    ///   * 3: top half, then bottom-left and bottom-right quarters
+    ///   * 4 or more: a grid as close to square as fits them, widest first,
+    ///     leaving the end of the last row empty when they do not fill it
+    pub fn split_screen(size: (TexDim, TexDim), count: usize) -> Vec<Self> {
+        let fractions: Vec<(f32, f32, f32, f32)> = match count {
+            0 => vec![],
+            1 => vec![(0.0, 0.0, 1.0, 1.0)],
+            2 => vec![(0.0, 0.0, 0.5, 1.0), (0.5, 0.0, 0.5, 1.0)],
+            3 => vec![
+                (0.0, 0.5, 1.0, 0.5),
+                (0.0, 0.0, 0.5, 0.5),
+                (0.5, 0.0, 0.5, 0.5),
+            ],
+            _ => {
+                let columns = (count as f32).sqrt().ceil() as usize;
+                let rows = count.div_ceil(columns);
+                let (width, height) = (1.0 / columns as f32, 1.0 / rows as f32);
+
+                (0..count)
+                    .map(|index| {
+                        let (row, column) = (index / columns, index % columns);
+                        let bottom = (rows - 1 - row) as f32 * height;
+                        (column as f32 * width, bottom, width, height)
+                    })
+                    .collect()
+            }
+        };
+
+        fractions
+            .into_iter()
+            .map(|(left, bottom, width, height)| {
+                Self::new(Rectangle::fraction(size, left, bottom, width, height)).scissored()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn size(width: i32, height: i32) -> (TexDim, TexDim) {
+        (TexDim::new(width), TexDim::new(height))
+    }
+
+    fn span(rectangle: Rectangle) -> (i32, i32, i32, i32) {
+        let Rectangle {
+            x,
+            y,
+            width,
+            height,
+        } = rectangle;
+        (x, y, x + width.to_primitive(), y + height.to_primitive())
+    }
+
+    #[test]
+    fn test_fraction_shares_edges() {
+        let left = Rectangle::fraction(size(5, 3), 0.0, 0.0, 0.5, 1.0);
+        let right = Rectangle::fraction(size(5, 3), 0.5, 0.0, 0.5, 1.0);
+        assert_eq!(span(left), (0, 0, 3, 3));
+        assert_eq!(span(right), (3, 0, 5, 3));
+
+        let tiny = Rectangle::fraction(size(5, 3), 0.0, 0.0, 0.01, 0.01);
+        assert_eq!(span(tiny), (0, 0, 1, 1));
+    }
+
+    #[test]
+    fn test_split_screen() {
+        let spans = |count| {
+            Viewport::split_screen(size(6, 4), count)
+                .into_iter()
+                .map(|viewport| {
+                    assert_eq!(viewport.scissor, Some(viewport.area));
+                    span(viewport.area)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert!(spans(0).is_empty());
+        assert_eq!(spans(1), [(0, 0, 6, 4)]);
+        assert_eq!(spans(2), [(0, 0, 3, 4), (3, 0, 6, 4)]);
+        assert_eq!(spans(3), [(0, 2, 6, 4), (0, 0, 3, 2), (3, 0, 6, 2)]);
+        assert_eq!(
+            spans(4),
+            [(0, 2, 3, 4), (3, 2, 6, 4), (0, 0, 3, 2), (3, 0, 6, 2)]
+        );
+        assert_eq!(
+            spans(5),
+            [
+                (0, 2, 2, 4),
+                (2, 2, 4, 4),
+                (4, 2, 6, 4),
+                (0, 0, 2, 2),
+                (2, 0, 4, 2),
+            ]
+        );
+    }
+}
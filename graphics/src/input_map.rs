@@ -0,0 +1,869 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::environment::{
+    Action,
+    Button,
+    GAMEPAD_BUTTONS,
+    Gamepad,
+    GamepadAxis,
+    GamepadButton,
+    Key,
+};
+use crate::error::{Error, Result};
+
+/// Every key that can be named in a binding, in the order of `glfw::Key`
+const KEYS: &[Key] = &[
+    Key::Space,
+    Key::Apostrophe,
+    Key::Comma,
+    Key::Minus,
+    Key::Period,
+    Key::Slash,
+    Key::Num0,
+    Key::Num1,
+    Key::Num2,
+    Key::Num3,
+    Key::Num4,
+    Key::Num5,
+    Key::Num6,
+    Key::Num7,
+    Key::Num8,
+    Key::Num9,
+    Key::Semicolon,
+    Key::Equal,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::LeftBracket,
+    Key::Backslash,
+    Key::RightBracket,
+    Key::GraveAccent,
+    Key::World1,
+    Key::World2,
+    Key::Escape,
+    Key::Enter,
+    Key::Tab,
+    Key::Backspace,
+    Key::Insert,
+    Key::Delete,
+    Key::Right,
+    Key::Left,
+    Key::Down,
+    Key::Up,
+    Key::PageUp,
+    Key::PageDown,
+    Key::Home,
+    Key::End,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::NumLock,
+    Key::PrintScreen,
+    Key::Pause,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+    Key::F21,
+    Key::F22,
+    Key::F23,
+    Key::F24,
+    Key::F25,
+    Key::Kp0,
+    Key::Kp1,
+    Key::Kp2,
+    Key::Kp3,
+    Key::Kp4,
+    Key::Kp5,
+    Key::Kp6,
+    Key::Kp7,
+    Key::Kp8,
+    Key::Kp9,
+    Key::KpDecimal,
+    Key::KpDivide,
+    Key::KpMultiply,
+    Key::KpSubtract,
+    Key::KpAdd,
+    Key::KpEnter,
+    Key::KpEqual,
+    Key::LeftShift,
+    Key::LeftControl,
+    Key::LeftAlt,
+    Key::LeftSuper,
+    Key::RightShift,
+    Key::RightControl,
+    Key::RightAlt,
+    Key::RightSuper,
+    Key::Menu,
+];
+
+/// Mouse buttons, named `Mouse1` to `Mouse8` in bindings
+const BUTTONS: &[Button] = &[
+    Button::Button1,
+    Button::Button2,
+    Button::Button3,
+    Button::Button4,
+    Button::Button5,
+    Button::Button6,
+    Button::Button7,
+    Button::Button8,
+];
+
+/// Modifier keys that must be held, on either side of the keyboard, for a
+/// `Binding` to apply
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub control: bool,
+    pub alt: bool,
+    pub super_: bool,
+}
+
+impl Modifiers {
+    const NAMES: [&'static str; 4] = ["Shift", "Control", "Alt", "Super"];
+
+    pub fn from_keys(keys: &HashSet<Key>) -> Self {
+        let either = |left, right| keys.contains(&left) || keys.contains(&right);
+        Self {
+            shift: either(Key::LeftShift, Key::RightShift),
+            control: either(Key::LeftControl, Key::RightControl),
+            alt: either(Key::LeftAlt, Key::RightAlt),
+            super_: either(Key::LeftSuper, Key::RightSuper),
+        }
+    }
+
+    fn as_array(self) -> [bool; 4] {
+        [self.shift, self.control, self.alt, self.super_]
+    }
+
+    fn as_array_mut(&mut self) -> [&mut bool; 4] {
+        [
+            &mut self.shift,
+            &mut self.control,
+            &mut self.alt,
+            &mut self.super_,
+        ]
+    }
+
+    /// Every modifier in `self` is also in `held`
+    pub fn is_subset(self, held: Self) -> bool {
+        std::iter::zip(self.as_array(), held.as_array()).all(|(needed, held)| !needed || held)
+    }
+
+    /// How many modifiers are held
+    pub fn count(self) -> usize {
+        self.as_array().into_iter().filter(|&held| held).count()
+    }
+}
+
+impl From<glfw::Modifiers> for Modifiers {
+    fn from(modifiers: glfw::Modifiers) -> Self {
+        Self {
+            shift: modifiers.contains(glfw::Modifiers::Shift),
+            control: modifiers.contains(glfw::Modifiers::Control),
+            alt: modifiers.contains(glfw::Modifiers::Alt),
+            super_: modifiers.contains(glfw::Modifiers::Super),
+        }
+    }
+}
+
+/// A physical key, mouse button or button on any gamepad, written by name as
+/// in `Binding`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Input {
+    Key(Key),
+    Mouse(Button),
+    Gamepad(GamepadButton),
+}
+
+/// A key or button being pressed, released or repeated by the OS
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InputEvent {
+    pub input: Input,
+    #[serde(with = "serde_action")]
+    pub action: Action,
+    /// Modifier keys held at the time
+    pub modifiers: Modifiers,
+    /// Seconds since GLFW was initialised, the clock of `Event::ActualTime`
+    pub time: f64,
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Key(key) => write!(f, "{key:?}"),
+            Self::Mouse(button) => {
+                let index = BUTTONS.iter().position(|b| b == button).unwrap_or(0);
+                write!(f, "Mouse{}", index + 1)
+            }
+            Self::Gamepad(button) => {
+                let name = format!("{button:?}");
+                write!(f, "Pad{}", name.strip_prefix("Button").unwrap_or(&name))
+            }
+        }
+    }
+}
+
+impl FromStr for Input {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        let key = KEYS
+            .iter()
+            .find(|key| format!("{key:?}").eq_ignore_ascii_case(name));
+        if let Some(key) = key {
+            return Ok(Self::Key(*key));
+        }
+
+        let gamepad_button = GAMEPAD_BUTTONS.iter().find(|button| {
+            Self::Gamepad(**button)
+                .to_string()
+                .eq_ignore_ascii_case(name)
+        });
+        if let Some(button) = gamepad_button {
+            return Ok(Self::Gamepad(*button));
+        }
+
+        let button = match name.to_ascii_lowercase().as_str() {
+            "mouseleft" => Some(Button::Button1),
+            "mouseright" => Some(Button::Button2),
+            "mousemiddle" => Some(Button::Button3),
+            lower => lower
+                .strip_prefix("mouse")
+                .and_then(|index| index.parse::<usize>().ok())
+                .and_then(|index| BUTTONS.get(index.checked_sub(1)?).copied()),
+        };
+
+        button
+            .map(Self::Mouse)
+            .ok_or_else(|| Error::Other(format!("unknown key or button {name:?}")))
+    }
+}
+
+/// An input with the modifiers that must be held alongside it, written as
+/// `"W"`, `"Mouse1"` or `"Shift+Control+S"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Binding {
+    pub input: Input,
+    pub modifiers: Modifiers,
+}
+
+impl Binding {
+    pub fn key(key: Key) -> Self {
+        Self {
+            input: Input::Key(key),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn mouse(button: Button) -> Self {
+        Self {
+            input: Input::Mouse(button),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn gamepad(button: GamepadButton) -> Self {
+        Self {
+            input: Input::Gamepad(button),
+            modifiers: Modifiers::default(),
+        }
+    }
+
+    pub fn with(mut self, modifiers: Modifiers) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+
+    /// The input is held along with at least the binding's modifiers
+    pub fn is_held(&self, snapshot: &InputSnapshot) -> bool {
+        let input_held = match self.input {
+            Input::Key(key) => snapshot.keys.contains(&key),
+            Input::Mouse(button) => snapshot.buttons.contains(&button),
+            Input::Gamepad(button) => snapshot
+                .gamepads
+                .iter()
+                .any(|gamepad| gamepad.buttons.contains(&button)),
+        };
+
+        input_held
+            && self
+                .modifiers
+                .is_subset(Modifiers::from_keys(snapshot.keys))
+    }
+
+    /// Any of `actions` happened to the input this frame with at least the
+    /// binding's modifiers held
+    fn happened(&self, snapshot: &InputSnapshot, actions: &[Action]) -> bool {
+        snapshot.events.iter().any(|event| {
+            event.input == self.input
+                && actions.contains(&event.action)
+                && self.modifiers.is_subset(event.modifiers)
+        })
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, held) in std::iter::zip(Modifiers::NAMES, self.modifiers.as_array()) {
+            if held {
+                write!(f, "{name}+")?;
+            }
+        }
+        write!(f, "{}", self.input)
+    }
+}
+
+impl FromStr for Binding {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let input = parts.pop().unwrap_or_default().parse()?;
+
+        let mut modifiers = Modifiers::default();
+        for part in parts {
+            let index = Modifiers::NAMES
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(part))
+                .ok_or_else(|| Error::Other(format!("unknown modifier {part:?} in {text:?}")))?;
+            *modifiers.as_array_mut()[index] = true;
+        }
+
+        Ok(Self { input, modifiers })
+    }
+}
+
+impl TryFrom<String> for Input {
+    type Error = Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        text.parse()
+    }
+}
+
+impl From<Input> for String {
+    fn from(input: Input) -> Self {
+        input.to_string()
+    }
+}
+
+macro_rules! input_kind {
+    ($variant:ident($type:ty)) => {
+        impl From<$type> for Input {
+            fn from(value: $type) -> Self {
+                Self::$variant(value)
+            }
+        }
+
+        impl TryFrom<Input> for $type {
+            type Error = Input;
+
+            fn try_from(input: Input) -> std::result::Result<Self, Input> {
+                match input {
+                    Input::$variant(value) => Ok(value),
+                    other => Err(other),
+                }
+            }
+        }
+    };
+}
+
+input_kind!(Key(Key));
+input_kind!(Mouse(Button));
+input_kind!(Gamepad(GamepadButton));
+
+/// Serialise `glfw::Action` by name, for `#[serde(with = "serde_action")]`
+mod serde_action {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::environment::Action;
+
+    const NAMES: [&str; 3] = ["press", "release", "repeat"];
+
+    pub(super) fn serialize<S: Serializer>(
+        action: &Action,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let name = match action {
+            Action::Press => NAMES[0],
+            Action::Release => NAMES[1],
+            Action::Repeat => NAMES[2],
+        };
+        serializer.serialize_str(name)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Action, D::Error> {
+        match String::deserialize(deserializer)?.as_str() {
+            "press" => Ok(Action::Press),
+            "release" => Ok(Action::Release),
+            "repeat" => Ok(Action::Repeat),
+            other => Err(D::Error::unknown_variant(other, &NAMES)),
+        }
+    }
+}
+
+/// Serialise a set of keys or buttons as a sorted list of names, for
+/// `#[serde(with = "input_set")]`
+pub(crate) mod input_set {
+    use std::collections::HashSet;
+    use std::hash::Hash;
+
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Input;
+
+    pub(crate) fn serialize<S, T>(set: &HashSet<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Copy + Into<Input>,
+    {
+        let mut names: Vec<String> = set
+            .iter()
+            .map(|&value| Into::<Input>::into(value).to_string())
+            .collect();
+        names.sort();
+        names.serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D, T>(deserializer: D) -> Result<HashSet<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TryFrom<Input, Error = Input> + Eq + Hash,
+    {
+        Vec::<Input>::deserialize(deserializer)?
+            .into_iter()
+            .map(|input| {
+                T::try_from(input)
+                    .map_err(|input| D::Error::custom(format!("unexpected kind of input {input}")))
+            })
+            .collect()
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        text.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> Self {
+        binding.to_string()
+    }
+}
+
+/// A direction of mouse movement or of the scroll wheel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MouseAxis {
+    X,
+    Y,
+    ScrollX,
+    ScrollY,
+}
+
+/// A value built from a pair of opposing bindings, worth `1` and `-1`, and
+/// optionally the mouse movement since the last frame and a gamepad axis, all
+/// multiplied by `scale`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AxisBinding {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub positive: Vec<Binding>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub negative: Vec<Binding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mouse: Option<MouseAxis>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<GamepadAxis>,
+    #[serde(default = "AxisBinding::default_scale")]
+    pub scale: f32,
+}
+
+impl Default for AxisBinding {
+    fn default() -> Self {
+        Self {
+            positive: Vec::new(),
+            negative: Vec::new(),
+            mouse: None,
+            gamepad: None,
+            scale: Self::default_scale(),
+        }
+    }
+}
+
+impl AxisBinding {
+    fn default_scale() -> f32 {
+        1.0
+    }
+
+    pub fn keys(positive: Binding, negative: Binding) -> Self {
+        Self {
+            positive: vec![positive],
+            negative: vec![negative],
+            ..Self::default()
+        }
+    }
+
+    pub fn mouse(axis: MouseAxis, scale: f32) -> Self {
+        Self {
+            mouse: Some(axis),
+            scale,
+            ..Self::default()
+        }
+    }
+
+    pub fn value(&self, snapshot: &InputSnapshot) -> f32 {
+        let any_held = |bindings: &[Binding]| bindings.iter().any(|b| b.is_held(snapshot));
+
+        let mut value = 0.0;
+        if any_held(&self.positive) {
+            value += 1.0;
+        }
+        if any_held(&self.negative) {
+            value -= 1.0;
+        }
+        value += match self.mouse {
+            Some(MouseAxis::X) => snapshot.mouse_delta.0 as f32,
+            Some(MouseAxis::Y) => snapshot.mouse_delta.1 as f32,
+            Some(MouseAxis::ScrollX) => snapshot.scroll.0 as f32,
+            Some(MouseAxis::ScrollY) => snapshot.scroll.1 as f32,
+            None => 0.0,
+        };
+        // With several controllers, the one pushed furthest wins
+        if let Some(axis) = self.gamepad {
+            value += snapshot
+                .gamepads
+                .iter()
+                .map(|gamepad| gamepad.axis(axis))
+                .max_by(|a, b| a.abs().total_cmp(&b.abs()))
+                .unwrap_or(0.0);
+        }
+
+        value * self.scale
+    }
+}
+
+/// The raw input of one frame, as delivered by `Event::Keyboard`,
+/// `Event::Mouse` and `Event::Gamepad`
+#[derive(Debug, Clone, Copy)]
+pub struct InputSnapshot<'a> {
+    pub keys: &'a HashSet<Key>,
+    pub buttons: &'a HashSet<Button>,
+    pub mouse_delta: (f64, f64),
+    /// Total scrolled this frame, from `Event::Scroll`
+    pub scroll: (f64, f64),
+    /// Every connected controller
+    pub gamepads: &'a [Gamepad],
+    /// Keys and buttons that changed this frame, from `Event::Input`
+    pub events: &'a [InputEvent],
+}
+
+/// Named actions and axes, each bound to any number of inputs, so that
+/// controls can be rebound from a file rather than in code
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputMap {
+    #[serde(default)]
+    actions: BTreeMap<String, Vec<Binding>>,
+    #[serde(default)]
+    axes: BTreeMap<String, AxisBinding>,
+}
+
+impl InputMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `binding` to the inputs that trigger `action`
+    pub fn bind(mut self, action: &str, binding: Binding) -> Self {
+        self.actions
+            .entry(action.to_string())
+            .or_default()
+            .push(binding);
+        self
+    }
+
+    pub fn bind_axis(mut self, axis: &str, binding: AxisBinding) -> Self {
+        self.axes.insert(axis.to_string(), binding);
+        self
+    }
+
+    /// Replace every binding of `action`
+    pub fn rebind(&mut self, action: &str, bindings: Vec<Binding>) {
+        self.actions.insert(action.to_string(), bindings);
+    }
+
+    pub fn rebind_axis(&mut self, axis: &str, binding: AxisBinding) {
+        self.axes.insert(axis.to_string(), binding);
+    }
+
+    pub fn bindings(&self, action: &str) -> &[Binding] {
+        self.actions
+            .get(action)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Add any actions and axes of `defaults` which are missing, so that a
+    /// saved file from an older version still binds everything
+    pub fn or_defaults(mut self, defaults: Self) -> Self {
+        for (action, bindings) in defaults.actions {
+            self.actions.entry(action).or_insert(bindings);
+        }
+        for (axis, binding) in defaults.axes {
+            self.axes.entry(axis).or_insert(binding);
+        }
+        self
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| Error::Other(format!("reading {}: {error}", path.display())))?;
+
+        toml::from_str(&text)
+            .map_err(|error| Error::Other(format!("parsing {}: {error}", path.display())))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = toml::to_string_pretty(self)
+            .map_err(|error| Error::Other(format!("serialising input map: {error}")))?;
+
+        fs::write(path, text)
+            .map_err(|error| Error::Other(format!("writing {}: {error}", path.display())))
+    }
+
+    /// Resolve every action and axis against one frame of input. Of the
+    /// bindings matching one input, only those with the most modifiers
+    /// trigger their actions, so `Control+S` does not also trigger `S`.
+    pub fn resolve(&self, snapshot: InputSnapshot) -> Actions {
+        let matching = |test: &dyn Fn(&Binding) -> bool| {
+            let matches: Vec<(&String, &Binding)> = self
+                .actions
+                .iter()
+                .flat_map(|(action, bindings)| bindings.iter().map(move |b| (action, b)))
+                .filter(|(_, binding)| test(binding))
+                .collect();
+
+            matches
+                .iter()
+                .filter(|(_, binding)| {
+                    matches.iter().all(|(_, other)| {
+                        other.input != binding.input
+                            || other.modifiers.count() <= binding.modifiers.count()
+                    })
+                })
+                .map(|(action, _)| (*action).clone())
+                .collect()
+        };
+
+        let held = matching(&|binding| binding.is_held(&snapshot));
+        let pressed = matching(&|binding| binding.happened(&snapshot, &[Action::Press]));
+        let repeated =
+            matching(&|binding| binding.happened(&snapshot, &[Action::Press, Action::Repeat]));
+        let released = matching(&|binding| binding.happened(&snapshot, &[Action::Release]));
+
+        let axes = self
+            .axes
+            .iter()
+            .map(|(axis, binding)| (axis.clone(), binding.value(&snapshot)))
+            .collect();
+
+        Actions {
+            held,
+            pressed,
+            repeated,
+            released,
+            axes,
+        }
+    }
+}
+
+/// The state of every action and axis of an `InputMap` during one frame
+#[derive(Debug, Clone, Default)]
+pub struct Actions {
+    held: HashSet<String>,
+    pressed: HashSet<String>,
+    repeated: HashSet<String>,
+    released: HashSet<String>,
+    axes: HashMap<String, f32>,
+}
+
+impl Actions {
+    pub fn is_held(&self, action: &str) -> bool {
+        self.held.contains(action)
+    }
+
+    /// The action started this frame, for toggles
+    pub fn is_pressed(&self, action: &str) -> bool {
+        self.pressed.contains(action)
+    }
+
+    /// The action started or was repeated by the OS this frame, for menu
+    /// navigation
+    pub fn is_repeated(&self, action: &str) -> bool {
+        self.repeated.contains(action)
+    }
+
+    pub fn is_released(&self, action: &str) -> bool {
+        self.released.contains(action)
+    }
+
+    /// The value of `axis`, or `0` if it is not bound
+    pub fn axis(&self, axis: &str) -> f32 {
+        self.axes.get(axis).copied().unwrap_or(0.0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binding_round_trip() {
+        for text in ["W", "Shift+Control+S", "Mouse2", "Alt+F4", "PadDpadUp"] {
+            let binding: Binding = text.parse().unwrap();
+            assert_eq!(binding.to_string(), text);
+        }
+
+        assert_eq!(
+            "mouseleft".parse::<Binding>().unwrap(),
+            Binding::mouse(Button::Button1)
+        );
+        assert!("Hyper+W".parse::<Binding>().is_err());
+        assert!("Mouse9".parse::<Binding>().is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let map = InputMap::new()
+            .bind("save", "Control+S".parse().unwrap())
+            .bind("back", Binding::key(Key::S))
+            .bind_axis(
+                "forward",
+                AxisBinding::keys(Binding::key(Key::W), Binding::key(Key::S)),
+            )
+            .bind_axis("look", AxisBinding::mouse(MouseAxis::X, 0.5));
+
+        let keys = HashSet::from([Key::S, Key::RightControl]);
+        let buttons = HashSet::new();
+        let actions = map.resolve(InputSnapshot {
+            keys: &keys,
+            buttons: &buttons,
+            mouse_delta: (4.0, 0.0),
+            scroll: (0.0, 0.0),
+            gamepads: &[],
+            events: &[],
+        });
+
+        assert!(actions.is_held("save"));
+        assert!(!actions.is_held("back"));
+        assert_eq!(actions.axis("forward"), -1.0);
+        assert_eq!(actions.axis("look"), 2.0);
+        assert_eq!(actions.axis("unbound"), 0.0);
+    }
+
+    #[test]
+    fn test_edges() {
+        let map = InputMap::new()
+            .bind("toggle", Binding::key(Key::B))
+            .bind("save", "Control+S".parse().unwrap());
+
+        let event = |key, action, control| InputEvent {
+            input: Input::Key(key),
+            action,
+            modifiers: Modifiers {
+                control,
+                ..Modifiers::default()
+            },
+            time: 0.0,
+        };
+        let events = [
+            event(Key::B, Action::Repeat, false),
+            event(Key::S, Action::Press, false),
+            event(Key::S, Action::Release, true),
+            event(Key::B, Action::Release, true),
+        ];
+
+        let keys = HashSet::new();
+        let buttons = HashSet::new();
+        let actions = map.resolve(InputSnapshot {
+            keys: &keys,
+            buttons: &buttons,
+            mouse_delta: (0.0, 0.0),
+            scroll: (0.0, 0.0),
+            gamepads: &[],
+            events: &events,
+        });
+
+        assert!(!actions.is_pressed("toggle"));
+        assert!(actions.is_repeated("toggle"));
+        assert!(!actions.is_pressed("save"));
+        assert!(actions.is_released("save"));
+        // Nothing more specific is bound to B, so Control does not stop it
+        assert!(actions.is_released("toggle"));
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let map = InputMap::new()
+            .bind("quit", Binding::key(Key::Escape))
+            .bind_axis("look", AxisBinding::mouse(MouseAxis::Y, 0.1));
+
+        let text = toml::to_string(&map).unwrap();
+        assert_eq!(toml::from_str::<InputMap>(&text).unwrap(), map);
+    }
+}
@@ -8,10 +8,17 @@
 #![feature(lazy_type_alias)]
 #![feature(inherent_associated_types)]
 
+pub mod context_local;
+pub mod debug;
 mod environment;
 pub mod error;
+pub mod input_map;
+pub mod profiler;
+pub mod query;
 pub mod shader_program;
+pub mod state_cache;
 pub mod texture;
+pub mod trace;
 pub mod types;
 
 pub mod element_array_buffer;
@@ -19,7 +26,27 @@ pub mod framebuffer;
 pub mod vertex;
 pub mod vertex_array;
 pub mod vertex_buffer;
-pub use environment::{Draw, Environment, Event, GlobalState, Key};
+pub use environment::{
+    Action,
+    Draw,
+    Environment,
+    Event,
+    FrameLimiter,
+    Gamepad,
+    GamepadAxis,
+    GamepadButton,
+    GamepadEvent,
+    GlobalState,
+    Key,
+    Monitor,
+    SharedResources,
+    StackedState,
+    StateStack,
+    Transition,
+    VideoMode,
+    WindowId,
+    WindowMode,
+};
 pub use error::{Error, Result};
 pub use shader_program::{ActiveShaderProgram, ShaderProgram, ShaderProgramContext};
 pub use {colour, linear_algebra};
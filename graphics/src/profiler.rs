@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Display};
+use std::time::{Duration, Instant};
+
+use crate::query::TimerQuery;
+use crate::state_cache::StateCacheStats;
+
+/// Frames whose timer queries are still waiting on the GPU before the oldest
+/// is read back by stalling.
+const MAX_FRAMES_IN_FLIGHT: usize = 4;
+
+/// The time taken by one labelled part of a frame
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub label: String,
+    /// Time spent by the GPU executing the section's commands
+    pub gpu: Duration,
+    /// Time spent by the CPU issuing the section's commands
+    pub cpu: Duration,
+}
+
+/// Per-frame breakdown produced by `Profiler`, in the order the sections were
+/// drawn. Timings arrive a few frames after the frame they describe.
+#[derive(Debug, Clone, Default)]
+pub struct FrameProfile {
+    pub frame: u64,
+    pub sections: Vec<Section>,
+    /// Redundant state changes skipped during the frame
+    pub state_cache: StateCacheStats,
+}
+
+impl FrameProfile {
+    pub fn gpu_total(&self) -> Duration {
+        self.sections.iter().map(|section| section.gpu).sum()
+    }
+
+    pub fn cpu_total(&self) -> Duration {
+        self.sections.iter().map(|section| section.cpu).sum()
+    }
+}
+
+impl Display for FrameProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .sections
+            .iter()
+            .map(|section| section.label.len())
+            .max()
+            .unwrap_or(0)
+            .max("total".len());
+
+        writeln!(f, "frame {}:", self.frame)?;
+        writeln!(f, "  {:<width$}  {:>9}  {:>9}", "", "gpu (ms)", "cpu (ms)")?;
+        for section in &self.sections {
+            writeln!(
+                f,
+                "  {:<width$}  {:>9.3}  {:>9.3}",
+                section.label,
+                section.gpu.as_secs_f64() * 1000.0,
+                section.cpu.as_secs_f64() * 1000.0,
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:<width$}  {:>9.3}  {:>9.3}",
+            "total",
+            self.gpu_total().as_secs_f64() * 1000.0,
+            self.cpu_total().as_secs_f64() * 1000.0,
+        )?;
+        write!(f, "{}", self.state_cache)
+    }
+}
+
+#[derive(Debug)]
+struct OpenSection {
+    label: String,
+    query: TimerQuery,
+    start: Instant,
+}
+
+#[derive(Debug)]
+struct PendingFrame {
+    frame: u64,
+    sections: Vec<(String, TimerQuery, Duration)>,
+    state_cache: StateCacheStats,
+}
+
+impl PendingFrame {
+    fn is_available(&self) -> bool {
+        self.sections
+            .iter()
+            .all(|(_, query, _)| query.is_available())
+    }
+}
+
+/// Times each `Draw` of a frame with `GL_TIME_ELAPSED` queries. Queries are
+/// recycled and read back without stalling once the GPU has caught up.
+///
+/// Timer queries cannot be nested, so a `Draw` is split into consecutive
+/// sections with `FramebufferContext::profile_section` rather than nested
+/// ones.
+#[derive(Debug, Default)]
+pub struct Profiler {
+    frame: u64,
+    recording: Option<PendingFrame>,
+    open: Option<OpenSection>,
+    /// The `Draw` currently being timed, and whether it has been split
+    draw: Option<(String, bool)>,
+    in_flight: VecDeque<PendingFrame>,
+    spare: Vec<TimerQuery>,
+    latest: Option<FrameProfile>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn begin_frame(&mut self) {
+        if self.recording.is_some() {
+            self.end_frame();
+        }
+
+        self.recording = Some(PendingFrame {
+            frame: self.frame,
+            sections: Vec::new(),
+            state_cache: StateCacheStats::default(),
+        });
+        self.frame += 1;
+    }
+
+    /// Start timing a `Draw`, everything until `end_draw` is labelled `name`
+    pub fn begin_draw(&mut self, name: String) {
+        self.end_draw();
+        self.open_section(name.clone());
+        self.draw = Some((name, false));
+    }
+
+    pub fn end_draw(&mut self) {
+        self.close_section();
+        self.draw = None;
+    }
+
+    /// Label the commands from here on as `label`, within the current `Draw`.
+    /// The first section of a `Draw` also takes over the commands issued
+    /// before it, so call this before doing any work.
+    pub fn section(&mut self, label: &str) {
+        let Some((name, split)) = &self.draw else {
+            self.close_section();
+            self.open_section(label.to_string());
+            return;
+        };
+
+        let label = format!("{name}: {label}");
+        if !*split && let Some(open) = &mut self.open {
+            open.label = label;
+        } else {
+            self.close_section();
+            self.open_section(label);
+        }
+
+        if let Some((_, split)) = &mut self.draw {
+            *split = true;
+        }
+    }
+
+    /// Attach the state changes made during the frame being recorded
+    pub fn record_state_cache(&mut self, state_cache: StateCacheStats) {
+        if let Some(frame) = &mut self.recording {
+            frame.state_cache = state_cache;
+        }
+    }
+
+    /// Finish recording the frame and collect the results of any earlier
+    /// frames that the GPU has finished.
+    pub fn end_frame(&mut self) {
+        self.end_draw();
+
+        if let Some(frame) = self.recording.take() {
+            self.in_flight.push_back(frame);
+        }
+
+        while let Some(frame) = self.in_flight.front() {
+            if self.in_flight.len() <= MAX_FRAMES_IN_FLIGHT && !frame.is_available() {
+                break;
+            }
+            let frame = self.in_flight.pop_front().expect("front exists");
+            self.latest = Some(self.read_frame(frame));
+        }
+    }
+
+    /// The most recent complete breakdown
+    pub fn latest(&self) -> Option<&FrameProfile> {
+        self.latest.as_ref()
+    }
+
+    /// Take the most recent complete breakdown, so that each is only seen
+    /// once
+    pub fn take_latest(&mut self) -> Option<FrameProfile> {
+        self.latest.take()
+    }
+
+    fn open_section(&mut self, label: String) {
+        let mut query = self.spare.pop().unwrap_or_default();
+        query.begin();
+        self.open = Some(OpenSection {
+            label,
+            query,
+            start: Instant::now(),
+        });
+    }
+
+    fn close_section(&mut self) {
+        if let Some(OpenSection {
+            label,
+            mut query,
+            start,
+        }) = self.open.take()
+        {
+            query.end();
+            match &mut self.recording {
+                Some(frame) => frame.sections.push((label, query, start.elapsed())),
+                None => self.spare.push(query),
+            }
+        }
+    }
+
+    fn read_frame(&mut self, frame: PendingFrame) -> FrameProfile {
+        let sections = frame
+            .sections
+            .into_iter()
+            .map(|(label, mut query, cpu)| {
+                let gpu = query.result();
+                self.spare.push(query);
+                Section { label, gpu, cpu }
+            })
+            .collect();
+
+        FrameProfile {
+            frame: frame.frame,
+            sections,
+            state_cache: frame.state_cache,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::gl_call;
+use crate::types::{GLenum, GLint, GLuint64, QueryId};
+
+/// The kind of question a `Query` asks of the GPU
+pub trait QueryTarget {
+    type Output;
+
+    fn target() -> GLenum;
+
+    fn convert(raw: GLuint64) -> Self::Output;
+}
+
+/// How long the GPU spent executing the commands issued between `begin` and
+/// `end`
+#[derive(Debug)]
+pub struct TimeElapsed;
+
+impl QueryTarget for TimeElapsed {
+    type Output = Duration;
+
+    fn target() -> GLenum {
+        gl::TIME_ELAPSED
+    }
+
+    fn convert(raw: GLuint64) -> Self::Output {
+        Duration::from_nanos(raw)
+    }
+}
+
+/// Whether any fragment drawn between `begin` and `end` passed the depth and
+/// stencil tests
+#[derive(Debug)]
+pub struct AnySamplesPassed;
+
+impl QueryTarget for AnySamplesPassed {
+    type Output = bool;
+
+    fn target() -> GLenum {
+        gl::ANY_SAMPLES_PASSED
+    }
+
+    fn convert(raw: GLuint64) -> Self::Output {
+        raw != 0
+    }
+}
+
+pub type TimerQuery = Query<TimeElapsed>;
+pub type OcclusionQuery = Query<AnySamplesPassed>;
+
+/// An asynchronous query object. Only one query of each target may be active
+/// at a time. Results become available some time after `end`, usually a frame
+/// or two later, so poll with `try_result` rather than stalling with `result`.
+#[derive(Debug)]
+pub struct Query<T: QueryTarget> {
+    id: QueryId,
+    active: bool,
+    _target: PhantomData<T>,
+}
+
+impl<T: QueryTarget> Drop for Query<T> {
+    fn drop(&mut self) {
+        if self.active {
+            self.end();
+        }
+        let primitive = self.id.to_primitive();
+        gl_call! {
+            gl::DeleteQueries(1, &raw const primitive);
+        }
+    }
+}
+
+impl<T: QueryTarget> Default for Query<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: QueryTarget> Query<T> {
+    pub fn new() -> Self {
+        let id = {
+            let mut id = 0;
+            gl_call! {
+                gl::GenQueries(1, &raw mut id);
+            }
+            QueryId::new(id)
+        };
+
+        Self {
+            id,
+            active: false,
+            _target: PhantomData,
+        }
+    }
+
+    pub fn begin(&mut self) {
+        if !self.active {
+            gl_call! {
+                gl::BeginQuery(T::target(), self.id.to_primitive());
+            }
+            self.active = true;
+        }
+    }
+
+    pub fn end(&mut self) {
+        if self.active {
+            gl_call! {
+                gl::EndQuery(T::target());
+            }
+            self.active = false;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Whether `try_result` will return a value without waiting on the GPU
+    pub fn is_available(&self) -> bool {
+        if self.active {
+            return false;
+        }
+
+        let mut available: GLint = 0;
+        gl_call! {
+            gl::GetQueryObjectiv(
+                self.id.to_primitive(),
+                gl::QUERY_RESULT_AVAILABLE,
+                &raw mut available,
+            );
+        }
+        available != 0
+    }
+
+    /// The result of the last completed query, or `None` if the GPU has not
+    /// finished with it yet
+    pub fn try_result(&self) -> Option<T::Output> {
+        self.is_available().then(|| self.read())
+    }
+
+    /// The result of the last completed query, blocking until the GPU has
+    /// finished with it
+    pub fn result(&mut self) -> T::Output {
+        self.end();
+        self.read()
+    }
+
+    fn read(&self) -> T::Output {
+        let mut raw: GLuint64 = 0;
+        gl_call! {
+            gl::GetQueryObjectui64v(self.id.to_primitive(), gl::QUERY_RESULT, &raw mut raw);
+        }
+        T::convert(raw)
+    }
+}
@@ -4,4 +4,17 @@ pub use error::Error;
 mod program;
 mod shader;
 
-pub use program::{ActiveShaderProgram, CullFace, ShaderProgram, ShaderProgramContext, Uniform};
+pub use program::{
+    ActiveShaderProgram,
+    Blend,
+    BlendEquation,
+    BlendFactor,
+    ColourMask,
+    CullFace,
+    DepthFunc,
+    PolygonOffset,
+    RenderState,
+    ShaderProgram,
+    ShaderProgramContext,
+    Uniform,
+};
@@ -4,9 +4,9 @@ use std::ffi::CString;
 use std::marker::PhantomData;
 
 pub use super::Error;
-use crate::gl_call;
 use crate::texture::Texture;
 use crate::types::{ShaderProgramId, UniformLocation};
+use crate::{debug, gl_call, state_cache};
 
 mod active_shader;
 mod utils;
@@ -24,11 +24,24 @@ pub use uniform::Uniform;
 mod cull_face;
 pub use cull_face::CullFace;
 
+mod render_state;
+pub use render_state::{
+    Blend,
+    BlendEquation,
+    BlendFactor,
+    ColourMask,
+    DepthFunc,
+    PolygonOffset,
+    RenderState,
+};
+
 #[derive(Debug)]
 pub struct ShaderProgram<M, const OUT: usize, T: Texture> {
     pub(crate) id: ShaderProgramId,
     uniform_locations: RefCell<HashMap<CString, i32>>,
     force_cull_face: Option<CullFace>,
+    render_state: RenderState,
+    label: Option<String>,
     _phantom_model: PhantomData<fn(M)>,
     _phantom_tex: PhantomData<fn(T)>,
 }
@@ -47,15 +60,27 @@ impl<M, const OUT: usize, T: Texture> ShaderProgram<M, OUT, T> {
         &self.id
     }
 
+    pub fn render_state(&self) -> RenderState {
+        self.render_state
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Name the program in debug messages, frame traces and frame debuggers
+    pub fn set_label(&mut self, label: &str) {
+        debug::label(gl::PROGRAM, self.id.to_primitive(), label);
+        self.label = Some(label.to_string());
+    }
+
     pub fn use_program<'a, 'b, 'c>(
         &'a self,
         marker: &'b mut ShaderProgramContext,
     ) -> ActiveShaderProgram<'a, 'b, 'c, M, T, OUT> {
-        gl_call! {
-            gl::UseProgram(self.id.to_primitive());
-        }
-
+        marker.use_program(self);
         marker.force_cull_face(self.force_cull_face);
+        marker.render_state(self.render_state);
 
         ActiveShaderProgram::new(self, marker)
     }
@@ -84,5 +109,6 @@ impl<M, T: Texture, const OUT: usize> Drop for ShaderProgram<M, OUT, T> {
         gl_call! {
             gl::DeleteProgram(self.id.to_primitive());
         }
+        state_cache::invalidate_program();
     }
 }
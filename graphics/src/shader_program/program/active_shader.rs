@@ -1,19 +1,20 @@
-use std::collections::HashMap;
 use std::ffi::CString;
 use std::fmt::Debug;
 use std::sync::LazyLock;
 use std::{iter, ptr};
 
 use super::uniform::Uniform;
-use super::{CullFace, Error, ShaderProgram, ShaderProgramContext};
+use super::{CullFace, Error, RenderState, ShaderProgram, ShaderProgramContext};
 use crate::error::Result;
 use crate::gl_call;
 use crate::texture::{self, Texture};
+use crate::trace::{self, TraceEvent};
 use crate::types::{self, UniformLocation};
 
 pub struct ActiveShaderProgram<'a, 'b, 'c, M, T: Texture, const OUT: usize> {
     shader_program: &'a ShaderProgram<M, OUT, T>,
-    texture_list: HashMap<String, &'c dyn Texture>,
+    /// Samplers in registration order, which decides their texture unit
+    texture_list: Vec<(String, &'c dyn Texture)>,
     context: &'b mut ShaderProgramContext,
 }
 
@@ -44,7 +45,7 @@ impl<'a, 'b, M, T: Texture, const OUT: usize> ActiveShaderProgram<'a, 'b, '_, M,
         Self {
             shader_program,
             context,
-            texture_list: HashMap::new(),
+            texture_list: Vec::new(),
         }
     }
 }
@@ -59,7 +60,14 @@ impl<'c, M, T: Texture, const OUT: usize> ActiveShaderProgram<'_, '_, 'c, M, T,
         texture_list: L,
     ) {
         for (string, texture) in texture_list {
-            self.texture_list.insert(string, texture);
+            match self
+                .texture_list
+                .iter_mut()
+                .find(|(name, _)| *name == string)
+            {
+                Some((_, existing)) => *existing = texture,
+                None => self.texture_list.push((string, texture)),
+            }
         }
     }
 
@@ -87,7 +95,10 @@ impl<'c, M, T: Texture, const OUT: usize> ActiveShaderProgram<'_, '_, 'c, M, T,
         }
     }
 
-    pub fn bind_textures(&self) -> Result<()> {
+    /// Bind every registered texture and point its sampler at it. Units are
+    /// assigned in registration order, so textures shared between draws keep
+    /// their unit and are not bound again.
+    pub fn bind_textures(&mut self) -> Result<()> {
         static MAX_TEX_UNITS: LazyLock<usize> = LazyLock::new(|| {
             let mut out = 0;
             gl_call! {
@@ -98,7 +109,7 @@ impl<'c, M, T: Texture, const OUT: usize> ActiveShaderProgram<'_, '_, 'c, M, T,
 
         if self.texture_list.len() <= *MAX_TEX_UNITS {
             for (index, (name, tex)) in self.texture_list.iter().enumerate() {
-                tex.bind_to(index as u32);
+                self.context.bind_texture(index, *tex);
                 self.set_uniform(name.clone(), index as i32);
             }
             Ok(())
@@ -112,12 +123,22 @@ impl<'c, M, T: Texture, const OUT: usize> ActiveShaderProgram<'_, '_, 'c, M, T,
     }
 
     pub fn set_uniform<U: Uniform>(&self, name: String, value: U) {
+        trace::record(|| TraceEvent::Uniform {
+            name: name.clone(),
+            values: value.trace_values(),
+        });
+
         if let Some(location) = self.get_uniform_location(name) {
             value.set_uniform(location, self);
         }
     }
 
     pub fn set_uniform_ref<U: Uniform>(&self, name: String, value: &U) {
+        trace::record(|| TraceEvent::Uniform {
+            name: name.clone(),
+            values: value.trace_values(),
+        });
+
         if let Some(location) = self.get_uniform_location(name) {
             value.set_uniform_ref(location, self);
         }
@@ -134,6 +155,11 @@ impl<'c, M, T: Texture, const OUT: usize> ActiveShaderProgram<'_, '_, 'c, M, T,
     pub fn drawing_skybox(&mut self, drawing_skybox: bool) {
         self.context.drawing_skybox(drawing_skybox);
     }
+
+    /// Override the program's `RenderState` until the next program is used
+    pub fn render_state(&mut self, render_state: RenderState) {
+        self.context.render_state(render_state);
+    }
 }
 
 pub trait IsActiveShaderProgram {}
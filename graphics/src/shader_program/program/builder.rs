@@ -1,14 +1,16 @@
+use std::fs;
 use std::marker::PhantomData;
 use std::path::Path;
 
 use utils::{builder, new};
 
-use super::{CullFace, ShaderProgram};
+use super::{CullFace, RenderState, ShaderProgram};
 use crate::error::Result;
-use crate::gl_call;
+use crate::shader_program::error::Error;
 use crate::shader_program::shader::Shader;
 use crate::texture::Texture;
 use crate::types::{self, ShaderProgramId};
+use crate::{debug, gl_call};
 
 #[derive(Debug)]
 pub struct VertexShader(Shader);
@@ -26,6 +28,10 @@ pub struct Builder<M, T: Texture, const OUT: usize, V, F> {
     fragment_shader: F,
     geometry_shader: Option<Shader>,
     force_cull_face: Option<CullFace>,
+    render_state: RenderState,
+    label: Option<String>,
+    defines: Vec<String>,
+    vertex_prelude: String,
     _phantom_model: PhantomData<fn(M)>,
     _phantom_tex: PhantomData<fn(T)>,
 }
@@ -39,6 +45,10 @@ impl<M, T: Texture, const OUT: usize> Default
             fragment_shader: MissingFragmentShader,
             geometry_shader: None,
             force_cull_face: None,
+            render_state: RenderState::default(),
+            label: None,
+            defines: Vec::new(),
+            vertex_prelude: String::new(),
             _phantom_model: PhantomData,
             _phantom_tex: PhantomData,
         }
@@ -54,18 +64,59 @@ impl<M, T: Texture, const OUT: usize>
 impl<M, T: Texture, const OUT: usize, V, F> Builder<M, T, OUT, V, F> {
     builder!(force_cull_face: Option<CullFace>);
 
+    builder!(render_state: RenderState);
+
+    // Name the program in debug messages and frame debuggers, defaults to
+    // the vertex shader's path
+    builder!(label: Option<String>);
+
+    /// `#define name` in every shader given after this, to build a variant
+    /// of the same sources
+    pub fn define(mut self, name: &str) -> Self {
+        self.defines.push(name.to_string());
+        self
+    }
+
+    /// Paste the source at `path` after the defines of the vertex shader
+    /// given after this, for functions shared between vertex shaders
+    pub fn vertex_prelude<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let path = path.as_ref();
+
+        self.vertex_prelude =
+            fs::read_to_string(path).map_err(|_| Error::NoSourceFile { path: path.into() })?;
+        Ok(self)
+    }
+
     pub fn vertex_shader<P: AsRef<Path>>(
         self,
         source: P,
     ) -> Result<Builder<M, T, OUT, VertexShader, F>> {
-        Shader::new(gl::VERTEX_SHADER, source).map(|shader| Builder {
+        let label = self
+            .label
+            .clone()
+            .or_else(|| Some(source.as_ref().display().to_string()));
+
+        Shader::new(
+            gl::VERTEX_SHADER,
+            source,
+            &self.defines,
+            &self.vertex_prelude,
+        )
+        .map(|shader| Builder {
             vertex_shader: VertexShader(shader),
+            label,
             ..self
         })
     }
 
     pub fn vertex_shader_raw(self, source: &[u8]) -> Result<Builder<M, T, OUT, VertexShader, F>> {
-        Shader::new_from_slice(gl::VERTEX_SHADER, source).map(|shader| Builder {
+        Shader::new_from_slice(
+            gl::VERTEX_SHADER,
+            source,
+            &self.defines,
+            &self.vertex_prelude,
+        )
+        .map(|shader| Builder {
             vertex_shader: VertexShader(shader),
             ..self
         })
@@ -75,7 +126,7 @@ impl<M, T: Texture, const OUT: usize, V, F> Builder<M, T, OUT, V, F> {
         self,
         source: P,
     ) -> Result<Builder<M, T, OUT, V, FragmentShader>> {
-        Shader::new(gl::FRAGMENT_SHADER, source).map(|shader| Builder {
+        Shader::new(gl::FRAGMENT_SHADER, source, &self.defines, "").map(|shader| Builder {
             fragment_shader: FragmentShader(shader),
             ..self
         })
@@ -85,23 +136,27 @@ impl<M, T: Texture, const OUT: usize, V, F> Builder<M, T, OUT, V, F> {
         self,
         source: &[u8],
     ) -> Result<Builder<M, T, OUT, V, FragmentShader>> {
-        Shader::new_from_slice(gl::FRAGMENT_SHADER, source).map(|shader| Builder {
-            fragment_shader: FragmentShader(shader),
-            ..self
+        Shader::new_from_slice(gl::FRAGMENT_SHADER, source, &self.defines, "").map(|shader| {
+            Builder {
+                fragment_shader: FragmentShader(shader),
+                ..self
+            }
         })
     }
 
     pub fn geometry_shader<P: AsRef<Path>>(self, source: P) -> Result<Self> {
-        Shader::new(gl::GEOMETRY_SHADER, source).map(|shader| Builder {
+        Shader::new(gl::GEOMETRY_SHADER, source, &self.defines, "").map(|shader| Builder {
             geometry_shader: Some(shader),
             ..self
         })
     }
 
     pub fn geometry_shader_raw(self, source: &[u8]) -> Result<Self> {
-        Shader::new_from_slice(gl::GEOMETRY_SHADER, source).map(|shader| Builder {
-            geometry_shader: Some(shader),
-            ..self
+        Shader::new_from_slice(gl::GEOMETRY_SHADER, source, &self.defines, "").map(|shader| {
+            Builder {
+                geometry_shader: Some(shader),
+                ..self
+            }
         })
     }
 }
@@ -151,10 +206,16 @@ impl<M, T: Texture, const OUT: usize> Builder<M, T, OUT, VertexShader, FragmentS
             );
         }
 
+        if let Some(label) = &self.label {
+            debug::label(gl::PROGRAM, program_id.to_primitive(), label);
+        }
+
         ShaderProgram {
             id: program_id,
             uniform_locations: Default::default(),
             force_cull_face: self.force_cull_face,
+            render_state: self.render_state,
+            label: self.label,
             _phantom_model: PhantomData,
             _phantom_tex: PhantomData,
         }
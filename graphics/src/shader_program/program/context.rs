@@ -1,43 +1,115 @@
-use std::sync::Mutex;
-
-use super::{CullFace, Error, ShaderProgram};
+use super::{CullFace, Error, RenderState, ShaderProgram};
 use crate::error::Result;
 use crate::gl_call;
+use crate::state_cache::{self, StateCacheStats};
 use crate::texture::Texture;
+use crate::trace::{self, TraceEvent};
+use crate::types::GLuint;
 
 #[derive(Debug)]
 pub struct ShaderProgramContext {
     current_cull_face: CullFace,
-    current_drawing_skybox: bool,
+    current_render_state: RenderState,
+    /// The state of the program last used, before any override, which
+    /// drawing a skybox returns to
+    program_render_state: RenderState,
+    current_program: Option<GLuint>,
+    /// The texture bound to each texture unit, indexed by unit
+    texture_units: Vec<Option<GLuint>>,
 
     forced_cull_face: Option<CullFace>,
+
+    stats: StateCacheStats,
 }
-static IS_INIT: Mutex<bool> = Mutex::new(false);
 
 impl ShaderProgramContext {
-    pub fn new() -> Option<Self> {
-        let mut is_init = IS_INIT.lock().ok()?;
-        if *is_init {
-            None
-        } else {
-            *is_init = true;
-            Some(ShaderProgramContext {
-                forced_cull_face: None,
-                current_cull_face: CullFace::DoNotCull,
-                current_drawing_skybox: false,
-            })
+    /// Made by each `Window` for its GL context, which must be current, and
+    /// dropped with it
+    pub(crate) fn new() -> Self {
+        let current_render_state = RenderState::default();
+        Self::apply_render_state(None, &current_render_state);
+
+        ShaderProgramContext {
+            forced_cull_face: None,
+            current_cull_face: CullFace::DoNotCull,
+            current_render_state,
+            program_render_state: current_render_state,
+            current_program: None,
+            texture_units: Vec::new(),
+            stats: StateCacheStats::default(),
         }
     }
 
+    /// Forget the bound program and textures, as another window's GL context
+    /// may have taken the notice that they changed
+    pub(crate) fn invalidate(&mut self) {
+        self.current_program = None;
+        self.texture_units.clear();
+    }
+
+    /// Make `program` current, unless it already is
     pub fn use_program<M, const OUT: usize, T: Texture>(
         &mut self,
         program: &ShaderProgram<M, OUT, T>,
     ) {
-        gl_call! {
-            gl::UseProgram(program.id().to_primitive());
+        if state_cache::take_program_dirty() {
+            self.current_program = None;
+        }
+
+        self.program_render_state = program.render_state();
+
+        let id = program.id().to_primitive();
+        let needed = self.stats.program.record(self.current_program != Some(id));
+        trace::record(|| TraceEvent::UseProgram {
+            program: id,
+            label: program.label().map(str::to_string),
+            cached: !needed,
+        });
+
+        if needed {
+            gl_call! {
+                gl::UseProgram(id);
+            }
+            self.current_program = Some(id);
         }
     }
 
+    /// Bind `texture` to texture unit `unit`, unless it is already bound there
+    pub(crate) fn bind_texture(&mut self, unit: usize, texture: &dyn Texture) {
+        if state_cache::take_textures_dirty() {
+            self.texture_units.clear();
+        }
+
+        if self.texture_units.len() <= unit {
+            self.texture_units.resize(unit + 1, None);
+        }
+
+        let id = texture.id().to_primitive();
+        let needed = self
+            .stats
+            .texture
+            .record(self.texture_units[unit] != Some(id));
+        trace::record(|| TraceEvent::BindTexture {
+            unit,
+            texture: id,
+            cached: !needed,
+        });
+
+        if needed {
+            texture.bind_to(unit as u32);
+            self.texture_units[unit] = Some(id);
+        }
+    }
+
+    /// Program and texture binds made and skipped since the last reset
+    pub fn cache_stats(&self) -> StateCacheStats {
+        self.stats
+    }
+
+    pub fn reset_cache_stats(&mut self) {
+        self.stats = StateCacheStats::default();
+    }
+
     fn cull_face_after_check(&mut self, cull_face: CullFace) {
         if cull_face == self.current_cull_face {
             return;
@@ -84,24 +156,87 @@ impl ShaderProgramContext {
     }
 
     pub fn drawing_skybox(&mut self, drawing_skybox: bool) {
-        if drawing_skybox == self.current_drawing_skybox {
+        let (depth_write, depth_func) = if drawing_skybox {
+            let skybox = RenderState::skybox();
+            (skybox.depth_write, skybox.depth_func)
+        } else {
+            let program = self.program_render_state;
+            (program.depth_write, program.depth_func)
+        };
+
+        self.render_state(
+            self.current_render_state
+                .depth_write(depth_write)
+                .depth_func(depth_func),
+        );
+    }
+
+    pub fn current_render_state(&self) -> RenderState {
+        self.current_render_state
+    }
+
+    /// Change the blend, depth, colour mask and polygon offset state. Only the
+    /// parts which differ from the current state result in OpenGL calls.
+    pub fn render_state(&mut self, render_state: RenderState) {
+        if state_cache::take_render_state_dirty() {
+            Self::apply_render_state(None, &render_state);
+            self.current_render_state = render_state;
+            return;
+        }
+        if render_state == self.current_render_state {
             return;
         }
-        self.current_drawing_skybox = drawing_skybox;
 
-        if drawing_skybox {
-            gl_call! {
-                gl::DepthMask(gl::FALSE);
-            }
-            gl_call! {
-                gl::DepthFunc(gl::LEQUAL);
-            }
-        } else {
-            gl_call! {
-                gl::DepthMask(gl::TRUE);
+        Self::apply_render_state(Some(&self.current_render_state), &render_state);
+        self.current_render_state = render_state;
+    }
+
+    /// Send `new` to OpenGL, skipping any part which is equal in `old`. When
+    /// `old` is `None` every part is sent.
+    fn apply_render_state(old: Option<&RenderState>, new: &RenderState) {
+        if old.is_none_or(|old| old.blend != new.blend) {
+            match new.blend {
+                Some(blend) => {
+                    gl_call! { gl::Enable(gl::BLEND); }
+                    gl_call! { gl::BlendEquation(blend.equation.get_enum()); }
+                    gl_call! { gl::BlendFuncSeparate(
+                        blend.source.get_enum(),
+                        blend.destination.get_enum(),
+                        blend.alpha_source.get_enum(),
+                        blend.alpha_destination.get_enum(),
+                    ); }
+                }
+                None => {
+                    gl_call! { gl::Disable(gl::BLEND); }
+                }
             }
-            gl_call! {
-                gl::DepthFunc(gl::LESS);
+        }
+
+        if old.is_none_or(|old| old.depth_func != new.depth_func) {
+            gl_call! { gl::DepthFunc(new.depth_func.get_enum()); }
+        }
+
+        if old.is_none_or(|old| old.depth_write != new.depth_write) {
+            let flag = if new.depth_write { gl::TRUE } else { gl::FALSE };
+            gl_call! { gl::DepthMask(flag); }
+        }
+
+        if old.is_none_or(|old| old.colour_mask != new.colour_mask) {
+            let mask = new.colour_mask;
+            let [red, green, blue, alpha] = [mask.red, mask.green, mask.blue, mask.alpha]
+                .map(|flag| if flag { gl::TRUE } else { gl::FALSE });
+            gl_call! { gl::ColorMask(red, green, blue, alpha); }
+        }
+
+        if old.is_none_or(|old| old.polygon_offset != new.polygon_offset) {
+            match new.polygon_offset {
+                Some(offset) => {
+                    gl_call! { gl::Enable(gl::POLYGON_OFFSET_FILL); }
+                    gl_call! { gl::PolygonOffset(offset.factor, offset.units); }
+                }
+                None => {
+                    gl_call! { gl::Disable(gl::POLYGON_OFFSET_FILL); }
+                }
             }
         }
     }
@@ -0,0 +1,237 @@
+use utils::builder;
+
+use crate::types::GLenum;
+
+/// How the output of a fragment shader is combined with the value already in
+/// the framebuffer: `equation(source * source_colour, destination *
+/// destination_colour)`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Blend {
+    pub equation: BlendEquation,
+    pub source: BlendFactor,
+    pub destination: BlendFactor,
+    /// The alpha channel's factors, the same as the colour's unless set with
+    /// `separate_alpha`
+    pub alpha_source: BlendFactor,
+    pub alpha_destination: BlendFactor,
+}
+
+impl Blend {
+    /// Light accumulation, particles and glow, `src * a + dst`
+    pub const ADDITIVE: Self = Self::new(BlendFactor::SrcAlpha, BlendFactor::One);
+    /// Standard transparency, `src * a + dst * (1 - a)`
+    pub const ALPHA: Self = Self::new(BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha);
+    /// Darkening such as decals and tinted glass, `src * dst`
+    pub const MULTIPLY: Self = Self::new(BlendFactor::DstColour, BlendFactor::Zero);
+    /// Transparency where the colour has already been multiplied by alpha,
+    /// `src + dst * (1 - a)`
+    pub const PREMULTIPLIED: Self = Self::new(BlendFactor::One, BlendFactor::OneMinusSrcAlpha);
+    /// Weighted blended order-independent transparency, summing colours,
+    /// `src + dst`, while multiplying alphas, `dst * (1 - a)`
+    pub const WEIGHTED_BLENDED: Self = Self::new(BlendFactor::One, BlendFactor::One)
+        .separate_alpha(BlendFactor::Zero, BlendFactor::OneMinusSrcAlpha);
+
+    pub const fn new(source: BlendFactor, destination: BlendFactor) -> Self {
+        Self {
+            equation: BlendEquation::Add,
+            source,
+            destination,
+            alpha_source: source,
+            alpha_destination: destination,
+        }
+    }
+
+    pub const fn equation(self, equation: BlendEquation) -> Self {
+        Self { equation, ..self }
+    }
+
+    pub const fn separate_alpha(self, source: BlendFactor, destination: BlendFactor) -> Self {
+        Self {
+            alpha_source: source,
+            alpha_destination: destination,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BlendEquation {
+    #[default]
+    Add,
+    Subtract,
+    ReverseSubtract,
+    Min,
+    Max,
+}
+
+impl BlendEquation {
+    pub(crate) fn get_enum(self) -> GLenum {
+        match self {
+            Self::Add => gl::FUNC_ADD,
+            Self::Subtract => gl::FUNC_SUBTRACT,
+            Self::ReverseSubtract => gl::FUNC_REVERSE_SUBTRACT,
+            Self::Min => gl::MIN,
+            Self::Max => gl::MAX,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendFactor {
+    Zero,
+    One,
+    SrcColour,
+    OneMinusSrcColour,
+    DstColour,
+    OneMinusDstColour,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+}
+
+impl BlendFactor {
+    pub(crate) fn get_enum(self) -> GLenum {
+        match self {
+            Self::Zero => gl::ZERO,
+            Self::One => gl::ONE,
+            Self::SrcColour => gl::SRC_COLOR,
+            Self::OneMinusSrcColour => gl::ONE_MINUS_SRC_COLOR,
+            Self::DstColour => gl::DST_COLOR,
+            Self::OneMinusDstColour => gl::ONE_MINUS_DST_COLOR,
+            Self::SrcAlpha => gl::SRC_ALPHA,
+            Self::OneMinusSrcAlpha => gl::ONE_MINUS_SRC_ALPHA,
+            Self::DstAlpha => gl::DST_ALPHA,
+            Self::OneMinusDstAlpha => gl::ONE_MINUS_DST_ALPHA,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DepthFunc {
+    Never,
+    #[default]
+    Less,
+    Equal,
+    LessEqual,
+    Greater,
+    NotEqual,
+    GreaterEqual,
+    Always,
+}
+
+impl DepthFunc {
+    pub(crate) fn get_enum(self) -> GLenum {
+        match self {
+            Self::Never => gl::NEVER,
+            Self::Less => gl::LESS,
+            Self::Equal => gl::EQUAL,
+            Self::LessEqual => gl::LEQUAL,
+            Self::Greater => gl::GREATER,
+            Self::NotEqual => gl::NOTEQUAL,
+            Self::GreaterEqual => gl::GEQUAL,
+            Self::Always => gl::ALWAYS,
+        }
+    }
+}
+
+/// Which channels of the colour buffer(s) may be written to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColourMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColourMask {
+    pub const ALL: Self = Self {
+        red: true,
+        green: true,
+        blue: true,
+        alpha: true,
+    };
+    pub const NONE: Self = Self {
+        red: false,
+        green: false,
+        blue: false,
+        alpha: false,
+    };
+}
+
+impl Default for ColourMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// Offset applied to the depth of filled polygons,
+/// `factor * slope + units * smallest_resolvable_depth`. Pushing depth-only
+/// geometry away from the light removes shadow acne.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PolygonOffset {
+    pub factor: f32,
+    pub units: f32,
+}
+
+impl PolygonOffset {
+    pub const fn new(factor: f32, units: f32) -> Self {
+        Self { factor, units }
+    }
+}
+
+/// Fixed-function state which is applied when a `ShaderProgram` is used, and
+/// may be changed for a single draw group with
+/// `ActiveShaderProgram::render_state`. Only the differences from the current
+/// state are sent to OpenGL.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    /// `None` disables blending
+    pub blend: Option<Blend>,
+    pub depth_func: DepthFunc,
+    pub depth_write: bool,
+    pub colour_mask: ColourMask,
+    /// `None` disables polygon offset
+    pub polygon_offset: Option<PolygonOffset>,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            blend: Some(Blend::ALPHA),
+            depth_func: DepthFunc::Less,
+            depth_write: true,
+            colour_mask: ColourMask::ALL,
+            polygon_offset: None,
+        }
+    }
+}
+
+impl RenderState {
+    builder!(blend, opt_blend: Option<Blend>);
+
+    builder!(depth_func: DepthFunc);
+
+    builder!(depth_write: bool);
+
+    builder!(colour_mask: ColourMask);
+
+    builder!(polygon_offset, opt_polygon_offset: Option<PolygonOffset>);
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Blending and depth settings for geometry which is drawn after all
+    /// opaque geometry and must not hide what is behind it.
+    pub fn transparent() -> Self {
+        Self::default().depth_write(false)
+    }
+
+    /// State for drawing a skybox at the far plane behind the scene
+    pub fn skybox() -> Self {
+        Self::default()
+            .depth_write(false)
+            .depth_func(DepthFunc::LessEqual)
+    }
+}
@@ -19,10 +19,13 @@ pub trait Uniform {
         location: UniformLocation,
         shader_program: &dyn IsActiveShaderProgram,
     );
+
+    /// The value as a flat list of numbers, for frame traces
+    fn trace_values(&self) -> Vec<f64>;
 }
 
 macro_rules! define_uniform {
-    ($typ:ty => |$self:ident, $loc:ident, $shader:ident| $out:expr ) => {
+    ($typ:ty => |$self:ident, $loc:ident, $shader:ident| $out:expr, trace: |$trace_self:ident| $trace:expr ) => {
         impl Uniform for $typ {
             fn set_uniform_ref(&self, location: UniformLocation, shader_program: &dyn IsActiveShaderProgram) {
                 //let location = location.to_primitive();
@@ -33,26 +36,30 @@ macro_rules! define_uniform {
             fn set_uniform(self, location: UniformLocation, shader_program: &dyn IsActiveShaderProgram) {
                 self.set_uniform_ref(location, shader_program)
             }
+
+            fn trace_values(&self) -> Vec<f64> {
+                ( |$trace_self: &Self| $trace )(self)
+            }
         }
     };
 }
 
-define_uniform!(i32 => |value, location, _s| { gl_call!{ gl::Uniform1i(location.to_primitive(), *value) } } );
-define_uniform!(f32 => |value, location, _s| { gl_call!{ gl::Uniform1f(location.to_primitive(), *value) } } );
-define_uniform!([i32; 1] => |value, location, s| value[0].set_uniform_ref(location, s));
-define_uniform!([f32; 1] => |value, location, s| value[0].set_uniform_ref(location, s));
-define_uniform!([i32; 2] => |value, location, _s| gl_call! { gl::Uniform2i(location.to_primitive(), value[0], value[1]); });
-define_uniform!([f32; 2] => |value, location, _s| gl_call! { gl::Uniform2f(location.to_primitive(), value[0], value[1]); });
-define_uniform!([i32; 3] => |value, location, _s| gl_call! { gl::Uniform3i(location.to_primitive(), value[0], value[1], value[2]); });
-define_uniform!([f32; 3] => |value, location, _s| gl_call! { gl::Uniform3f(location.to_primitive(), value[0], value[1], value[2]); });
-define_uniform!([i32; 4] => |value, location, _s| gl_call! { gl::Uniform4i(location.to_primitive(), value[0], value[1], value[2], value[3]); });
-define_uniform!([f32; 4] => |value, location, _s| gl_call! { gl::Uniform4f(location.to_primitive(), value[0], value[1], value[2], value[3]); });
+define_uniform!(i32 => |value, location, _s| { gl_call!{ gl::Uniform1i(location.to_primitive(), *value) } }, trace: |value| vec![*value as f64] );
+define_uniform!(f32 => |value, location, _s| { gl_call!{ gl::Uniform1f(location.to_primitive(), *value) } }, trace: |value| vec![*value as f64] );
+define_uniform!([i32; 1] => |value, location, s| value[0].set_uniform_ref(location, s), trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([f32; 1] => |value, location, s| value[0].set_uniform_ref(location, s), trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([i32; 2] => |value, location, _s| gl_call! { gl::Uniform2i(location.to_primitive(), value[0], value[1]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([f32; 2] => |value, location, _s| gl_call! { gl::Uniform2f(location.to_primitive(), value[0], value[1]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([i32; 3] => |value, location, _s| gl_call! { gl::Uniform3i(location.to_primitive(), value[0], value[1], value[2]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([f32; 3] => |value, location, _s| gl_call! { gl::Uniform3f(location.to_primitive(), value[0], value[1], value[2]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([i32; 4] => |value, location, _s| gl_call! { gl::Uniform4i(location.to_primitive(), value[0], value[1], value[2], value[3]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
+define_uniform!([f32; 4] => |value, location, _s| gl_call! { gl::Uniform4f(location.to_primitive(), value[0], value[1], value[2], value[3]); }, trace: |value| value.iter().map(|&x| x as f64).collect());
 
 macro_rules! vectors {
     ($($num:literal),*) => {
         $(
-define_uniform!(Vector<$num> => |value, location, s| value.inner().set_uniform_ref(location, s));
-define_uniform!(UnitVector<$num> => |value, location, s| value.v().inner().set_uniform_ref(location, s));
+define_uniform!(Vector<$num> => |value, location, s| value.inner().set_uniform_ref(location, s), trace: |value| value.inner().trace_values());
+define_uniform!(UnitVector<$num> => |value, location, s| value.v().inner().set_uniform_ref(location, s), trace: |value| value.v().inner().trace_values());
         )*
     };
 }
@@ -61,7 +68,11 @@ vectors!(1,2,3,4);
 
 macro_rules! matrix {
     ($row:literal, $col:literal => $func:ident) => {
-define_uniform!(Matrix<$row,$col> => |value, location, _s| gl_call! { gl::$func(location.to_primitive(), 1, gl::FALSE, value.col_major().as_ptr()); });
+define_uniform!(Matrix<$row,$col> => |value, location, _s| gl_call! { gl::$func(location.to_primitive(), 1, gl::FALSE, value.col_major().as_ptr()); }, trace: |value| value.col_major().iter().map(|&x| x as f64).collect());
+define_uniform!(&[Matrix<$row,$col>] => |value, location, _s| {
+    let values: Vec<f32> = value.iter().flat_map(|matrix| matrix.col_major()).collect();
+    gl_call! { gl::$func(location.to_primitive(), value.len() as i32, gl::FALSE, values.as_ptr()); }
+}, trace: |value| value.iter().flat_map(|matrix| matrix.col_major()).map(|x| x as f64).collect());
     };
 }
 
@@ -75,5 +86,5 @@ matrix!(4,2 => UniformMatrix2x4fv);
 matrix!(4,3 => UniformMatrix3x4fv);
 matrix!(4,4 => UniformMatrix4fv);
 
-define_uniform!(ColourRGB => |value, location, s| value.as_ref().set_uniform_ref(location, s));
-define_uniform!(ColourRGBA => |value, location, s| value.as_ref().set_uniform_ref(location, s));
\ No newline at end of file
+define_uniform!(ColourRGB => |value, location, s| value.as_ref().set_uniform_ref(location, s), trace: |value| value.as_ref().trace_values());
+define_uniform!(ColourRGBA => |value, location, s| value.as_ref().set_uniform_ref(location, s), trace: |value| value.as_ref().trace_values());
\ No newline at end of file
@@ -1,10 +1,11 @@
+use std::borrow::Cow;
 use std::fs;
 use std::path::Path;
 
 use super::error::Error;
 use crate::error::Result;
-use crate::gl_call;
 use crate::types::{GLenum, ShaderId};
+use crate::{debug, gl_call};
 
 #[derive(Debug)]
 pub struct Shader {
@@ -13,17 +14,38 @@ pub struct Shader {
 }
 
 impl Shader {
-    pub fn new<P: AsRef<Path>>(shader_type: GLenum, source: P) -> Result<Self> {
+    /// `defines` are each declared by a `#define` under the `#version` line,
+    /// so that one source can be compiled into several variants, and
+    /// `prelude` is pasted after them for code shared between sources
+    pub fn new<P: AsRef<Path>>(
+        shader_type: GLenum,
+        source: P,
+        defines: &[String],
+        prelude: &str,
+    ) -> Result<Self> {
         let source = source.as_ref();
 
         let shader_source = fs::read_to_string(source).map_err(|_| Error::NoSourceFile {
             path: source.into(),
         })?;
 
-        Self::new_from_slice(shader_type, shader_source.as_bytes())
+        let shader = Self::new_from_slice(shader_type, shader_source.as_bytes(), defines, prelude)?;
+        debug::label(
+            gl::SHADER,
+            shader.id.to_primitive(),
+            &source.display().to_string(),
+        );
+
+        Ok(shader)
     }
 
-    pub(crate) fn new_from_slice(shader_type: GLenum, cstr: &[u8]) -> Result<Self> {
+    pub(crate) fn new_from_slice(
+        shader_type: GLenum,
+        cstr: &[u8],
+        defines: &[String],
+        prelude: &str,
+    ) -> Result<Self> {
+        let cstr = &*with_defines(cstr, defines, prelude);
         let shader_id = ShaderId::new(gl_call! { gl::CreateShader(shader_type) });
 
         let len = [cstr.len().try_into().map_err(|_| Error::SourceTooLong {
@@ -66,8 +88,86 @@ impl Shader {
     }
 }
 
+/// GLSL requires `#version` before anything else, so the defines and the
+/// prelude go after it when it is there
+fn with_defines<'a>(source: &'a [u8], defines: &[String], prelude: &str) -> Cow<'a, [u8]> {
+    if defines.is_empty() && prelude.is_empty() {
+        return Cow::Borrowed(source);
+    }
+
+    let split = if source.trim_ascii_start().starts_with(b"#version") {
+        source
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(source.len(), |newline| newline + 1)
+    } else {
+        0
+    };
+    let (version, rest) = source.split_at(split);
+
+    let mut out = version.to_vec();
+    if !out.ends_with(b"\n") && !out.is_empty() {
+        out.push(b'\n');
+    }
+    for define in defines {
+        out.extend_from_slice(format!("#define {define}\n").as_bytes());
+    }
+    out.extend_from_slice(prelude.as_bytes());
+    if !prelude.is_empty() && !prelude.ends_with('\n') {
+        out.push(b'\n');
+    }
+    out.extend_from_slice(rest);
+
+    Cow::Owned(out)
+}
+
 impl Drop for Shader {
     fn drop(&mut self) {
         gl_call! { gl::DeleteShader(self.id.to_primitive()) }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_with_defines() {
+        let defines = ["SKINNED".to_string()];
+
+        assert_eq!(
+            &*with_defines(b"#version 330 core\nvoid main() {}", &defines, ""),
+            b"#version 330 core\n#define SKINNED\nvoid main() {}"
+        );
+        assert_eq!(
+            &*with_defines(b"#version 330 core", &defines, ""),
+            b"#version 330 core\n#define SKINNED\n"
+        );
+        assert_eq!(
+            &*with_defines(b"void main() {}", &defines, ""),
+            b"#define SKINNED\nvoid main() {}"
+        );
+        assert_eq!(
+            &*with_defines(b"void main() {}", &[], ""),
+            b"void main() {}"
+        );
+    }
+
+    #[test]
+    fn test_with_prelude() {
+        let defines = ["SKINNED".to_string()];
+
+        assert_eq!(
+            &*with_defines(
+                b"#version 330 core\nvoid main() {}",
+                &defines,
+                "mat4 skin();"
+            ),
+            b"#version 330 core\n#define SKINNED\nmat4 skin();\nvoid main() {}"
+        );
+        assert_eq!(
+            &*with_defines(b"void main() {}", &[], "mat4 skin();\n"),
+            b"mat4 skin();\nvoid main() {}"
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when a texture is bound or deleted outside of `ShaderProgramContext`,
+/// for example while building one, so the texture units are no longer known.
+static TEXTURES_DIRTY: AtomicBool = AtomicBool::new(false);
+/// Set when a framebuffer is bound or deleted outside of
+/// `FramebufferContext`.
+static FRAMEBUFFER_DIRTY: AtomicBool = AtomicBool::new(false);
+/// Set when a shader program is deleted, as its id may be reused.
+static PROGRAM_DIRTY: AtomicBool = AtomicBool::new(false);
+/// Set when the depth or colour mask is changed outside of
+/// `ShaderProgramContext`, such as to clear a framebuffer.
+static RENDER_STATE_DIRTY: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn invalidate_textures() {
+    TEXTURES_DIRTY.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn invalidate_framebuffer() {
+    FRAMEBUFFER_DIRTY.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn invalidate_program() {
+    PROGRAM_DIRTY.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn invalidate_render_state() {
+    RENDER_STATE_DIRTY.store(true, Ordering::Relaxed);
+}
+
+pub(crate) fn take_textures_dirty() -> bool {
+    TEXTURES_DIRTY.swap(false, Ordering::Relaxed)
+}
+
+pub(crate) fn take_framebuffer_dirty() -> bool {
+    FRAMEBUFFER_DIRTY.swap(false, Ordering::Relaxed)
+}
+
+pub(crate) fn take_program_dirty() -> bool {
+    PROGRAM_DIRTY.swap(false, Ordering::Relaxed)
+}
+
+pub(crate) fn take_render_state_dirty() -> bool {
+    RENDER_STATE_DIRTY.swap(false, Ordering::Relaxed)
+}
+
+/// How many times a kind of OpenGL call was made, and how many times it was
+/// skipped because the state was already set
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CallCount {
+    pub issued: u64,
+    pub avoided: u64,
+}
+
+impl CallCount {
+    /// Record a call, returning whether it is `needed`
+    pub(crate) fn record(&mut self, needed: bool) -> bool {
+        if needed {
+            self.issued += 1;
+        } else {
+            self.avoided += 1;
+        }
+        needed
+    }
+}
+
+impl Display for CallCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} issued, {} avoided", self.issued, self.avoided)
+    }
+}
+
+/// Redundant state changes skipped by `ShaderProgramContext` and
+/// `FramebufferContext`, counted since they were last reset
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StateCacheStats {
+    pub program: CallCount,
+    pub texture: CallCount,
+    pub framebuffer: CallCount,
+    pub viewport: CallCount,
+}
+
+impl Display for StateCacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program binds:     {}", self.program)?;
+        writeln!(f, "texture binds:     {}", self.texture)?;
+        writeln!(f, "framebuffer binds: {}", self.framebuffer)?;
+        write!(f, "viewports:         {}", self.viewport)
+    }
+}
@@ -1,3 +1,4 @@
+use crate::debug;
 use crate::types::{TexDim, TexId};
 
 mod cubemap;
@@ -24,6 +25,11 @@ pub trait Texture {
     fn size(&self) -> (TexDim, TexDim);
 
     fn id(&self) -> &TexId;
+
+    /// Name the texture in debug messages and frame debuggers
+    fn set_label(&self, label: &str) {
+        debug::label(gl::TEXTURE, self.id().to_primitive(), label);
+    }
 }
 
 pub trait TextureHasBuilder: Texture {
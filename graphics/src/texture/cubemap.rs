@@ -1,9 +1,8 @@
-use std::sync::LazyLock;
-
 use builder::MissingData;
 use colour::ColourRGB;
 
 use super::{Texture, TextureHasBuilder};
+use crate::context_local::ContextLocal;
 use crate::gl_call;
 use crate::types::{TexDim, TexId, };
 
@@ -56,9 +55,9 @@ impl Texture for CubeMap {
     }
 
     fn dyn_blank() -> &'static dyn Texture {
-        static DEFAULT_CUBEMAP: LazyLock<CubeMap> = LazyLock::new(CubeMap::default);
+        static DEFAULT_CUBEMAP: ContextLocal<CubeMap> = ContextLocal::new(CubeMap::default);
 
-        &*DEFAULT_CUBEMAP
+        DEFAULT_CUBEMAP.get()
     }
 
     fn bind_to(&self, index: u32) {
@@ -13,7 +13,7 @@ use crate::framebuffer::attachments::CubeWithDepth;
 use crate::framebuffer::traits::Attachment;
 use crate::texture::{Error, Magnification, Minification, TexBuilder, TexBuilderCanBuild};
 use crate::types::{TexDim, TexId};
-use crate::{gl_call, types};
+use crate::{gl_call, state_cache, types};
 
 #[derive(Debug, Default)]
 pub struct MissingData;
@@ -139,6 +139,7 @@ fn make_tex_set_parameters<T>(build: &Builder<T>) -> TexId {
         let mut id = 0;
         gl_call! { gl::GenTextures(1, &raw mut id); }
         gl_call! { gl::BindTexture(gl::TEXTURE_CUBE_MAP, id); }
+        state_cache::invalidate_textures();
         TexId::new(id)
     };
 
@@ -301,5 +302,6 @@ impl Drop for CubeMap {
         gl_call! {
             gl::DeleteTextures(1, &raw const primitive);
         }
+        state_cache::invalidate_textures();
     }
 }
@@ -5,6 +5,7 @@ pub enum Error {
     BindTooHigh { maximum: usize, requested: usize },
     OpeningTexture { path: PathBuf },
     ParsingTextureImage { path: PathBuf },
+    PixelsDoNotFitSize { width: u32, height: u32, len: usize },
     NoTextureDataOrDims,
     CubeMapNotAllSidesDefined,
 }
@@ -1,11 +1,11 @@
 use std::fmt::Debug;
-use std::sync::LazyLock;
 
 use colour::ColourRGBA;
 
 use super::{Texture, TextureHasBuilder};
-use crate::gl_call;
+use crate::context_local::ContextLocal;
 use crate::types::{TexDim, TexId, };
+use crate::{gl_call, state_cache};
 
 mod builder;
 use builder::MissingData;
@@ -39,6 +39,7 @@ impl Drop for FlatTexture {
     fn drop(&mut self) {
         let primitive = self.id.to_primitive();
         gl_call! { gl::DeleteTextures(1, &raw const primitive); }
+        state_cache::invalidate_textures();
     }
 }
 
@@ -58,10 +59,10 @@ impl TextureHasBuilder for FlatTexture {
 
 impl Texture for FlatTexture {
     fn dyn_blank() -> &'static dyn Texture {
-        static DEFAULT_FLAT_TEXTURE: LazyLock<FlatTexture> =
-            LazyLock::new(FlatTexture::default);
+        static DEFAULT_FLAT_TEXTURE: ContextLocal<FlatTexture> =
+            ContextLocal::new(FlatTexture::default);
 
-        &*DEFAULT_FLAT_TEXTURE
+        DEFAULT_FLAT_TEXTURE.get()
     }
 
     fn id(&self) -> &TexId {
@@ -11,9 +11,9 @@ use super::FlatTexture;
 use crate::error::Result;
 use crate::framebuffer::attachments::{WithDepth, WithStencil, WithoutExtra};
 use crate::framebuffer::traits::Attachment;
-use crate::gl_call;
 use crate::texture::{Magnification, Minification, TexBuilder, TexBuilderCanBuild, WrapType};
 use crate::types::{self, GLint, GLsizei, TexDim, TexId, };
+use crate::{debug, gl_call, state_cache};
 
 #[derive(Default, Debug)]
 pub struct Builder<T> {
@@ -21,6 +21,7 @@ pub struct Builder<T> {
     wrap_s_t: WrapType,
     mag_filter: Magnification,
     min_filter: Minification,
+    label: Option<String>,
 }
 
 #[derive(Debug, Default)]
@@ -46,24 +47,81 @@ macro_rules! add_image {
     ($image_type:ident => $fn_name:ident, $into_func:ident) => {
         pub fn $fn_name<P: AsRef<Path> + Debug>(self, path: P) -> Result<Builder<ImageType>> {
             let image = ImageType::$image_type(
-                image::ImageReader::open(&path)
-                    .map_err(|_| super::super::error::Error::OpeningTexture {
-                        path: path.as_ref().into(),
-                    })?
-                    .decode()
-                    .map_err(|_| super::super::error::Error::ParsingTextureImage {
-                        path: path.as_ref().into(),
-                    })?
+                decode(path.as_ref())?
                     .flipv()
                     .$into_func()
                     .into_flat_samples(),
             );
+            let label = self
+                .label
+                .clone()
+                .or_else(|| Some(path.as_ref().display().to_string()));
+
+            Ok(Builder {
+                image,
+                label,
+                ..self
+            })
+        }
+    };
+}
+
+macro_rules! add_pixels {
+    ($image_type:ident => $fn_name:ident) => {
+        /// 8-bit RGBA pixels already in memory, such as an image embedded in
+        /// a model file. Unlike a loaded image it is not flipped, so the first
+        /// row sits at texture coordinate v = 0.
+        pub fn $fn_name(
+            self,
+            (width, height): (u32, u32),
+            pixels: Vec<u8>,
+        ) -> Result<Builder<ImageType>> {
+            let len = pixels.len();
+            let image = ImageType::$image_type(
+                image::RgbaImage::from_raw(width, height, pixels)
+                    .ok_or(super::super::error::Error::PixelsDoNotFitSize { width, height, len })?
+                    .into_flat_samples(),
+            );
 
             Ok(Builder { image, ..self })
         }
     };
 }
 
+fn decode(path: &Path) -> Result<image::DynamicImage> {
+    let image = image::ImageReader::open(path)
+        .map_err(|_| super::super::error::Error::OpeningTexture { path: path.into() })?
+        .decode()
+        .map_err(|_| super::super::error::Error::ParsingTextureImage { path: path.into() })?;
+
+    Ok(image)
+}
+
+/// Tangent-space normals as 8-bit RGBA, from heights in rows starting at
+/// v = 0. `strength` is the slope of a full step in height over one texel.
+fn normals_from_heights(
+    heights: &[f32],
+    (width, height): (usize, usize),
+    strength: f32,
+) -> Vec<u8> {
+    let at = |x: usize, y: usize| heights[y.min(height - 1) * width + x.min(width - 1)];
+    let mut out = Vec::with_capacity(width * height * 4);
+
+    for y in 0..height {
+        for x in 0..width {
+            // Central differences, one-sided at the edges
+            let du = (at(x + 1, y) - at(x.saturating_sub(1), y)) * strength;
+            let dv = (at(x, y + 1) - at(x, y.saturating_sub(1))) * strength;
+            let length = (du * du + dv * dv + 1.0).sqrt();
+            let encode = |value: f32| ((value / length * 0.5 + 0.5) * 255.0).round() as u8;
+
+            out.extend([encode(-du), encode(-dv), encode(1.0), u8::MAX]);
+        }
+    }
+
+    out
+}
+
 impl Builder<MissingData> {
     new!();
 }
@@ -86,12 +144,20 @@ impl<T> Builder<T> {
 
     builder!(mag_filter: Magnification);
 
+    // Name the texture in debug messages and frame debuggers, defaults to
+    // the image's path
+    builder!(label: Option<String>);
+
     add_image!(Srgba => srgba_image, into_rgba8);
 
     add_image!(Rgba => rgba_image, into_rgba8);
 
     add_image!(RgbaFloat => rgba_float_image, into_rgba32f);
 
+    add_pixels!(Srgba => srgba_pixels);
+
+    add_pixels!(Rgba => rgba_pixels);
+
     pub fn withoutextra_attachment(
         self,
         size: (TexDim, TexDim),
@@ -135,6 +201,43 @@ impl<T> Builder<T> {
 
         Builder { image, ..self }
     }
+
+    /// A normal map made from a greyscale height map. A coloured image is
+    /// taken to be a normal map already, as the bump maps of OBJ files often
+    /// are, and kept as it is.
+    pub fn normal_image_from_height<P: AsRef<Path> + Debug>(
+        self,
+        path: P,
+        strength: f32,
+    ) -> Result<Builder<ImageType>> {
+        let mut image = decode(path.as_ref())?.flipv().into_rgba8();
+
+        let is_greyscale = image
+            .pixels()
+            .all(|&image::Rgba([r, g, b, _])| r.abs_diff(g) <= 2 && g.abs_diff(b) <= 2);
+        if is_greyscale {
+            let (width, height) = image.dimensions();
+            let heights: Vec<f32> = image
+                .pixels()
+                .map(|pixel| f32::from(pixel[0]) / 255.0)
+                .collect();
+            let normals =
+                normals_from_heights(&heights, (width as usize, height as usize), strength);
+            image =
+                image::RgbaImage::from_raw(width, height, normals).expect("one normal per height");
+        }
+
+        let label = self
+            .label
+            .clone()
+            .or_else(|| Some(path.as_ref().display().to_string()));
+
+        Ok(Builder {
+            image: ImageType::Rgba(image.into_flat_samples()),
+            label,
+            ..self
+        })
+    }
 }
 
 fn gen_tex_set_parameters<T>(builder: &Builder<T>) -> TexId {
@@ -146,6 +249,11 @@ fn gen_tex_set_parameters<T>(builder: &Builder<T>) -> TexId {
     };
 
     gl_call! { gl::BindTexture(gl::TEXTURE_2D, id.to_primitive()); }
+    state_cache::invalidate_textures();
+
+    if let Some(label) = &builder.label {
+        debug::label(gl::TEXTURE, id.to_primitive(), label);
+    }
 
     let wrap_s_t = builder.wrap_s_t.get_enum();
 
@@ -298,3 +406,19 @@ impl<X: Attachment> Builder<FramebufferAttachment<X>> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_normals_from_heights() {
+        let flat = normals_from_heights(&[0.5; 4], (2, 2), 1.0);
+        assert_eq!(flat[..4], [128, 128, 255, 255]);
+
+        // Rising with u, so the normal leans towards -u
+        let ramp = normals_from_heights(&[0.0, 1.0, 0.0, 1.0], (2, 2), 1.0);
+        assert!(ramp[0] < 128);
+        assert_eq!(ramp[1], 128);
+    }
+}
@@ -0,0 +1,349 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// One recorded step of a frame
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceEvent {
+    /// A `Draw` returned by `GlobalState::poll` started
+    Draw {
+        name: String,
+    },
+    UseProgram {
+        program: u32,
+        label: Option<String>,
+        /// The program was already current, so no call was made
+        cached: bool,
+    },
+    Uniform {
+        name: String,
+        values: Vec<f64>,
+    },
+    BindTexture {
+        unit: usize,
+        texture: u32,
+        cached: bool,
+    },
+    BindFramebuffer {
+        framebuffer: u32,
+        cached: bool,
+    },
+    Viewport {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        cached: bool,
+    },
+    DrawElements {
+        vertex_array: u32,
+        count: i32,
+    },
+}
+
+impl Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cached = |cached: &bool| if *cached { " (cached)" } else { "" };
+
+        match self {
+            Self::Draw { name } => write!(f, "draw {name}"),
+            Self::UseProgram {
+                program,
+                label,
+                cached: is_cached,
+            } => write!(
+                f,
+                "  use program {program} {}{}",
+                label.as_deref().unwrap_or("<unlabelled>"),
+                cached(is_cached),
+            ),
+            Self::Uniform { name, values } => write!(f, "  uniform {name} = {values:?}"),
+            Self::BindTexture {
+                unit,
+                texture,
+                cached: is_cached,
+            } => write!(
+                f,
+                "  bind texture {texture} to unit {unit}{}",
+                cached(is_cached)
+            ),
+            Self::BindFramebuffer {
+                framebuffer,
+                cached: is_cached,
+            } => write!(f, "  bind framebuffer {framebuffer}{}", cached(is_cached)),
+            Self::Viewport {
+                x,
+                y,
+                width,
+                height,
+                cached: is_cached,
+            } => write!(
+                f,
+                "  viewport ({x}, {y}) {width}x{height}{}",
+                cached(is_cached),
+            ),
+            Self::DrawElements {
+                vertex_array,
+                count,
+            } => write!(f, "  draw vertex array {vertex_array}, {count} elements"),
+        }
+    }
+}
+
+/// Everything sent to OpenGL through `graphics` during one frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub frame: u64,
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|error| Error::Other(format!("reading {}: {error}", path.display())))?;
+
+        serde_json::from_str(&text)
+            .map_err(|error| Error::Other(format!("parsing {}: {error}", path.display())))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = serde_json::to_string_pretty(self)
+            .map_err(|error| Error::Other(format!("serialising trace: {error}")))?;
+
+        fs::write(path, text)
+            .map_err(|error| Error::Other(format!("writing {}: {error}", path.display())))
+    }
+
+    /// Split the trace at each `TraceEvent::Draw`. Events before the first
+    /// `Draw` form a section of their own.
+    pub fn sections(&self) -> Vec<&[TraceEvent]> {
+        let mut sections = Vec::new();
+        let mut start = 0;
+        for (index, event) in self.events.iter().enumerate() {
+            if index != 0 && matches!(event, TraceEvent::Draw { .. }) {
+                sections.push(&self.events[start..index]);
+                start = index;
+            }
+        }
+        if start < self.events.len() {
+            sections.push(&self.events[start..]);
+        }
+
+        sections
+    }
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "frame {}", self.frame)?;
+        for event in &self.events {
+            write!(f, "\n{event}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A line of the difference between two traces
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change<'a> {
+    Same(&'a TraceEvent),
+    Removed(&'a TraceEvent),
+    Added(&'a TraceEvent),
+}
+
+impl Display for Change<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Same(event) => write!(f, " {event}"),
+            Self::Removed(event) => write!(f, "-{event}"),
+            Self::Added(event) => write!(f, "+{event}"),
+        }
+    }
+}
+
+/// Compare two traces `Draw` by `Draw`, using the longest common subsequence
+/// of events inside each one.
+pub fn diff<'a>(old: &'a Trace, new: &'a Trace) -> Vec<Change<'a>> {
+    let old_sections = old.sections();
+    let new_sections = new.sections();
+
+    let mut changes = Vec::new();
+    for index in 0..old_sections.len().max(new_sections.len()) {
+        let old_section = old_sections.get(index).copied().unwrap_or_default();
+        let new_section = new_sections.get(index).copied().unwrap_or_default();
+        changes.extend(diff_events(old_section, new_section));
+    }
+
+    changes
+}
+
+fn diff_events<'a>(old: &'a [TraceEvent], new: &'a [TraceEvent]) -> Vec<Change<'a>> {
+    // lengths[i][j] is the longest common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0_u32; (old.len() + 1) * width];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut changes = Vec::with_capacity(old.len().max(new.len()));
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            changes.push(Change::Same(&old[i]));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            changes.push(Change::Removed(&old[i]));
+            i += 1;
+        } else {
+            changes.push(Change::Added(&new[j]));
+            j += 1;
+        }
+    }
+    changes.extend(old[i..].iter().map(Change::Removed));
+    changes.extend(new[j..].iter().map(Change::Added));
+
+    changes
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    frame: u64,
+    requested: Option<PathBuf>,
+    recording: Option<(PathBuf, Trace)>,
+}
+
+thread_local! {
+    static RECORDER: RefCell<Recorder> = RefCell::default();
+}
+
+/// Record everything drawn in the next frame and write it to `path` as JSON.
+/// View or compare traces with the `trace` binary.
+pub fn capture_next_frame<P: Into<PathBuf>>(path: P) {
+    RECORDER.with_borrow_mut(|recorder| recorder.requested = Some(path.into()));
+}
+
+pub fn is_recording() -> bool {
+    RECORDER.with_borrow(|recorder| recorder.recording.is_some())
+}
+
+/// Add an event to the trace, `event` is only evaluated while recording
+pub(crate) fn record<F: FnOnce() -> TraceEvent>(event: F) {
+    RECORDER.with_borrow_mut(|recorder| {
+        if let Some((_, trace)) = &mut recorder.recording {
+            trace.events.push(event());
+        }
+    });
+}
+
+pub(crate) fn begin_frame() {
+    RECORDER.with_borrow_mut(|recorder| {
+        let frame = recorder.frame;
+        recorder.frame += 1;
+        recorder.recording = recorder.requested.take().map(|path| {
+            (
+                path,
+                Trace {
+                    frame,
+                    events: Vec::new(),
+                },
+            )
+        });
+    });
+}
+
+pub(crate) fn end_frame() {
+    let Some((path, trace)) = RECORDER.with_borrow_mut(|recorder| recorder.recording.take()) else {
+        return;
+    };
+
+    match trace.save(&path) {
+        Ok(()) => log::info!("wrote trace of frame {} to {}", trace.frame, path.display()),
+        Err(error) => log::error!("could not write trace: {error:?}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn draw(name: &str) -> TraceEvent {
+        TraceEvent::Draw { name: name.into() }
+    }
+
+    fn elements(vertex_array: u32) -> TraceEvent {
+        TraceEvent::DrawElements {
+            vertex_array,
+            count: 36,
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Trace {
+            frame: 0,
+            events: vec![
+                draw("cubic"),
+                elements(1),
+                elements(2),
+                draw("quad"),
+                elements(3),
+            ],
+        };
+        let new = Trace {
+            frame: 1,
+            events: vec![
+                draw("cubic"),
+                elements(2),
+                elements(4),
+                draw("quad"),
+                elements(3),
+            ],
+        };
+
+        let changes = diff(&old, &new);
+
+        assert_eq!(
+            changes,
+            vec![
+                Change::Same(&old.events[0]),
+                Change::Removed(&old.events[1]),
+                Change::Same(&old.events[2]),
+                Change::Added(&new.events[2]),
+                Change::Same(&old.events[3]),
+                Change::Same(&old.events[4]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let trace = Trace {
+            frame: 3,
+            events: vec![
+                draw("shadow"),
+                TraceEvent::Uniform {
+                    name: "exposure".into(),
+                    values: vec![1.5],
+                },
+            ],
+        };
+
+        let text = serde_json::to_string(&trace).unwrap();
+        assert_eq!(serde_json::from_str::<Trace>(&text).unwrap(), trace);
+    }
+}
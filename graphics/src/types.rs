@@ -3,6 +3,7 @@ use std::io::Write;
 use std::num::NonZero;
 
 pub(crate) use gl::types::*;
+use serde::{Deserialize, Serialize};
 
 macro_rules! opaque {
     ($name:ident : $type:ident $(, $derives: ident)* $(,)?) => {
@@ -70,7 +71,7 @@ macro_rules! nz_opaque {
     };
 }
 
-nz_opaque!(TexDim: GLsizei, Clone, Copy);
+nz_opaque!(TexDim: GLsizei, Clone, Copy, Serialize, Deserialize);
 nz_opaque!(TexId: GLuint);
 opaque!(FrameBufferId: GLuint);
 nz_opaque!(VertexArrayId: GLuint);
@@ -87,6 +88,7 @@ nz_opaque!(VertexBufferId: GLuint);
 opaque!(UniformLocation: GLint);
 nz_opaque!(ShaderId: GLuint);
 nz_opaque!(ShaderProgramId: GLuint);
+nz_opaque!(QueryId: GLuint);
 
 #[derive(Clone, Copy)]
 pub struct GLError(pub(crate) gl::types::GLenum);
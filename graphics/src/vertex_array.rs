@@ -9,10 +9,11 @@ use crate::element_array_buffer::ElementArrayBuffer;
 use crate::error::Result;
 use crate::framebuffer::traits::FramebufferInternals;
 use crate::shader_program::ActiveShaderProgram;
+use crate::trace::{self, TraceEvent};
 use crate::types::{VertexArrayId};
 use crate::vertex::Vertex;
 use crate::vertex_buffer::VertexBuffer;
-use crate::{gl_call, types};
+use crate::{debug, gl_call, types};
 
 // mod cubic_builder;
 // mod quad_builder;
@@ -122,6 +123,15 @@ where
         }
     }
 
+    /// Name the vertex array and its buffers in debug messages and frame
+    /// debuggers
+    pub fn set_label(&self, label: &str) {
+        debug::label(gl::VERTEX_ARRAY, self.id.to_primitive(), label);
+        self._vertex_buffer.set_label(&format!("{label} vertices"));
+        self.element_array_buffer
+            .set_label(&format!("{label} indices"));
+    }
+
     pub(crate) fn bind(&self) {
         gl_call! {
             gl::BindVertexArray(self.id.to_primitive());
@@ -136,7 +146,7 @@ where
 
     pub fn draw<M, const OUT: usize, D: FramebufferInternals<OUT>>(
         &self,
-        active_shader_program: &ActiveShaderProgram<'_, '_, '_, M, D::Tex, OUT>,
+        active_shader_program: &mut ActiveShaderProgram<'_, '_, '_, M, D::Tex, OUT>,
         _: &mut ActiveFramebuffer<'_, '_, OUT, D>,
     ) -> Result<()> {
         self.bind();
@@ -144,6 +154,10 @@ where
 
         active_shader_program.validate()?;
 
+        trace::record(|| TraceEvent::DrawElements {
+            vertex_array: self.id.to_primitive(),
+            count: self.element_array_buffer.len().to_primitive(),
+        });
         gl_call! {
             gl::DrawElements(
                 gl::TRIANGLES,
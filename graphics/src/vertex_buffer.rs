@@ -3,7 +3,7 @@ use std::mem;
 
 use crate::types::{VertexBufferId};
 use crate::vertex::Vertex;
-use crate::{gl_call, types};
+use crate::{debug, gl_call, types};
 
 #[derive(Debug)] // No Clone
 pub struct VertexBuffer<V: Vertex> {
@@ -38,6 +38,10 @@ impl<V: Vertex> VertexBuffer<V> {
         }
     }
 
+    pub(crate) fn set_label(&self, label: &str) {
+        debug::label(gl::BUFFER, self.id.to_primitive(), label);
+    }
+
     pub(crate) fn bind(&self) {
         gl_call! {
             gl::BindBuffer(gl::ARRAY_BUFFER, self.id.to_primitive());